@@ -0,0 +1,12 @@
+#version 420 core
+
+in vec2 uv;
+in vec4 color;
+
+out vec4 out_color;
+
+layout(binding = 0) uniform sampler2D atlas;
+
+void main() {
+    out_color = vec4(color.rgb, color.a * texture(atlas, uv).a);
+}
\ No newline at end of file
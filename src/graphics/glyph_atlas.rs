@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+
+/// Glyphs are rasterized at this many horizontal and vertical offsets within a pixel
+pub const SUBPIXEL_STEPS: u8 = 4;
+
+/// Space left between glyphs, so that neighbours never bleed into each other
+const PADDING: u32 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct GlyphKey {
+    pub font_id: usize,
+    pub glyph_id: u16,
+
+    /// Bits of the horizontal and vertical scale
+    pub scale: (u32, u32),
+
+    /// Offset within a pixel in steps of `1 / SUBPIXEL_STEPS`
+    pub subpixel: (u8, u8),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// Where a rasterized glyph is stored, in pixels
+pub struct AtlasEntry {
+    pub page: usize,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+
+    /// Offset of the bitmap from the pixel the glyph is positioned in
+    pub offset: (i32, i32),
+}
+
+#[derive(Debug)]
+/// A row of glyphs of similar height, which is evicted as a whole
+struct Shelf {
+    y: u32,
+    height: u32,
+
+    /// Where the next glyph is placed
+    x: u32,
+    keys: Vec<GlyphKey>,
+    last_used: u64,
+}
+
+#[derive(Debug)]
+struct Page {
+    shelves: Vec<Shelf>,
+}
+
+#[derive(Debug)]
+/// Packs rasterized glyphs into square pages on shelves, evicting the shelf
+/// that was least recently used when all pages are full.
+/// Only the placement is tracked here, the pixels are uploaded by the renderer.
+pub struct GlyphAtlas {
+    page_size: u32,
+    max_pages: usize,
+    pages: Vec<Page>,
+    entries: HashMap<GlyphKey, (AtlasEntry, usize)>,
+    frame: u64,
+}
+
+impl GlyphAtlas {
+    pub fn new(page_size: u32, max_pages: usize) -> GlyphAtlas {
+        GlyphAtlas {
+            page_size,
+            max_pages: max_pages.max(1),
+            pages: Vec::new(),
+            entries: HashMap::new(),
+            frame: 0,
+        }
+    }
+
+    pub fn page_size(&self) -> u32 {
+        self.page_size
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Glyphs used in the current frame are never evicted
+    pub fn begin_frame(&mut self) {
+        self.frame += 1;
+    }
+
+    /// Returns the entry of a glyph that is already rasterized, marking it as used
+    pub fn get(&mut self, key: &GlyphKey) -> Option<AtlasEntry> {
+        let (entry, shelf) = *self.entries.get(key)?;
+        self.pages[entry.page].shelves[shelf].last_used = self.frame;
+
+        Some(entry)
+    }
+
+    /// Finds space for a glyph of the given size, returning None if it's larger than a page
+    /// or everything is in use by the current frame
+    pub fn insert(
+        &mut self,
+        key: GlyphKey,
+        width: u32,
+        height: u32,
+        offset: (i32, i32),
+    ) -> Option<AtlasEntry> {
+        let padded = (width + PADDING, height + PADDING);
+
+        if padded.0 > self.page_size || padded.1 > self.page_size {
+            return None;
+        }
+
+        let (page, shelf) = self.allocate(padded)?;
+        let target = &mut self.pages[page].shelves[shelf];
+
+        let entry = AtlasEntry {
+            page,
+            x: target.x,
+            y: target.y,
+            width,
+            height,
+            offset,
+        };
+
+        target.x += padded.0;
+        target.keys.push(key);
+        target.last_used = self.frame;
+
+        self.entries.insert(key, (entry, shelf));
+        Some(entry)
+    }
+
+    fn allocate(&mut self, (width, height): (u32, u32)) -> Option<(usize, usize)> {
+        let page_size = self.page_size;
+
+        // The shelf that wastes the least height, on any page
+        let best = self
+            .pages
+            .iter()
+            .enumerate()
+            .flat_map(|(p, page)| {
+                page.shelves
+                    .iter()
+                    .enumerate()
+                    .map(move |(s, shelf)| (p, s, shelf))
+            })
+            .filter(|(_, _, shelf)| shelf.height >= height && shelf.x + width <= page_size)
+            .min_by_key(|(_, _, shelf)| shelf.height - height)
+            .map(|(p, s, _)| (p, s));
+
+        // Shelves that are much taller than the glyph are only used over opening a new one
+        if let Some((p, s)) = best {
+            if self.pages[p].shelves[s].height <= height * 3 / 2 {
+                return Some((p, s));
+            }
+        }
+
+        for (p, page) in self.pages.iter_mut().enumerate() {
+            let bottom = page.shelves.last().map_or(0, |s| s.y + s.height);
+
+            if bottom + height <= page_size {
+                page.shelves.push(Shelf::new(bottom, height));
+                return Some((p, page.shelves.len() - 1));
+            }
+        }
+
+        if self.pages.len() < self.max_pages {
+            self.pages.push(Page {
+                shelves: vec![Shelf::new(0, height)],
+            });
+
+            return Some((self.pages.len() - 1, 0));
+        }
+
+        best.or_else(|| self.evict(height))
+    }
+
+    /// Empties the least recently used shelf that the glyph fits on
+    fn evict(&mut self, height: u32) -> Option<(usize, usize)> {
+        let frame = self.frame;
+
+        let (p, s) = self
+            .pages
+            .iter()
+            .enumerate()
+            .flat_map(|(p, page)| {
+                page.shelves
+                    .iter()
+                    .enumerate()
+                    .map(move |(s, shelf)| (p, s, shelf))
+            })
+            .filter(|(_, _, shelf)| shelf.height >= height && shelf.last_used < frame)
+            .min_by_key(|(_, _, shelf)| (shelf.last_used, shelf.height))
+            .map(|(p, s, _)| (p, s))?;
+
+        let shelf = &mut self.pages[p].shelves[s];
+
+        for key in shelf.keys.drain(..) {
+            self.entries.remove(&key);
+        }
+
+        shelf.x = 0;
+        Some((p, s))
+    }
+}
+
+impl Shelf {
+    fn new(y: u32, height: u32) -> Shelf {
+        Shelf {
+            y,
+            height,
+            x: 0,
+            keys: Vec::new(),
+            last_used: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn key(glyph_id: u16) -> GlyphKey {
+        GlyphKey {
+            font_id: 0,
+            glyph_id,
+            scale: (16, 16),
+            subpixel: (0, 0),
+        }
+    }
+
+    #[test]
+    fn packs_glyphs_on_shelves() {
+        let mut atlas = GlyphAtlas::new(64, 1);
+
+        let a = atlas.insert(key(1), 10, 12, (0, -12)).unwrap();
+        let b = atlas.insert(key(2), 10, 11, (0, -11)).unwrap();
+        let c = atlas.insert(key(3), 10, 30, (0, -30)).unwrap();
+
+        // Glyphs of similar height share a shelf
+        assert_eq!((a.x, a.y), (0, 0));
+        assert_eq!((b.x, b.y), (11, 0));
+        assert_eq!((c.x, c.y), (0, 13));
+
+        assert_eq!(atlas.get(&key(2)), Some(b));
+        assert_eq!(atlas.insert(key(4), 64, 10, (0, 0)), None);
+    }
+
+    #[test]
+    fn evicts_least_recently_used_shelf() {
+        let mut atlas = GlyphAtlas::new(32, 1);
+
+        atlas.begin_frame();
+        atlas.insert(key(1), 30, 15, (0, 0)).unwrap();
+        atlas.insert(key(2), 30, 15, (0, 0)).unwrap();
+
+        atlas.begin_frame();
+        atlas.get(&key(1)).unwrap();
+
+        let c = atlas.insert(key(3), 20, 10, (0, 0)).unwrap();
+
+        assert_eq!(c.y, 16);
+        assert_eq!(atlas.get(&key(2)), None);
+        assert!(atlas.get(&key(1)).is_some());
+
+        // Everything left is in use by this frame
+        assert_eq!(atlas.insert(key(4), 20, 10, (0, 0)), None);
+    }
+}
@@ -1,5 +1,7 @@
+pub mod glyph_atlas;
 pub mod rectangle_shape;
 pub mod shader;
+pub mod text_renderer;
 
 use gl::types::GLuint;
 use shader::Uniform;
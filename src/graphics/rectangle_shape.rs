@@ -41,6 +41,39 @@ impl RGBATexture {
 
         RGBATexture { handle }
     }
+
+    /// Allocates a texture whose contents are uploaded later
+    pub fn empty(width: usize, height: usize) -> Self {
+        let mut handle: GLuint = 0;
+
+        unsafe {
+            gl::CreateTextures(gl::TEXTURE_2D, 1, &mut handle);
+            gl::TextureStorage2D(handle, 1, gl::RGBA8, width as i32, height as i32);
+
+            gl::TextureParameteri(handle, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TextureParameteri(handle, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            gl::TextureParameteri(handle, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+            gl::TextureParameteri(handle, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+        }
+
+        RGBATexture { handle }
+    }
+
+    pub fn update(&self, x: usize, y: usize, width: usize, height: usize, data: *const u8) {
+        unsafe {
+            gl::TextureSubImage2D(
+                self.handle,
+                0,
+                x as i32,
+                y as i32,
+                width as i32,
+                height as i32,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                data as *const GLvoid,
+            );
+        }
+    }
 }
 
 impl Drop for RGBATexture {
@@ -0,0 +1,222 @@
+extern crate gl;
+
+use std::mem;
+use std::ptr;
+
+use gl::types::*;
+use rusttype::point;
+
+use crate::{Float, LaidOutGlyph, Position, TextLayout};
+
+use super::glyph_atlas::{AtlasEntry, GlyphAtlas, GlyphKey, SUBPIXEL_STEPS};
+use super::rectangle_shape::{Float4, RGBATexture, Vertex};
+
+/// Position, texture coordinates and color
+type GlyphVertex = (Vertex, Vertex, Float4);
+
+/// Draws text from glyphs cached in an atlas, with one draw call per atlas page
+pub struct TextRenderer {
+    atlas: GlyphAtlas,
+    textures: Vec<RGBATexture>,
+
+    /// Vertices queued for the current frame, by page
+    batches: Vec<Vec<GlyphVertex>>,
+    vbo: GLuint,
+    vao: GLuint,
+}
+
+impl Drop for TextRenderer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &mut self.vbo);
+            gl::DeleteVertexArrays(1, &mut self.vao);
+        }
+    }
+}
+
+impl TextRenderer {
+    pub fn new(page_size: u32, max_pages: usize) -> TextRenderer {
+        let mut renderer = TextRenderer {
+            atlas: GlyphAtlas::new(page_size, max_pages),
+            textures: Vec::new(),
+            batches: Vec::new(),
+            vbo: 0,
+            vao: 0,
+        };
+        renderer.init_opengl_members();
+
+        renderer
+    }
+
+    fn init_opengl_members(&mut self) {
+        let stride = mem::size_of::<GlyphVertex>() as i32;
+        let float = mem::size_of::<GLfloat>();
+
+        unsafe {
+            gl::CreateVertexArrays(1, &mut self.vao);
+            gl::CreateBuffers(1, &mut self.vbo);
+
+            gl::BindVertexArray(self.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, stride, ptr::null());
+            gl::VertexAttribPointer(1, 2, gl::FLOAT, gl::FALSE, stride, (float * 2) as *const _);
+            gl::VertexAttribPointer(2, 4, gl::FLOAT, gl::FALSE, stride, (float * 4) as *const _);
+
+            gl::EnableVertexAttribArray(0);
+            gl::EnableVertexAttribArray(1);
+            gl::EnableVertexAttribArray(2);
+
+            gl::BindVertexArray(0);
+        }
+    }
+
+    pub fn atlas(&self) -> &GlyphAtlas {
+        &self.atlas
+    }
+
+    pub fn begin_frame(&mut self) {
+        self.atlas.begin_frame();
+
+        for batch in &mut self.batches {
+            batch.clear();
+        }
+    }
+
+    /// Queues the glyphs of a laid out text, positioned relative to the given origin
+    pub fn queue(&mut self, layout: &TextLayout, origin: Position) {
+        for run in &layout.runs {
+            for glyph in &layout.glyphs[run.glyphs.clone()] {
+                self.queue_glyph(glyph, origin, run.color);
+            }
+        }
+    }
+
+    fn queue_glyph(&mut self, glyph: &LaidOutGlyph, origin: Position, color: Float4) {
+        let position = glyph.glyph.position();
+        let steps = SUBPIXEL_STEPS as Float;
+
+        // Positions are snapped to the closest subpixel offset the glyph is rasterized at
+        let snap = |value: Float| {
+            let pixel = value.floor();
+            let step = ((value - pixel) * steps).round();
+
+            if step >= steps {
+                (pixel + 1., 0)
+            } else {
+                (pixel, step as u8)
+            }
+        };
+
+        let (x, subpixel_x) = snap(position.x + origin.x);
+        let (y, subpixel_y) = snap(position.y + origin.y);
+
+        let scale = glyph.glyph.scale();
+        let key = GlyphKey {
+            font_id: glyph.font_id,
+            glyph_id: glyph.glyph.id().0,
+            scale: (scale.x.to_bits(), scale.y.to_bits()),
+            subpixel: (subpixel_x, subpixel_y),
+        };
+
+        let entry = match self.atlas.get(&key) {
+            Some(entry) => entry,
+            None => match self.rasterize(glyph, key) {
+                Some(entry) => entry,
+                None => return,
+            },
+        };
+
+        let page_size = self.atlas.page_size() as Float;
+        let left = x + entry.offset.0 as Float;
+        let top = y + entry.offset.1 as Float;
+        let (width, height) = (entry.width as Float, entry.height as Float);
+
+        let corner = |u: Float, v: Float| -> GlyphVertex {
+            (
+                (left + u * width, top + v * height),
+                (
+                    (entry.x as Float + u * width) / page_size,
+                    (entry.y as Float + v * height) / page_size,
+                ),
+                color,
+            )
+        };
+
+        if self.batches.len() <= entry.page {
+            self.batches.resize_with(entry.page + 1, Vec::new);
+        }
+
+        self.batches[entry.page].extend_from_slice(&[
+            corner(0., 0.),
+            corner(1., 0.),
+            corner(0., 1.),
+            corner(0., 1.),
+            corner(1., 0.),
+            corner(1., 1.),
+        ]);
+    }
+
+    /// Rasterizes a glyph into the atlas, returning None for glyphs without pixels
+    fn rasterize(&mut self, glyph: &LaidOutGlyph, key: GlyphKey) -> Option<AtlasEntry> {
+        let steps = SUBPIXEL_STEPS as Float;
+        let positioned = glyph.glyph.unpositioned().clone().positioned(point(
+            key.subpixel.0 as Float / steps,
+            key.subpixel.1 as Float / steps,
+        ));
+
+        let bounds = positioned.pixel_bounding_box()?;
+        let (width, height) = (bounds.width() as u32, bounds.height() as u32);
+        let entry = self
+            .atlas
+            .insert(key, width, height, (bounds.min.x, bounds.min.y))?;
+
+        let mut buffer: Vec<(u8, u8, u8, u8)> =
+            vec![(255, 255, 255, 0); width as usize * height as usize];
+
+        positioned.draw(|x, y, v| {
+            buffer[x as usize + y as usize * width as usize].3 = (v * 255.) as u8;
+        });
+
+        let page_size = self.atlas.page_size() as usize;
+
+        while self.textures.len() <= entry.page {
+            self.textures.push(RGBATexture::empty(page_size, page_size));
+        }
+
+        self.textures[entry.page].update(
+            entry.x as usize,
+            entry.y as usize,
+            width as usize,
+            height as usize,
+            buffer.as_ptr() as *const u8,
+        );
+
+        Some(entry)
+    }
+
+    /// Draws everything queued this frame, expects the text shader to be bound
+    pub fn draw(&self) {
+        unsafe {
+            gl::BindVertexArray(self.vao);
+
+            for (page, vertices) in self.batches.iter().enumerate() {
+                if vertices.is_empty() {
+                    continue;
+                }
+
+                gl::NamedBufferData(
+                    self.vbo,
+                    (mem::size_of::<GlyphVertex>() * vertices.len()) as isize,
+                    vertices.as_ptr() as *const GLvoid,
+                    gl::STREAM_DRAW,
+                );
+
+                gl::BindTextureUnit(0, self.textures[page].handle);
+                gl::DrawArrays(gl::TRIANGLES, 0, vertices.len() as i32);
+            }
+
+            gl::BindVertexArray(0);
+        }
+    }
+}
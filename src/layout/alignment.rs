@@ -25,7 +25,7 @@ impl AlignUnit {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 pub struct Alignment {
     horizontal: AlignUnit,
     vertical: AlignUnit,
@@ -0,0 +1,64 @@
+use super::{Float, LayoutContext};
+
+#[derive(Clone, Debug, PartialEq)]
+/// An arithmetic expression of sizes, such as `calc(100% - 2 * 1.5rem - 16px)`,
+/// which is parsed once and evaluated every time the element is calculated
+pub enum CalcExpression {
+    /// A number without a unit, which sizes can be multiplied and divided by
+    Number(Float),
+    Fixed(Float),
+
+    /// Hundredths of the outer bounds, as written
+    Percent(Float),
+
+    /// Multiples of the root font size of the layout context. Elements don't have a font size
+    /// of their own to inherit, so only `rem` is supported and not `em`.
+    Rem(Float),
+
+    /// The size of the content
+    Content,
+
+    /// The size of the available bounds
+    Available,
+
+    Add(Box<CalcExpression>, Box<CalcExpression>),
+    Subtract(Box<CalcExpression>, Box<CalcExpression>),
+    Multiply(Box<CalcExpression>, Box<CalcExpression>),
+    Divide(Box<CalcExpression>, Box<CalcExpression>),
+}
+
+impl CalcExpression {
+    pub fn evaluate(
+        &self,
+        content: Float,
+        bound: Float,
+        outer: Float,
+        context: &LayoutContext,
+    ) -> Float {
+        let evaluate = |e: &CalcExpression| e.evaluate(content, bound, outer, context);
+
+        match self {
+            CalcExpression::Number(n) | CalcExpression::Fixed(n) => *n,
+            CalcExpression::Percent(p) => outer * p / 100.,
+            CalcExpression::Rem(rem) => rem * context.root_font_size(),
+            CalcExpression::Content => content,
+            CalcExpression::Available => bound,
+            CalcExpression::Add(a, b) => evaluate(a) + evaluate(b),
+            CalcExpression::Subtract(a, b) => evaluate(a) - evaluate(b),
+            CalcExpression::Multiply(a, b) => evaluate(a) * evaluate(b),
+            CalcExpression::Divide(a, b) => evaluate(a) / evaluate(b),
+        }
+    }
+
+    /// Whether the value depends on the bounds, so it can't be known from the content alone
+    pub fn depends_on_bounds(&self) -> bool {
+        match self {
+            CalcExpression::Percent(_) | CalcExpression::Available => true,
+            CalcExpression::Add(a, b)
+            | CalcExpression::Subtract(a, b)
+            | CalcExpression::Multiply(a, b)
+            | CalcExpression::Divide(a, b) => a.depends_on_bounds() || b.depends_on_bounds(),
+            _ => false,
+        }
+    }
+}
@@ -1,5 +1,7 @@
 use std::fmt;
 
+use crate::text::TextLayout;
+
 use super::{dimension::Dimensions, rect::Rect};
 
 const INDENTATION: &str = "  ";
@@ -8,6 +10,9 @@ const INDENTATION: &str = "  ";
 pub struct CalculatedElement {
     pub rect: Rect,
     pub children: Vec<CalculatedElement>,
+
+    /// The laid out lines and glyphs, if the element is a text
+    pub text: Option<TextLayout>,
 }
 
 impl CalculatedElement {
@@ -15,6 +20,7 @@ impl CalculatedElement {
         CalculatedElement {
             rect: Rect::from_dimensions(dimensions),
             children: Vec::new(),
+            text: None,
         }
     }
 
@@ -22,6 +28,7 @@ impl CalculatedElement {
         CalculatedElement {
             rect,
             children: Vec::new(),
+            text: None,
         }
     }
 
@@ -1,4 +1,4 @@
-use super::{dimension::Dimensions, ConstraintUnit};
+use super::{context::LayoutContext, dimension::Dimensions, CalcExpression, ConstraintUnit};
 
 #[derive(Clone, Copy, Debug)]
 pub enum Direction {
@@ -33,41 +33,96 @@ impl Direction {
 
 pub type Float = f32;
 
-#[derive(PartialEq, Debug, Clone, Copy)]
+#[derive(PartialEq, Debug, Clone)]
 pub enum SizingUnit {
     Percent(Float, ConstraintUnit, ConstraintUnit),
     Fixed(Float),
     Collapse(ConstraintUnit),
     Stretch(ConstraintUnit),
+
+    /// Shares the space left over by the other children of a directional element
+    /// with the other fractions, in proportion to the given fraction
+    Fraction(Float),
+
+    /// Evaluated against the content, the available bounds and the outer bounds,
+    /// never resulting in a negative size
+    Calc(Box<CalcExpression>),
 }
 
 impl SizingUnit {
-    pub fn calculate(&self, content: Float, bound: Float, outer: Float) -> Float {
+    pub fn calculate(
+        &self,
+        content: Float,
+        bound: Float,
+        outer: Float,
+        context: &LayoutContext,
+    ) -> Float {
+        // There's nothing to stretch to in unbounded space, such as when a height is measured
+        // for a width, so units that depend on the bounds take on the content instead
+        let unbounded = match self {
+            SizingUnit::Stretch(_) | SizingUnit::Fraction(_) => bound.is_infinite(),
+            SizingUnit::Percent(_, _, _) => outer.is_infinite(),
+            SizingUnit::Calc(e) => {
+                e.depends_on_bounds() && (bound.is_infinite() || outer.is_infinite())
+            }
+            SizingUnit::Fixed(_) | SizingUnit::Collapse(_) => false,
+        };
+
+        if unbounded {
+            return self.intrinsic(content, context);
+        }
+
         match self {
             SizingUnit::Fixed(a) => *a,
             SizingUnit::Stretch(c) => {
-                let max = c.calculate(bound);
+                let max = c.calculate(bound, context);
 
                 bound.min(max).max(0.)
             }
-            SizingUnit::Percent(p, _min, _max) => {
-                //let min = min.calculate(bound * p);
-                //let max = max.calculate(bound * p);
-
-                outer * p
+            SizingUnit::Percent(p, min, max) => {
+                let value = outer * p;
+
+                // A missing constraint would otherwise be the value and undo the other one
+                let constraint = |unit: &ConstraintUnit, none: Float| match unit {
+                    ConstraintUnit::None => none,
+                    unit => unit.calculate(value, context),
+                };
+
+                value
+                    .min(constraint(max, Float::INFINITY))
+                    .max(constraint(min, 0.))
             }
+            // The share is given as the bound by the parent
+            SizingUnit::Fraction(_) => bound.max(0.),
+            SizingUnit::Calc(e) => e.evaluate(content, bound, outer, context).max(0.),
             SizingUnit::Collapse(c) => {
-                let min = c.calculate(content);
+                let min = c.calculate(content, context);
                 content.max(min)
             }
         }
     }
 
+    /// The size taken on when only the content is known, as when measuring intrinsic sizes
+    pub fn intrinsic(&self, content: Float, context: &LayoutContext) -> Float {
+        match self {
+            SizingUnit::Fixed(a) => *a,
+            SizingUnit::Collapse(c) => content.max(c.calculate(content, context)),
+            SizingUnit::Calc(e) if !e.depends_on_bounds() => {
+                e.evaluate(content, 0., 0., context).max(0.)
+            }
+            SizingUnit::Stretch(_)
+            | SizingUnit::Percent(_, _, _)
+            | SizingUnit::Fraction(_)
+            | SizingUnit::Calc(_) => content,
+        }
+    }
+
     pub fn index(&self) -> u32 {
         match self {
             SizingUnit::Fixed(_) => 0,
             SizingUnit::Collapse(c) => 1 + c.index(),
-            SizingUnit::Percent(_, _, _) => 5,
+            SizingUnit::Percent(_, _, _) | SizingUnit::Calc(_) => 5,
+            SizingUnit::Fraction(_) => 6,
             SizingUnit::Stretch(c) => 6 + c.index(),
         }
     }
@@ -80,7 +135,7 @@ impl SizingUnit {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Sizing {
     pub width: SizingUnit,
     pub height: SizingUnit,
@@ -91,29 +146,66 @@ impl Sizing {
         Self { width, height }
     }
 
+    /// Fixed to the given dimensions
+    pub fn from_dimensions(dimensions: Dimensions) -> Self {
+        Self::new(
+            SizingUnit::Fixed(dimensions.width),
+            SizingUnit::Fixed(dimensions.height),
+        )
+    }
+
+    /// Takes up all of the available space
+    pub fn stretch() -> Self {
+        Self::new(
+            SizingUnit::Stretch(ConstraintUnit::None),
+            SizingUnit::Stretch(ConstraintUnit::None),
+        )
+    }
+
+    /// Shrinks to fit the content
+    pub fn collapse() -> Self {
+        Self::new(
+            SizingUnit::Collapse(ConstraintUnit::None),
+            SizingUnit::Collapse(ConstraintUnit::None),
+        )
+    }
+
     pub fn calculate(
         &self,
         content: Dimensions,
         bounds: Dimensions,
         outer: Dimensions,
+        context: &LayoutContext,
     ) -> Dimensions {
         Dimensions {
             width: self
                 .width
-                .calculate(content.width, bounds.width, outer.width),
+                .calculate(content.width, bounds.width, outer.width, context),
             height: self
                 .height
-                .calculate(content.height, bounds.height, outer.height),
+                .calculate(content.height, bounds.height, outer.height, context),
         }
     }
 
     // This is used when the content is unknown, such as with a childless element
-    pub fn calculate_without_content(&self, bounds: Dimensions, outer: Dimensions) -> Dimensions {
-        self.calculate(Dimensions::new(0.0, 0.0), bounds, outer)
+    pub fn calculate_without_content(
+        &self,
+        bounds: Dimensions,
+        outer: Dimensions,
+        context: &LayoutContext,
+    ) -> Dimensions {
+        self.calculate(Dimensions::new(0.0, 0.0), bounds, outer, context)
+    }
+
+    pub fn intrinsic(&self, content: Dimensions, context: &LayoutContext) -> Dimensions {
+        Dimensions::new(
+            self.width.intrinsic(content.width, context),
+            self.height.intrinsic(content.height, context),
+        )
     }
 
-    pub fn as_tuple(&self) -> (SizingUnit, SizingUnit) {
-        (self.width, self.height)
+    pub fn as_tuple(&self) -> (&SizingUnit, &SizingUnit) {
+        (&self.width, &self.height)
     }
 
     pub fn fixed(&self) -> Result<Dimensions, &str> {
@@ -1,17 +1,22 @@
-use super::Float;
+use super::{CalcExpression, Float, LayoutContext};
 
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Debug, Clone)]
 pub enum ConstraintUnit {
+    /// A fraction of the constrained value, so `0.5` is half of it
     Percent(Float),
     Fixed(Float),
+
+    /// Percentages, the content and the available size are all the constrained value
+    Calc(Box<CalcExpression>),
     None,
 }
 
 impl ConstraintUnit {
-    pub fn calculate(&self, value: Float) -> Float {
+    pub fn calculate(&self, value: Float, context: &LayoutContext) -> Float {
         match self {
             ConstraintUnit::Percent(p) => value * p,
             ConstraintUnit::Fixed(f) => *f,
+            ConstraintUnit::Calc(e) => e.evaluate(value, value, value, context),
             ConstraintUnit::None => value,
         }
     }
@@ -19,7 +24,7 @@ impl ConstraintUnit {
     pub fn index(&self) -> u32 {
         match self {
             ConstraintUnit::Percent(_) => 1,
-            ConstraintUnit::Fixed(_) => 2,
+            ConstraintUnit::Fixed(_) | ConstraintUnit::Calc(_) => 2,
             ConstraintUnit::None => 3,
         }
     }
@@ -0,0 +1,80 @@
+use super::{common::Float, dimension::Dimensions};
+
+#[derive(Clone, Copy, Debug)]
+/// What an element is laid out in, which is passed from the root element down to its children
+pub struct LayoutContext {
+    viewport: Dimensions,
+    container: Dimensions,
+    font_size: Float,
+
+    /// Whether the parent decided the size of the element, which then fills its available bounds
+    solved: bool,
+}
+
+impl LayoutContext {
+    /// The context of a root element in a window of the given size, which is also its container
+    pub fn new(viewport: Dimensions) -> LayoutContext {
+        LayoutContext {
+            viewport,
+            container: viewport,
+            font_size: 16.,
+            solved: false,
+        }
+    }
+
+    /// Sets the font size that `rem` is relative to (by default 16)
+    pub fn font_size(mut self, size: Float) -> Self {
+        self.font_size = size;
+        self
+    }
+
+    /// The size of the window, which viewport breakpoints are matched against
+    pub fn viewport(&self) -> Dimensions {
+        self.viewport
+    }
+
+    /// The space inside the padding of the parent, which container breakpoints are matched against
+    pub fn container(&self) -> Dimensions {
+        self.container
+    }
+
+    /// The font size that `rem` is relative to
+    pub fn root_font_size(&self) -> Float {
+        self.font_size
+    }
+
+    /// Whether the size of the element was decided by its parent, such as by solving relationships
+    pub fn is_solved(&self) -> bool {
+        self.solved
+    }
+
+    /// The context of the children of an element, with the space inside its padding
+    pub fn nested(&self, container: Dimensions) -> LayoutContext {
+        LayoutContext {
+            container,
+            solved: false,
+            ..*self
+        }
+    }
+
+    /// The context of a child whose size was decided by its parent, which it takes on
+    /// in place of its own sizing
+    pub fn solved(&self) -> LayoutContext {
+        LayoutContext {
+            solved: true,
+            ..*self
+        }
+    }
+
+    /// The context of the children while an element is measured, where its size isn't known
+    pub fn unbounded(&self) -> LayoutContext {
+        self.nested(Dimensions::new(Float::INFINITY, Float::INFINITY))
+    }
+}
+
+impl Default for LayoutContext {
+    /// An unbounded window, such as when sizes are evaluated outside of a layout
+    fn default() -> Self {
+        LayoutContext::new(Dimensions::new(Float::INFINITY, Float::INFINITY))
+    }
+}
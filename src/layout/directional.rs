@@ -1,6 +1,8 @@
+use rayon::prelude::*;
+
 use super::{
-    alignment::*, calculated::CalculatedElement, common::*, dimension::Dimensions,
-    element::Element, rect::Rect,
+    alignment::*, calculated::CalculatedElement, common::*, context::LayoutContext,
+    dimension::Dimensions, element::Element, rect::Rect,
 };
 
 #[derive(Debug)]
@@ -11,11 +13,56 @@ pub struct Directional {
 
     /// Spacing between children (by default 0)
     spacing: Float,
+
+    /// Whether independent children are laid out on a thread pool (by default false)
+    parallel: bool,
 }
 
 impl Directional {
     pub fn new(direction: Direction, spacing: Float) -> Directional {
-        Directional { direction, spacing }
+        Directional {
+            direction,
+            spacing,
+            parallel: false,
+        }
+    }
+
+    pub fn parallel(mut self, parallel: bool) -> Directional {
+        self.parallel = parallel;
+        self
+    }
+
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    pub fn spacing(&self) -> Float {
+        self.spacing
+    }
+
+    pub(crate) fn set_spacing(&mut self, spacing: Float) {
+        self.spacing = spacing;
+    }
+
+    pub fn is_parallel(&self) -> bool {
+        self.parallel
+    }
+
+    /// Maps every child, in parallel if enabled. The result is always in child order.
+    fn map_children<T, F>(&self, children: &Vec<Element>, f: F) -> Vec<T>
+    where
+        T: Send,
+        F: Fn(usize, &Element) -> T + Send + Sync,
+    {
+        if self.parallel && children.len() > 1 {
+            children
+                .par_iter()
+                .enumerate()
+                .map(|(i, c)| f(i, c))
+                .collect()
+        } else {
+            children.iter().enumerate().map(|(i, c)| f(i, c)).collect()
+        }
     }
 
     fn calculate_position_offsets(
@@ -23,6 +70,7 @@ impl Directional {
         accumulations: &Vec<Float>,
         children: &Vec<Element>,
         inner_bounds: &Rect,
+        nested: &LayoutContext,
     ) -> (f32, f32) {
         let (width, height) = inner_bounds.dimensions.as_tuple();
         let primary = self.direction.primary(width, height);
@@ -35,7 +83,7 @@ impl Directional {
             let child = &children[i];
             let accumulation = *&accumulations[i];
 
-            let (vertical, horizontal) = child.alignment().as_tuple();
+            let (vertical, horizontal) = child.alignment_in(nested).as_tuple();
             let unit = self.direction.primary(vertical, horizontal);
 
             let accumulator = match unit {
@@ -66,6 +114,7 @@ impl Directional {
         primary_accumulations: &Vec<Float>,
         secondary_accumulations: &Vec<Float>,
         inner_bounds: &Rect,
+        nested: &LayoutContext,
     ) -> Vec<(Float, Float)> {
         let children = element.children();
         let (offset_x, offset_y) = inner_bounds.position.as_tuple();
@@ -76,7 +125,7 @@ impl Directional {
         };
 
         let (middle_offset, end_offset) =
-            self.calculate_position_offsets(primary_accumulations, children, inner_bounds);
+            self.calculate_position_offsets(primary_accumulations, children, inner_bounds, nested);
 
         let mut start_offset = 0.;
         let mut middle_offset = middle_offset;
@@ -94,7 +143,7 @@ impl Directional {
                 let child = &children[i];
                 let accumulation = *&primary_accumulations[i];
 
-                let (vertical, horizontal) = child.alignment().as_tuple();
+                let (vertical, horizontal) = child.alignment_in(nested).as_tuple();
                 let (primary, secondary) = self.direction.swap(vertical, horizontal);
 
                 let primary_offset = match primary {
@@ -113,8 +162,13 @@ impl Directional {
             .collect()
     }
 
-    fn calculate_inner_bounds(&self, element: &Element, box_bounds: &Rect) -> Rect {
-        let (top, bottom, left, right) = element.padding().as_tuple();
+    fn calculate_inner_bounds(
+        &self,
+        element: &Element,
+        box_bounds: &Rect,
+        context: &LayoutContext,
+    ) -> Rect {
+        let (top, bottom, left, right) = element.padding_in(context).as_tuple();
 
         let (mut width, mut height) = box_bounds.dimensions.as_tuple();
         let (mut x, mut y) = box_bounds.position.as_tuple();
@@ -128,14 +182,14 @@ impl Directional {
         Rect::new(width, height, x, y)
     }
 
-    fn sort_primary_indices(&self, element: &Element) -> Vec<usize> {
+    fn sort_primary_indices(&self, element: &Element, nested: &LayoutContext) -> Vec<usize> {
         let children = element.children();
         let mut indices: Vec<_> = (0..children.len()).collect();
 
         indices.sort_by_key(|i| {
             let child = &children[*i];
 
-            let (width, height) = child.sizing().as_tuple();
+            let (width, height) = child.sizing_in(nested).as_tuple();
             let sizing = self.direction.primary(width, height);
 
             sizing.index()
@@ -150,6 +204,7 @@ impl Directional {
         sorted_indices: &Vec<usize>,
         inner_bounds: &Rect,
         children: &Vec<Element>,
+        nested: &LayoutContext,
     ) -> (Vec<f32>, Vec<f32>, Float, Float) {
         let mut primary_intrinsic: Float = 0.;
         let mut primary_accumulation: Vec<Float> = vec![0.; children.len()];
@@ -163,11 +218,43 @@ impl Directional {
         // Occupy spacing between children
         let available_primary = available_primary - (children.len() - 1) as Float * self.spacing;
 
-        fn calculate_intrinsic(child: &Element, inner_bounds: Rect) -> (Float, Float) {
+        fn calculate_intrinsic(
+            child: &Element,
+            inner_bounds: Rect,
+            nested: &LayoutContext,
+        ) -> (Float, Float) {
+            let (mut width, mut height, x, y) = inner_bounds.as_tuple();
+            let sizing = child.sizing_in(nested);
+
+            let collapses = (
+                matches!(sizing.width, SizingUnit::Collapse(_)),
+                matches!(sizing.height, SizingUnit::Collapse(_)),
+            );
+
+            // Collapsing axes are limited to the max-content size, so content
+            // such as text is not laid out against the full inner bounds.
+            // With less space they shrink down to the min-content size, and overflow past that.
+            if collapses.0 || collapses.1 {
+                let max = child.max_content(nested);
+                let shrinks =
+                    (collapses.0 && width < max.width) || (collapses.1 && height < max.height);
+                let min = match shrinks {
+                    true => child.min_content(nested),
+                    false => max,
+                };
+
+                if collapses.0 {
+                    width = width.max(min.width).min(max.width);
+                }
+
+                if collapses.1 {
+                    height = height.max(min.height).min(max.height);
+                }
+            }
+
             child
-                // Inner bounds is used for both designated and outer, because
-                // designated space is not known at this time
-                .calculate(inner_bounds.clone(), inner_bounds)
+                // Inner bounds is used for outer, because designated space is not known at this time
+                .calculate_in(Rect::new(width, height, x, y), inner_bounds, nested)
                 .rect
                 .dimensions
                 .as_tuple()
@@ -179,6 +266,7 @@ impl Directional {
             available_primary: Float,
             available_secondary: Float,
             direction: Direction,
+            nested: &LayoutContext,
         ) -> (Float, Float) {
             let available = available_primary - primary_intrinsic;
             let (width, height) = direction.swap(available, available_secondary);
@@ -186,15 +274,41 @@ impl Directional {
             let available = Rect::new(width, height, 0.0, 0.0);
 
             child
-                .calculate(available.clone(), available)
+                .calculate_in(available.clone(), available, nested)
                 .rect
                 .dimensions
                 .as_tuple()
         }
 
+        // Children that don't stretch don't depend on each other,
+        // so they can be measured up front (and in parallel)
+        let intrinsics = self.map_children(children, |_, child| {
+            let (width, height) = child.sizing_in(nested).as_tuple();
+
+            match self.direction.primary(width, height) {
+                SizingUnit::Stretch(_) | SizingUnit::Fraction(_) => None,
+                _ => Some(calculate_intrinsic(child, inner_bounds.clone(), nested)),
+            }
+        });
+
+        let fractions: Float = children
+            .iter()
+            .filter_map(|child| {
+                let (width, height) = child.sizing_in(nested).as_tuple();
+
+                match self.direction.primary(width, height) {
+                    SizingUnit::Fraction(f) => Some(f),
+                    _ => None,
+                }
+            })
+            .sum();
+
+        // What's left after the children that don't stretch, shared between the fractions
+        let mut fraction_space = None;
+
         for index in sorted_indices {
             let child = &children[*index];
-            let child_sizing = child.sizing();
+            let child_sizing = child.sizing_in(nested);
 
             // Get the primary (directional) unit here
             let (primary_unit, secondary_unit) = self
@@ -202,16 +316,31 @@ impl Directional {
                 .swap(&child_sizing.width, &child_sizing.height);
 
             let (calculated_width, calculated_height) = match primary_unit {
-                SizingUnit::Fixed(_) | SizingUnit::Collapse(_) | SizingUnit::Percent(_, _, _) => {
-                    calculate_intrinsic(child, inner_bounds.clone())
-                }
+                SizingUnit::Fixed(_)
+                | SizingUnit::Collapse(_)
+                | SizingUnit::Percent(_, _, _)
+                | SizingUnit::Calc(_) => intrinsics[*index].unwrap(),
                 SizingUnit::Stretch(_) => calculate_stretch(
                     child,
                     primary_intrinsic,
                     available_primary,
                     available_secondary,
                     self.direction,
+                    nested,
                 ),
+                SizingUnit::Fraction(f) => {
+                    let space =
+                        *fraction_space.get_or_insert(available_primary - primary_intrinsic);
+
+                    calculate_stretch(
+                        child,
+                        0.,
+                        (space * f / fractions).max(0.),
+                        available_secondary,
+                        self.direction,
+                        nested,
+                    )
+                }
             };
 
             let (primary, secondary) = self.direction.swap(calculated_width, calculated_height);
@@ -224,7 +353,7 @@ impl Directional {
             // Accumulate intrinsic only for collapse/fixed,
             // So stretching secondaries can stretch based on that
             match secondary_unit {
-                SizingUnit::Stretch(_) => {}
+                SizingUnit::Stretch(_) | SizingUnit::Fraction(_) => {}
                 _ => secondary_intrinsic = secondary_intrinsic.max(secondary),
             }
         }
@@ -244,6 +373,7 @@ impl Directional {
         outer_bounds: &Rect,
         primary_intrinsic: Float,
         secondary_intrinsic: Float,
+        context: &LayoutContext,
     ) -> Rect {
         let inner = {
             // Add the missing spacing to the intrinsic
@@ -251,15 +381,18 @@ impl Directional {
                 primary_intrinsic + (element.children().len() - 1) as Float * self.spacing;
 
             let (width, height) = self.direction.swap(primary_intrinsic, secondary_intrinsic);
-            let (top, bottom, left, right) = element.padding().as_tuple();
+            let (top, bottom, left, right) = element.padding_in(context).as_tuple();
 
             Dimensions::new(width + left + right, height + top + bottom)
         };
 
         Rect::from_dimensions_and_position(
-            element
-                .sizing()
-                .calculate(inner, available_bounds.dimensions, outer_bounds.dimensions),
+            element.sizing_in(context).calculate(
+                inner,
+                available_bounds.dimensions,
+                outer_bounds.dimensions,
+                context,
+            ),
             available_bounds.position,
         )
     }
@@ -269,19 +402,47 @@ impl Directional {
         element: &Element,
         designated_bounds: Rect,
         outer_bounds: Rect,
+        context: &LayoutContext,
     ) -> CalculatedElement {
-        let box_bounds =
-            self.calculate_box_bounds(element, &designated_bounds, &outer_bounds, 0., 0.);
+        let nested = element.nested_context(&designated_bounds, &outer_bounds, context);
+
+        // Collapsing axes take on the designated bounds until the content is known,
+        // so children aren't measured against empty bounds
+        let (designated_primary, designated_secondary) = {
+            let (width, height) = designated_bounds.dimensions.as_tuple();
+            let (top, bottom, left, right) = element.padding_in(context).as_tuple();
+            let spacing = (element.children().len() - 1) as Float * self.spacing;
+
+            let (primary, secondary) = self
+                .direction
+                .swap(width - left - right, height - top - bottom);
+
+            (primary - spacing, secondary)
+        };
+
+        let box_bounds = self.calculate_box_bounds(
+            element,
+            &designated_bounds,
+            &outer_bounds,
+            designated_primary,
+            designated_secondary,
+            context,
+        );
 
-        let inner_bounds = self.calculate_inner_bounds(element, &box_bounds);
-        let sorted_indices = self.sort_primary_indices(element);
+        let inner_bounds = self.calculate_inner_bounds(element, &box_bounds, context);
+        let sorted_indices = self.sort_primary_indices(element, &nested);
 
         let (
             primary_accumulations,
             secondary_accumulations,
             primary_intrinsic,
             secondary_intrinsic,
-        ) = self.calculate_accumulation(&sorted_indices, &inner_bounds, element.children());
+        ) = self.calculate_accumulation(
+            &sorted_indices,
+            &inner_bounds,
+            element.children(),
+            &nested,
+        );
 
         // Calculate the new box and inner bounds so future calculations are correct
         let box_bounds = self.calculate_box_bounds(
@@ -290,38 +451,37 @@ impl Directional {
             &outer_bounds,
             primary_intrinsic,
             secondary_intrinsic,
+            context,
         );
 
-        let inner_bounds = self.calculate_inner_bounds(element, &box_bounds);
+        let inner_bounds = self.calculate_inner_bounds(element, &box_bounds, context);
 
         let positions = self.calculate_positions(
             &element,
             &primary_accumulations,
             &secondary_accumulations,
             &inner_bounds,
+            &nested,
         );
 
-        let children = element.children();
-        let mut calculated_children: Vec<Option<CalculatedElement>> = vec![None; children.len()];
-
-        for i in 0..children.len() {
-            let child = &children[i];
-
+        let calculated_children = self.map_children(element.children(), |i, child| {
             let primary = &primary_accumulations[i];
             let secondary = &secondary_accumulations[i];
 
             let (outer_width, outer_height) = self.direction.swap(primary, secondary);
             let (x, y) = &positions[i];
 
-            calculated_children[i] = Some(child.calculate(
+            child.calculate_in(
                 Rect::new(*outer_width, *outer_height, *x, *y),
                 inner_bounds.clone(),
-            ));
-        }
+                &nested,
+            )
+        });
 
         CalculatedElement {
-            children: calculated_children.into_iter().flatten().collect(),
+            children: calculated_children,
             rect: box_bounds,
+            text: None,
         }
     }
 
@@ -330,33 +490,88 @@ impl Directional {
         element: &Element,
         available_bounds: Rect,
         outer_bounds: Rect,
+        context: &LayoutContext,
     ) -> CalculatedElement {
-        let calculated = element
-            .sizing()
-            .calculate_without_content(available_bounds.dimensions, outer_bounds.dimensions);
+        let calculated = element.sizing_in(context).calculate_without_content(
+            available_bounds.dimensions,
+            outer_bounds.dimensions,
+            context,
+        );
 
         let rect = Rect::from_dimensions_and_position(calculated, available_bounds.position);
 
         CalculatedElement::from_rect(rect)
     }
 
+    fn calculate_content(
+        &self,
+        element: &Element,
+        context: &LayoutContext,
+        measure: impl Fn(&Element) -> Dimensions,
+    ) -> Dimensions {
+        let children = element.children();
+
+        let spacing = children.len().saturating_sub(1) as Float * self.spacing;
+        let (primary, secondary) =
+            children
+                .iter()
+                .fold((spacing, 0.), |(p, s): (Float, Float), child| {
+                    let (width, height) = measure(child).as_tuple();
+                    let (primary, secondary) = self.direction.swap(width, height);
+
+                    (p + primary, s.max(secondary))
+                });
+
+        let (width, height) = self.direction.swap(primary, secondary);
+        let (top, bottom, left, right) = element.padding_in(context).as_tuple();
+
+        let content = if !children.is_empty() {
+            Dimensions::new(width + left + right, height + top + bottom)
+        } else {
+            Dimensions::new(0., 0.)
+        };
+
+        element.sizing_in(context).intrinsic(content, context)
+    }
+
+    pub fn min_content(&self, element: &Element, context: &LayoutContext) -> Dimensions {
+        let nested = context.unbounded();
+
+        self.calculate_content(element, context, |child| child.min_content(&nested))
+    }
+
+    pub fn max_content(&self, element: &Element, context: &LayoutContext) -> Dimensions {
+        let nested = context.unbounded();
+
+        self.calculate_content(element, context, |child| child.max_content(&nested))
+    }
+
     pub fn calculate(
         &self,
         element: &Element,
         available_bounds: Rect,
         outer_bounds: Rect,
+        context: &LayoutContext,
     ) -> CalculatedElement {
         if element.children().len() > 0 {
-            return self.calculate_childful(element, available_bounds, outer_bounds);
+            return self.calculate_childful(element, available_bounds, outer_bounds, context);
         }
 
-        self.calculate_childless(element, available_bounds, outer_bounds)
+        self.calculate_childless(element, available_bounds, outer_bounds, context)
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::layout::{element::ElementBuilder, rect::Rect, Direction::*};
+    use crate::layout::{
+        alignment::*, calculated::CalculatedElement, common::*, context::LayoutContext,
+        dimension::Dimensions, element::*, padding::Padding, rect::Rect, ConstraintUnit,
+        Direction::*,
+    };
+    use crate::{
+        mock::layout::FONT,
+        text::{Text, TextStyle},
+    };
 
     #[test]
     fn calculates_childless() {
@@ -436,6 +651,36 @@ mod test {
         assert_eq!(child.rect.dimensions.height, 30.0);
     }
 
+    #[test]
+    fn calculates_fractions() {
+        let rect = Rect::new(100.0, 30.0, 0.0, 0.0);
+
+        let child = |width: &str| {
+            ElementBuilder::new()
+                .directional(Horizontal, 0.)
+                .sizing(width, "Stretch")
+                .build()
+        };
+
+        let element = ElementBuilder::new()
+            .directional(Horizontal, 5.)
+            .sizing("Stretch", "Stretch")
+            .children(vec![child("1fr"), child("30px"), child("2fr")])
+            .build();
+
+        let result = element.calculate(rect.clone(), rect);
+        let widths: Vec<_> = result
+            .children
+            .iter()
+            .map(|c| c.rect.dimensions.width)
+            .collect();
+
+        // 100 - 30 - 2 * 5 = 60 is shared between the fractions
+        assert_eq!(widths, vec![20.0, 30.0, 40.0]);
+        assert_eq!(result.children[2].rect.position.x, 60.0);
+        assert_eq!(result.children[0].rect.dimensions.height, 30.0);
+    }
+
     #[test]
     fn calculates_padding() {
         let rect = Rect::new(100.0, 100.0, 0.0, 0.0);
@@ -456,4 +701,194 @@ mod test {
         assert_eq!(child.rect.dimensions.height, 50.0 - (10. * 2.0));
         assert_eq!(child.rect.dimensions.width, 100.0 - (10. * 2.0));
     }
+
+    /// Lays out fixed-width words, wrapping like text does
+    struct Words {
+        count: usize,
+        width: Float,
+        line_height: Float,
+    }
+
+    impl ElementLayout for Words {
+        fn kind_name(&self) -> String {
+            "Words".into()
+        }
+
+        fn calculate(
+            &self,
+            _: &Element,
+            available_bounds: Rect,
+            _: Rect,
+            _: &LayoutContext,
+        ) -> CalculatedElement {
+            let per_line = ((available_bounds.dimensions.width / self.width) as usize)
+                .max(1)
+                .min(self.count);
+            let lines = self.count.div_ceil(per_line);
+
+            CalculatedElement::from_rect(Rect::new(
+                per_line as Float * self.width,
+                lines as Float * self.line_height,
+                available_bounds.position.x,
+                available_bounds.position.y,
+            ))
+        }
+
+        fn max_content(&self, _: &Element, _: &LayoutContext) -> Dimensions {
+            Dimensions::new(self.count as Float * self.width, self.line_height)
+        }
+    }
+
+    #[test]
+    fn collapses_to_max_content() {
+        let rect = Rect::new(400.0, 100.0, 0.0, 0.0);
+
+        let label = Element {
+            kind: ElementKind::Extern(Box::new(Words {
+                count: 5,
+                width: 20.,
+                line_height: 10.,
+            })),
+            sizing: Sizing::new(
+                SizingUnit::Collapse(ConstraintUnit::None),
+                SizingUnit::Collapse(ConstraintUnit::None),
+            ),
+            padding: Padding::empty(),
+            alignment: Alignment::new(AlignUnit::Start, AlignUnit::Start),
+            children: Vec::new(),
+            label: None,
+            base_style: None,
+            breakpoints: Vec::new(),
+        };
+
+        let button = ElementBuilder::new()
+            .directional(Horizontal, 0.)
+            .sizing("Collapse", "Collapse")
+            .children(vec![label])
+            .pad_all(8.)
+            .build();
+
+        let element = ElementBuilder::new()
+            .directional(Horizontal, 0.)
+            .sizing("Stretch", "Collapse")
+            .children(vec![button])
+            .build();
+
+        let context = LayoutContext::new(rect.dimensions);
+        assert_eq!(
+            element.children()[0].max_content(&context).width,
+            100.0 + 16.0
+        );
+
+        let result = element.calculate(rect.clone(), rect);
+        let button = &result.children[0];
+
+        // The label stays on a single line instead of wrapping
+        assert_eq!(button.rect.dimensions.width, 100.0 + 16.0);
+        assert_eq!(button.rect.dimensions.height, 10.0 + 16.0);
+    }
+
+    #[test]
+    fn shrinks_to_min_content() {
+        let style = FONT.with(|f| TextStyle::new(f.clone(), 16.));
+        let text = ElementBuilder::new()
+            .text(Text::new("pizza krabbe", style))
+            .sizing("Collapse", "Collapse")
+            .build();
+
+        let context = LayoutContext::default();
+        let min_content = text.min_content(&context).width;
+
+        let element = ElementBuilder::new()
+            .directional(Horizontal, 0.)
+            .sizing("Stretch", "Stretch")
+            .children(vec![text])
+            .build();
+
+        let rect = Rect::new(10.0, 100.0, 0.0, 0.0);
+        let result = element.calculate(rect.clone(), rect);
+        let text = &result.children[0];
+
+        // Rather than breaking the words apart, the text overflows at its min-content width
+        assert_eq!(text.rect.dimensions.width, min_content);
+        assert_eq!(text.text.as_ref().unwrap().lines.len(), 2);
+    }
+
+    #[test]
+    fn measures_height_for_width() {
+        let words = Element {
+            kind: ElementKind::Extern(Box::new(Words {
+                count: 5,
+                width: 20.,
+                line_height: 10.,
+            })),
+            sizing: Sizing::collapse(),
+            padding: Padding::empty(),
+            alignment: Alignment::new(AlignUnit::Start, AlignUnit::Start),
+            children: Vec::new(),
+            label: None,
+            base_style: None,
+            breakpoints: Vec::new(),
+        };
+
+        let element = ElementBuilder::new()
+            .directional(Vertical, 0.)
+            .sizing("Stretch", "Stretch")
+            .pad_all(5.)
+            .children(vec![words])
+            .build();
+
+        // Two words fit on a line, and the stretching height takes on the content
+        let context = LayoutContext::default();
+        assert_eq!(element.height_for_width(50., &context), 3. * 10. + 10.);
+    }
+
+    #[test]
+    fn parallel_matches_serial() {
+        let rect = Rect::new(300.0, 300.0, 0.0, 0.0);
+
+        let build = |parallel: bool| {
+            let children = (0..8)
+                .map(|i| {
+                    ElementBuilder::new()
+                        .directional(Vertical, 4.)
+                        .sizing("Collapse", "Collapse")
+                        .children(vec![
+                            ElementBuilder::new()
+                                .directional(Horizontal, 0.)
+                                .sizing(&format!("Fixed:{}", 10 + i), "Fixed:12")
+                                .build(),
+                            ElementBuilder::new()
+                                .directional(Horizontal, 0.)
+                                .sizing("Percent:0.1,_,_", "Fixed:8")
+                                .build(),
+                        ])
+                        .build()
+                })
+                .chain(std::iter::once(
+                    ElementBuilder::new()
+                        .directional(Horizontal, 0.)
+                        .sizing("Stretch", "Stretch")
+                        .build(),
+                ))
+                .collect();
+
+            let builder = ElementBuilder::new()
+                .directional(Horizontal, 8.)
+                .sizing("Stretch", "Collapse")
+                .children(children)
+                .pad_all(4.);
+
+            if parallel {
+                builder.parallel().build()
+            } else {
+                builder.build()
+            }
+        };
+
+        let serial = build(false).calculate(rect.clone(), rect.clone());
+        let parallel = build(true).calculate(rect.clone(), rect);
+
+        assert_eq!(serial.display(), parallel.display());
+    }
 }
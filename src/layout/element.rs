@@ -1,22 +1,65 @@
-use crate::{parsing::parse_sizing_unit, Alignment, ConstraintUnit};
+use crate::{
+    parsing::{parse_sizing_unit, ParsingError},
+    text::{Inline, Text},
+    Alignment, ConstraintUnit,
+};
 
 use super::{
-    alignment::*, calculated::CalculatedElement, common::*, dimension::Dimensions,
-    directional::Directional, padding::Padding, rect::Rect,
+    alignment::*, calculated::CalculatedElement, common::*, context::LayoutContext,
+    dimension::Dimensions, directional::Directional, padding::Padding, rect::Rect, relational::*,
+    responsive::Breakpoint, stylesheet::BaseStyle,
 };
 
-pub trait ElementLayout {
+/// Layout implementations must be `Send + Sync`, so element trees can be
+/// laid out on a thread pool
+pub trait ElementLayout: Send + Sync {
     fn kind_name(&self) -> String;
     fn calculate(
         &self,
         element: &Element,
         available_bounds: Rect,
         outer_bounds: Rect,
+        context: &LayoutContext,
     ) -> CalculatedElement;
+
+    /// Size of the element when its content is as narrow as possible,
+    /// such as text that wraps at every opportunity
+    fn min_content(&self, element: &Element, context: &LayoutContext) -> Dimensions {
+        measure(element, 0., 0., context)
+    }
+
+    /// Size of the element when given unlimited space, such as text that never wraps
+    fn max_content(&self, element: &Element, context: &LayoutContext) -> Dimensions {
+        measure(element, Float::INFINITY, Float::INFINITY, context)
+    }
+
+    /// Height of the element when laid out at the given width, without a limit on its height
+    fn height_for_width(&self, element: &Element, width: Float, context: &LayoutContext) -> Float {
+        measure(element, width, Float::INFINITY, context).height
+    }
+
+    /// Settings written as attributes of the `kind_name` tag when the element is
+    /// written as markup, which a constructor given to `MarkupParser::extern_kind` reads back
+    fn markup_attributes(&self) -> Vec<(String, String)> {
+        Vec::new()
+    }
+}
+
+/// Calculates the element in the given bounds, only keeping its dimensions
+fn measure(element: &Element, width: Float, height: Float, context: &LayoutContext) -> Dimensions {
+    let bounds = Rect::new(width, height, 0., 0.);
+
+    element
+        .calculate_in(bounds.clone(), bounds, context)
+        .rect
+        .dimensions
 }
 
 pub enum ElementKind {
     Directional(Directional),
+    Relational(Relational),
+    Text(Text),
+    Inline(Inline),
     Extern(Box<dyn ElementLayout>),
     None,
 }
@@ -25,6 +68,9 @@ impl std::fmt::Debug for ElementKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ElementKind::Directional(d) => f.debug_tuple("DirectionaElement").field(d).finish(),
+            ElementKind::Relational(r) => f.debug_tuple("RelationalElement").field(r).finish(),
+            ElementKind::Text(t) => f.debug_tuple("TextElement").field(t).finish(),
+            ElementKind::Inline(i) => f.debug_tuple("InlineElement").field(i).finish(),
             ElementKind::None => f.debug_tuple("NoneElement").finish(),
             ElementKind::Extern(e) => f
                 .debug_tuple("ExternElement")
@@ -42,21 +88,159 @@ pub struct Element {
     pub(crate) alignment: Alignment,
     pub(crate) children: Vec<Element>,
     pub(crate) label: Option<String>,
+
+    /// How the element was built, kept once a stylesheet has been applied
+    pub(crate) base_style: Option<Box<BaseStyle>>,
+    pub(crate) breakpoints: Vec<Breakpoint>,
 }
 
 impl Element {
+    /// Lays out the element as the root, in a window the size of the available bounds
     pub fn calculate(&self, available_bounds: Rect, outer_bounds: Rect) -> CalculatedElement {
+        let context = LayoutContext::new(available_bounds.dimensions);
+
+        self.calculate_in(available_bounds, outer_bounds, &context)
+    }
+
+    /// Lays out the element with the breakpoints that match the context
+    pub fn calculate_in(
+        &self,
+        available_bounds: Rect,
+        outer_bounds: Rect,
+        context: &LayoutContext,
+    ) -> CalculatedElement {
         match &self.kind {
-            ElementKind::Directional(l) => l.calculate(&self, available_bounds, outer_bounds),
-            ElementKind::Extern(e) => e.calculate(&self, available_bounds, outer_bounds),
+            ElementKind::Directional(d) => self.with_directional(d, context, |d| {
+                d.calculate(self, available_bounds, outer_bounds, context)
+            }),
+            ElementKind::Relational(r) => {
+                r.calculate(self, available_bounds, outer_bounds, context)
+            }
+            ElementKind::Text(t) => t.calculate(self, available_bounds, outer_bounds, context),
+            ElementKind::Inline(i) => i.calculate(self, available_bounds, outer_bounds, context),
+            ElementKind::Extern(e) => e.calculate(self, available_bounds, outer_bounds, context),
             ElementKind::None => CalculatedElement::empty(Dimensions::new(0.0, 0.0)),
         }
     }
 
+    pub fn min_content(&self, context: &LayoutContext) -> Dimensions {
+        match &self.kind {
+            ElementKind::Directional(d) => {
+                self.with_directional(d, context, |d| d.min_content(self, context))
+            }
+            ElementKind::Text(t) => t.min_content(self, context),
+            ElementKind::Inline(i) => i.min_content(self, context),
+            ElementKind::Extern(e) => e.min_content(self, context),
+            ElementKind::Relational(r) => r.content(self, context),
+            ElementKind::None => Dimensions::new(0.0, 0.0),
+        }
+    }
+
+    pub fn max_content(&self, context: &LayoutContext) -> Dimensions {
+        match &self.kind {
+            ElementKind::Directional(d) => {
+                self.with_directional(d, context, |d| d.max_content(self, context))
+            }
+            ElementKind::Text(t) => t.max_content(self, context),
+            ElementKind::Inline(i) => i.max_content(self, context),
+            ElementKind::Extern(e) => e.max_content(self, context),
+            // Relational content is positioned absolutely, so it doesn't grow with the bounds
+            ElementKind::Relational(r) => r.content(self, context),
+            ElementKind::None => Dimensions::new(0.0, 0.0),
+        }
+    }
+
+    pub fn height_for_width(&self, width: Float, context: &LayoutContext) -> Float {
+        match &self.kind {
+            ElementKind::Text(t) => t.height_for_width(width),
+            ElementKind::Inline(i) => i.height_for_width(self, width, context),
+            ElementKind::Extern(e) => e.height_for_width(self, width, context),
+            ElementKind::None => 0.0,
+            _ => measure(self, width, Float::INFINITY, context).height,
+        }
+    }
+
+    /// The context of the children, whose container is the space inside the padding of the box
+    /// the element takes on in the available bounds. Collapsing axes take on the max-content
+    /// size, which is measured with the children in an unbounded container.
+    pub(crate) fn nested_context(
+        &self,
+        available_bounds: &Rect,
+        outer_bounds: &Rect,
+        context: &LayoutContext,
+    ) -> LayoutContext {
+        let sizing = self.sizing_in(context);
+        let (width, height) = available_bounds.dimensions.as_tuple();
+
+        let content = match sizing.as_tuple() {
+            (SizingUnit::Collapse(_), _) | (_, SizingUnit::Collapse(_)) => {
+                let content = self.max_content(context);
+                Dimensions::new(content.width.min(width), content.height.min(height))
+            }
+            _ => Dimensions::new(0., 0.),
+        };
+
+        let (width, height) = sizing
+            .calculate(
+                content,
+                available_bounds.dimensions,
+                outer_bounds.dimensions,
+                context,
+            )
+            .as_tuple();
+        let (top, bottom, left, right) = self.padding_in(context).as_tuple();
+
+        context.nested(Dimensions::new(
+            (width - left - right).max(0.),
+            (height - top - bottom).max(0.),
+        ))
+    }
+
+    /// The breakpoint that applies in the context, which is the last one that matches
+    pub fn breakpoint(&self, context: &LayoutContext) -> Option<&Breakpoint> {
+        self.breakpoints.iter().rev().find(|b| b.matches(context))
+    }
+
+    /// Lays out with the direction and spacing of the breakpoint that applies, if it sets them
+    fn with_directional<T>(
+        &self,
+        directional: &Directional,
+        context: &LayoutContext,
+        f: impl FnOnce(&Directional) -> T,
+    ) -> T {
+        match self.breakpoint(context) {
+            Some(b) if b.direction.is_some() || b.spacing.is_some() => f(&Directional::new(
+                b.direction.unwrap_or(directional.direction()),
+                b.spacing.unwrap_or(directional.spacing()),
+            )
+            .parallel(directional.is_parallel())),
+            _ => f(directional),
+        }
+    }
+
+    /// The sizing the element was built with, see `sizing_in` for the one of a breakpoint
     pub fn sizing(&self) -> &Sizing {
         &self.sizing
     }
 
+    /// The sizing of the breakpoint that applies, or the one the element was built with.
+    /// Elements whose size was decided by their parent stretch to fill their available bounds.
+    pub fn sizing_in(&self, context: &LayoutContext) -> &Sizing {
+        static SOLVED: Sizing = Sizing {
+            width: SizingUnit::Stretch(ConstraintUnit::None),
+            height: SizingUnit::Stretch(ConstraintUnit::None),
+        };
+
+        if context.is_solved() {
+            return &SOLVED;
+        }
+
+        match self.breakpoint(context).and_then(|b| b.sizing.as_ref()) {
+            Some(sizing) => sizing,
+            None => &self.sizing,
+        }
+    }
+
     pub fn children(&self) -> &Vec<Element> {
         &self.children
     }
@@ -65,6 +249,13 @@ impl Element {
         &self.padding
     }
 
+    pub fn padding_in(&self, context: &LayoutContext) -> &Padding {
+        match self.breakpoint(context).and_then(|b| b.padding.as_ref()) {
+            Some(padding) => padding,
+            None => &self.padding,
+        }
+    }
+
     pub fn label(&self) -> Option<&String> {
         self.label.as_ref()
     }
@@ -72,6 +263,17 @@ impl Element {
     pub fn alignment(&self) -> &Alignment {
         &self.alignment
     }
+
+    pub fn alignment_in(&self, context: &LayoutContext) -> &Alignment {
+        match self.breakpoint(context).and_then(|b| b.alignment.as_ref()) {
+            Some(alignment) => alignment,
+            None => &self.alignment,
+        }
+    }
+
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
 }
 
 pub struct ElementBuilder {
@@ -81,6 +283,8 @@ pub struct ElementBuilder {
     alignment: Alignment,
     children: Vec<Element>,
     label: Option<String>,
+    parallel: bool,
+    breakpoints: Vec<Breakpoint>,
 }
 
 impl ElementBuilder {
@@ -95,6 +299,8 @@ impl ElementBuilder {
                 height: SizingUnit::Collapse(ConstraintUnit::None),
             },
             label: None,
+            parallel: false,
+            breakpoints: Vec::new(),
         }
     }
 
@@ -108,14 +314,60 @@ impl ElementBuilder {
         self
     }
 
+    /// Lays out independent children on a thread pool, with the same result as the serial path.
+    /// Only directional elements have a parallel path, so relational, text and inline elements
+    /// (and any extern layout) always lay out their children serially and ignore this.
+    pub fn parallel(mut self) -> Self {
+        self.parallel = true;
+        self
+    }
+
+    pub fn relational(mut self, relationships: Vec<Relationship>) -> Self {
+        self.kind = ElementKind::Relational(Relational::new(relationships));
+        self
+    }
+
+    pub fn text(mut self, text: Text) -> Self {
+        self.kind = ElementKind::Text(text);
+        self
+    }
+
+    /// Flows the children within the text, in place of its object spans
+    pub fn inline(mut self, text: Text) -> Self {
+        self.kind = ElementKind::Inline(Inline::new(text));
+        self
+    }
+
     pub fn align(mut self, horizontal: AlignUnit, vertical: AlignUnit) -> Self {
         self.alignment = Alignment::new(horizontal, vertical);
         self
     }
 
-    pub fn sizing(mut self, width: &str, height: &str) -> Self {
-        self.sizing.width = parse_sizing_unit(width).unwrap();
-        self.sizing.height = parse_sizing_unit(height).unwrap();
+    /// Sizes the element with units such as `Stretch`, `Percent:0.5,_,300` or `min(300px, 50%)`,
+    /// panicking if either can't be parsed. See `try_sizing` to handle the error instead.
+    pub fn sizing(self, width: &str, height: &str) -> Self {
+        self.try_sizing(width, height)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_sizing(mut self, width: &str, height: &str) -> Result<Self, ParsingError> {
+        self.sizing.width = parse_sizing_unit(width)?;
+        self.sizing.height = parse_sizing_unit(height)?;
+        Ok(self)
+    }
+
+    pub fn size(mut self, sizing: Sizing) -> Self {
+        self.sizing = sizing;
+        self
+    }
+
+    pub fn width(mut self, width: SizingUnit) -> Self {
+        self.sizing.width = width;
+        self
+    }
+
+    pub fn height(mut self, height: SizingUnit) -> Self {
+        self.sizing.height = height;
         self
     }
 
@@ -129,14 +381,28 @@ impl ElementBuilder {
         self
     }
 
+    /// Overrides properties while the viewport or the container is within a range of sizes,
+    /// where the last breakpoint that matches applies
+    pub fn breakpoint(mut self, breakpoint: Breakpoint) -> Self {
+        self.breakpoints.push(breakpoint);
+        self
+    }
+
     pub fn build(self) -> Element {
+        let kind = match self.kind {
+            ElementKind::Directional(d) => ElementKind::Directional(d.parallel(self.parallel)),
+            kind => kind,
+        };
+
         Element {
-            kind: self.kind,
+            kind,
             label: self.label,
             sizing: self.sizing,
             padding: self.padding,
             children: self.children,
             alignment: self.alignment,
+            base_style: None,
+            breakpoints: self.breakpoints,
         }
     }
 }
@@ -0,0 +1,350 @@
+use super::{ConstraintUnit, Float, SizingUnit};
+
+/// Builds an element tree, where every element is a builder method that sets its kind
+/// (or `none`), followed by its properties and then its children:
+///
+/// ```text
+/// layout! {
+///     directional(Vertical, 16.) {
+///         width: Collapse;
+///         height: Stretch;
+///         pad_all: 16.;
+///         label: "server_sidebar";
+///
+///         for i in 0..7 {
+///             directional(Horizontal, 0.) { width: 40px; height: 40px; }
+///         }
+///
+///         if compact {
+///             none { width: 1fr; }
+///         } else {
+///             { user() }
+///             ..channels.into_iter();
+///         }
+///     }
+/// }
+/// ```
+///
+/// `width` and `height` take `60px`, `50%`, `1fr`, `auto`, `Collapse`, `Stretch`, a maximum
+/// such as `Stretch(800px)`, or any `SizingUnit` in braces. Units are checked while compiling,
+/// so a unit such as `60pt` fails to compile. Every other property calls the builder method
+/// with its name, and `{ element }` and `..elements;` add children that were built elsewhere.
+#[macro_export]
+macro_rules! layout {
+    (none { $($body:tt)* }) => {
+        $crate::layout!(@element [$crate::ElementBuilder::new()] $($body)*)
+    };
+    ($kind:ident ( $($arguments:tt)* ) { $($body:tt)* }) => {
+        $crate::layout!(@element [$crate::ElementBuilder::new().$kind($($arguments)*)] $($body)*)
+    };
+
+    (@element [$($builder:tt)*] $($body:tt)*) => {{
+        let builder = $($builder)*;
+        #[allow(unused_mut)]
+        let mut children: ::std::vec::Vec<$crate::Element> = ::std::vec::Vec::new();
+
+        $crate::layout!(@properties builder children $($body)*);
+        builder.children(children).build()
+    }};
+
+    // Properties, which come before the children
+    (@properties $b:ident $c:ident width : $($rest:tt)*) => {
+        $crate::layout!(@unit $b $c width $($rest)*);
+    };
+    (@properties $b:ident $c:ident height : $($rest:tt)*) => {
+        $crate::layout!(@unit $b $c height $($rest)*);
+    };
+    (@properties $b:ident $c:ident $method:ident : $($argument:expr),+ ; $($rest:tt)*) => {
+        let $b = $b.$method($($argument),+);
+        $crate::layout!(@properties $b $c $($rest)*);
+    };
+    (@properties $b:ident $c:ident $method:ident ; $($rest:tt)*) => {
+        let $b = $b.$method();
+        $crate::layout!(@properties $b $c $($rest)*);
+    };
+    (@properties $b:ident $c:ident $($rest:tt)*) => {
+        $crate::layout!(@children $c $($rest)*);
+    };
+
+    (@unit $b:ident $c:ident $property:ident $number:literal % ; $($rest:tt)*) => {
+        let $b = $b.$property($crate::layout!(@sizing $number %));
+        $crate::layout!(@properties $b $c $($rest)*);
+    };
+    (@unit $b:ident $c:ident $property:ident $unit:ident ( $($constraint:tt)* ) ; $($rest:tt)*) => {
+        let $b = $b.$property($crate::layout!(@sizing $unit ( $($constraint)* )));
+        $crate::layout!(@properties $b $c $($rest)*);
+    };
+    (@unit $b:ident $c:ident $property:ident $unit:tt ; $($rest:tt)*) => {
+        let $b = $b.$property($crate::layout!(@sizing $unit));
+        $crate::layout!(@properties $b $c $($rest)*);
+    };
+
+    (@sizing auto) => {
+        $crate::SizingUnit::Collapse($crate::ConstraintUnit::None)
+    };
+    (@sizing Collapse) => {
+        $crate::SizingUnit::Collapse($crate::ConstraintUnit::None)
+    };
+    (@sizing Stretch) => {
+        $crate::SizingUnit::Stretch($crate::ConstraintUnit::None)
+    };
+    (@sizing Collapse ( $($constraint:tt)+ )) => {
+        $crate::SizingUnit::Collapse($crate::layout!(@constraint $($constraint)+))
+    };
+    (@sizing Stretch ( $($constraint:tt)+ )) => {
+        $crate::SizingUnit::Stretch($crate::layout!(@constraint $($constraint)+))
+    };
+    (@sizing { $unit:expr }) => {
+        $unit
+    };
+    (@sizing $number:literal %) => {{
+        const UNIT: $crate::SizingUnit = $crate::SizingUnit::Percent(
+            $crate::percent_literal(stringify!($number)),
+            $crate::ConstraintUnit::None,
+            $crate::ConstraintUnit::None,
+        );
+        UNIT
+    }};
+    (@sizing $literal:literal) => {{
+        const UNIT: $crate::SizingUnit = $crate::sizing_literal(stringify!($literal));
+        UNIT
+    }};
+
+    (@constraint $number:literal %) => {{
+        const UNIT: $crate::ConstraintUnit =
+            $crate::ConstraintUnit::Percent($crate::percent_literal(stringify!($number)));
+        UNIT
+    }};
+    (@constraint $literal:literal) => {{
+        const UNIT: $crate::ConstraintUnit = $crate::constraint_literal(stringify!($literal));
+        UNIT
+    }};
+
+    // Children, which are elements, loops, conditionals and elements built elsewhere
+    (@children $c:ident) => {};
+    (@children $c:ident for $pattern:pat in $($rest:tt)*) => {
+        $crate::layout!(@for $c [$pattern] [] $($rest)*);
+    };
+    (@children $c:ident if $($rest:tt)*) => {
+        $crate::layout!(@if $c [] [] $($rest)*);
+    };
+    (@children $c:ident { $element:expr } $($rest:tt)*) => {
+        $c.push($element);
+        $crate::layout!(@children $c $($rest)*);
+    };
+    (@children $c:ident .. $elements:expr ; $($rest:tt)*) => {
+        $c.extend($elements);
+        $crate::layout!(@children $c $($rest)*);
+    };
+    (@children $c:ident none { $($body:tt)* } $($rest:tt)*) => {
+        $c.push($crate::layout!(none { $($body)* }));
+        $crate::layout!(@children $c $($rest)*);
+    };
+    (@children $c:ident $kind:ident ( $($arguments:tt)* ) { $($body:tt)* } $($rest:tt)*) => {
+        $c.push($crate::layout!($kind ( $($arguments)* ) { $($body)* }));
+        $crate::layout!(@children $c $($rest)*);
+    };
+
+    // Collects the expression being iterated until the body of the loop
+    (@for $c:ident [$pattern:pat] [$($iterator:tt)*] { $($body:tt)* } $($rest:tt)*) => {
+        for $pattern in $($iterator)* {
+            $crate::layout!(@children $c $($body)*);
+        }
+
+        $crate::layout!(@children $c $($rest)*);
+    };
+    (@for $c:ident [$pattern:pat] [$($iterator:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::layout!(@for $c [$pattern] [$($iterator)* $next] $($rest)*);
+    };
+
+    // Collects every condition and branch of an `if` chain, then the `else` branch
+    (@if $c:ident [$($chain:tt)*] [$($condition:tt)*] { $($body:tt)* } else if $($rest:tt)*) => {
+        $crate::layout!(@if $c [$($chain)* [$($condition)*] { $($body)* }] [] $($rest)*);
+    };
+    (@if $c:ident [$($chain:tt)*] [$($condition:tt)*] { $($body:tt)* } else { $($otherwise:tt)* } $($rest:tt)*) => {
+        $crate::layout!(@branches $c [$($chain)* [$($condition)*] { $($body)* }] { $($otherwise)* });
+        $crate::layout!(@children $c $($rest)*);
+    };
+    (@if $c:ident [$($chain:tt)*] [$($condition:tt)*] { $($body:tt)* } $($rest:tt)*) => {
+        $crate::layout!(@branches $c [$($chain)* [$($condition)*] { $($body)* }] {});
+        $crate::layout!(@children $c $($rest)*);
+    };
+    (@if $c:ident [$($chain:tt)*] [$($condition:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::layout!(@if $c [$($chain)*] [$($condition)* $next] $($rest)*);
+    };
+
+    (@branches $c:ident [$([$($condition:tt)*] { $($body:tt)* })*] { $($otherwise:tt)* }) => {
+        $(if $($condition)* {
+            $crate::layout!(@children $c $($body)*);
+        } else)* {
+            $crate::layout!(@children $c $($otherwise)*);
+        }
+    };
+}
+
+/// Reads the number at the start of a literal, and where it ends
+const fn number(literal: &str) -> (f64, usize) {
+    let bytes = literal.as_bytes();
+
+    let mut mantissa: f64 = 0.;
+    let mut divisor: f64 = 1.;
+    let mut fractional = false;
+    let mut digits = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'0'..=b'9' => {
+                mantissa = mantissa * 10. + (bytes[i] - b'0') as f64;
+                digits += 1;
+
+                if fractional {
+                    divisor *= 10.;
+                }
+            }
+            b'.' if !fractional => fractional = true,
+            b'_' => (),
+            _ => break,
+        }
+
+        i += 1;
+    }
+
+    if digits == 0 {
+        panic!("expected a number");
+    }
+
+    (mantissa / divisor, i)
+}
+
+/// Whether the literal ends with the suffix right after its number
+const fn has_suffix(literal: &str, end: usize, suffix: &str) -> bool {
+    let (literal, suffix) = (literal.as_bytes(), suffix.as_bytes());
+
+    if literal.len() - end != suffix.len() {
+        return false;
+    }
+
+    let mut i = 0;
+
+    while i < suffix.len() {
+        if literal[end + i] != suffix[i] {
+            return false;
+        }
+
+        i += 1;
+    }
+
+    true
+}
+
+#[doc(hidden)]
+/// Reads a unit such as `60px` or `1fr` while `layout!` is compiled
+pub const fn sizing_literal(literal: &str) -> SizingUnit {
+    let (value, end) = number(literal);
+
+    if has_suffix(literal, end, "px") {
+        SizingUnit::Fixed(value as Float)
+    } else if has_suffix(literal, end, "fr") {
+        SizingUnit::Fraction(value as Float)
+    } else {
+        panic!("sizing units are written as 60px, 50%, 1fr, auto, Collapse or Stretch");
+    }
+}
+
+#[doc(hidden)]
+/// Reads a maximum such as `800px` while `layout!` is compiled
+pub const fn constraint_literal(literal: &str) -> ConstraintUnit {
+    let (value, end) = number(literal);
+
+    if !has_suffix(literal, end, "px") {
+        panic!("constraints are written as 800px or 50%");
+    }
+
+    ConstraintUnit::Fixed(value as Float)
+}
+
+#[doc(hidden)]
+/// Reads the number before a `%` as a fraction while `layout!` is compiled,
+/// the same way as `parse_constraint_unit`
+pub const fn percent_literal(literal: &str) -> Float {
+    let (value, end) = number(literal);
+
+    if end != literal.len() {
+        panic!("percentages are written as 50%");
+    }
+
+    (value / 100.) as Float
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{write_markup, AlignUnit::*, Direction::*, Element, ElementBuilder};
+
+    fn server(index: usize) -> Element {
+        ElementBuilder::new()
+            .directional(Horizontal, 0.)
+            .sizing("40px", "40px")
+            .label(format!("server {}", index))
+            .build()
+    }
+
+    #[test]
+    fn expands_to_builders() {
+        let compact = false;
+        let extra = vec![server(8), server(9)];
+
+        let element = crate::layout! {
+            directional(Vertical, 16.) {
+                width: Stretch(800px);
+                height: 12.5%;
+                pad_all: 16.;
+                align: Middle, End;
+                label: "sidebar";
+
+                for i in (0..3).filter(|i| i % 2 == 0) {
+                    { server(i) }
+                }
+
+                if compact {
+                    none { width: 1fr; }
+                } else if extra.len() > 1 {
+                    none { width: auto; height: { crate::parse_sizing_unit("calc(100% - 8px)").unwrap() }; }
+                    ..extra;
+                } else {
+                    none {}
+                }
+
+                directional(Horizontal, 8.) {
+                    width: 50%;
+                    height: Collapse(1_000.5px);
+                    parallel;
+                }
+            }
+        };
+
+        let expected = ElementBuilder::new()
+            .directional(Vertical, 16.)
+            .sizing("Stretch:800", "Percent:0.125,_,_")
+            .pad_all(16.)
+            .align(Middle, End)
+            .label("sidebar")
+            .children(vec![
+                server(0),
+                server(2),
+                ElementBuilder::new()
+                    .sizing("auto", "calc(100% - 8px)")
+                    .build(),
+                server(8),
+                server(9),
+                ElementBuilder::new()
+                    .directional(Horizontal, 8.)
+                    .sizing("50%", "Collapse:1000.5px")
+                    .parallel()
+                    .build(),
+            ])
+            .build();
+
+        assert_eq!(write_markup(&element), write_markup(&expected));
+    }
+}
@@ -1,23 +1,36 @@
 mod alignment;
+mod calc;
 mod calculated;
 mod common;
 mod constraints;
+mod context;
 mod dimension;
 mod directional;
 mod element;
+mod macros;
 mod padding;
 mod position;
 mod rect;
+mod relational;
+mod responsive;
+mod stylesheet;
 
 pub use alignment::*;
+pub use calc::*;
 pub use calculated::*;
 pub use common::*;
 pub use constraints::*;
+pub use context::*;
 pub use dimension::*;
 pub use directional::*;
 pub use element::*;
+pub use macros::*;
 pub use padding::*;
+pub use position::*;
 pub use rect::*;
+pub use relational::*;
+pub use responsive::*;
+pub use stylesheet::*;
 
 #[cfg(test)]
 mod test {
@@ -0,0 +1,793 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+
+use cassowary::{
+    strength, AddConstraintError, Constraint, Expression, RelationalOperator, Solver, Term,
+    Variable,
+};
+
+use super::{
+    calculated::CalculatedElement, common::*, context::LayoutContext, dimension::Dimensions,
+    element::Element, rect::Rect,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Attribute {
+    Left,
+    Right,
+    Top,
+    Bottom,
+    Width,
+    Height,
+    CenterX,
+    CenterY,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Refers to an attribute of a sibling by label, or of the parent's inner bounds
+pub struct Anchor {
+    label: Option<String>,
+    attribute: Attribute,
+}
+
+impl Anchor {
+    pub fn new(label: impl ToString, attribute: Attribute) -> Anchor {
+        Anchor {
+            label: Some(label.to_string()),
+            attribute,
+        }
+    }
+
+    pub fn parent(attribute: Attribute) -> Anchor {
+        Anchor {
+            label: None,
+            attribute,
+        }
+    }
+
+    pub fn label(&self) -> Option<&String> {
+        self.label.as_ref()
+    }
+
+    pub fn attribute(&self) -> Attribute {
+        self.attribute
+    }
+
+    pub fn equals(self, other: impl Into<LinearExpression>) -> Relationship {
+        LinearExpression::from(self).equals(other)
+    }
+
+    pub fn at_most(self, other: impl Into<LinearExpression>) -> Relationship {
+        LinearExpression::from(self).at_most(other)
+    }
+
+    pub fn at_least(self, other: impl Into<LinearExpression>) -> Relationship {
+        LinearExpression::from(self).at_least(other)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+/// A sum of weighted anchors plus a constant
+pub struct LinearExpression {
+    terms: Vec<(Float, Anchor)>,
+    constant: Float,
+}
+
+impl LinearExpression {
+    pub fn terms(&self) -> &Vec<(Float, Anchor)> {
+        &self.terms
+    }
+
+    pub fn constant(&self) -> Float {
+        self.constant
+    }
+
+    pub fn equals(self, other: impl Into<LinearExpression>) -> Relationship {
+        Relationship::new(self, Relation::Equal, other.into())
+    }
+
+    pub fn at_most(self, other: impl Into<LinearExpression>) -> Relationship {
+        Relationship::new(self, Relation::AtMost, other.into())
+    }
+
+    pub fn at_least(self, other: impl Into<LinearExpression>) -> Relationship {
+        Relationship::new(self, Relation::AtLeast, other.into())
+    }
+}
+
+impl From<Anchor> for LinearExpression {
+    fn from(anchor: Anchor) -> Self {
+        LinearExpression {
+            terms: vec![(1., anchor)],
+            constant: 0.,
+        }
+    }
+}
+
+impl From<Float> for LinearExpression {
+    fn from(constant: Float) -> Self {
+        LinearExpression {
+            terms: Vec::new(),
+            constant,
+        }
+    }
+}
+
+impl<T: Into<LinearExpression>> Add<T> for LinearExpression {
+    type Output = LinearExpression;
+
+    fn add(mut self, other: T) -> LinearExpression {
+        let other = other.into();
+
+        self.terms.extend(other.terms);
+        self.constant += other.constant;
+        self
+    }
+}
+
+impl<T: Into<LinearExpression>> Sub<T> for LinearExpression {
+    type Output = LinearExpression;
+
+    fn sub(self, other: T) -> LinearExpression {
+        self + -other.into()
+    }
+}
+
+impl Mul<Float> for LinearExpression {
+    type Output = LinearExpression;
+
+    fn mul(mut self, factor: Float) -> LinearExpression {
+        for (coefficient, _) in &mut self.terms {
+            *coefficient *= factor;
+        }
+
+        self.constant *= factor;
+        self
+    }
+}
+
+impl Neg for LinearExpression {
+    type Output = LinearExpression;
+
+    fn neg(self) -> LinearExpression {
+        self * -1.
+    }
+}
+
+impl<T: Into<LinearExpression>> Add<T> for Anchor {
+    type Output = LinearExpression;
+
+    fn add(self, other: T) -> LinearExpression {
+        LinearExpression::from(self) + other
+    }
+}
+
+impl<T: Into<LinearExpression>> Sub<T> for Anchor {
+    type Output = LinearExpression;
+
+    fn sub(self, other: T) -> LinearExpression {
+        LinearExpression::from(self) - other
+    }
+}
+
+impl Mul<Float> for Anchor {
+    type Output = LinearExpression;
+
+    fn mul(self, factor: Float) -> LinearExpression {
+        LinearExpression::from(self) * factor
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Relation {
+    Equal,
+    AtMost,
+    AtLeast,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Strength {
+    Required,
+    Strong,
+    Medium,
+    Weak,
+}
+
+impl Strength {
+    fn value(&self) -> f64 {
+        match self {
+            Strength::Required => strength::REQUIRED,
+            Strength::Strong => strength::STRONG,
+            Strength::Medium => strength::MEDIUM,
+            Strength::Weak => strength::WEAK,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A linear equality or inequality between two expressions (required by default)
+pub struct Relationship {
+    left: LinearExpression,
+    relation: Relation,
+    right: LinearExpression,
+    strength: Strength,
+}
+
+impl Relationship {
+    pub fn new(left: LinearExpression, relation: Relation, right: LinearExpression) -> Self {
+        Relationship {
+            left,
+            relation,
+            right,
+            strength: Strength::Required,
+        }
+    }
+
+    pub fn strength(mut self, strength: Strength) -> Self {
+        self.strength = strength;
+        self
+    }
+
+    pub fn as_tuple(&self) -> (&LinearExpression, Relation, &LinearExpression, Strength) {
+        (&self.left, self.relation, &self.right, self.strength)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A relationship that was left out when a relational element was solved
+pub enum RelationalError {
+    /// The relationship refers to a label that none of the children have
+    UnknownLabel(String),
+
+    /// The relationship is required, but conflicts with the required relationships before it
+    Unsatisfiable(Relationship),
+}
+
+impl fmt::Display for RelationalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RelationalError::UnknownLabel(label) => {
+                write!(f, "no child is labelled '{}'", label)
+            }
+            RelationalError::Unsatisfiable(relationship) => {
+                write!(f, "unsatisfiable relationship {:?}", relationship)
+            }
+        }
+    }
+}
+
+impl Error for RelationalError {}
+
+/// The solver variables of a single child
+struct ChildVariables {
+    left: Variable,
+    top: Variable,
+    width: Variable,
+    height: Variable,
+}
+
+impl ChildVariables {
+    fn new() -> ChildVariables {
+        ChildVariables {
+            left: Variable::new(),
+            top: Variable::new(),
+            width: Variable::new(),
+            height: Variable::new(),
+        }
+    }
+
+    fn terms(&self, attribute: Attribute) -> Vec<Term> {
+        let term = |variable, coefficient| Term {
+            variable,
+            coefficient,
+        };
+
+        match attribute {
+            Attribute::Left => vec![term(self.left, 1.)],
+            Attribute::Top => vec![term(self.top, 1.)],
+            Attribute::Width => vec![term(self.width, 1.)],
+            Attribute::Height => vec![term(self.height, 1.)],
+            Attribute::Right => vec![term(self.left, 1.), term(self.width, 1.)],
+            Attribute::Bottom => vec![term(self.top, 1.), term(self.height, 1.)],
+            Attribute::CenterX => vec![term(self.left, 1.), term(self.width, 0.5)],
+            Attribute::CenterY => vec![term(self.top, 1.), term(self.height, 0.5)],
+        }
+    }
+}
+
+#[derive(Debug)]
+/// Represents a layout where children are positioned by solving linear relationships
+/// between them, referenced by label
+pub struct Relational {
+    relationships: Vec<Relationship>,
+}
+
+impl Relational {
+    pub fn new(relationships: Vec<Relationship>) -> Relational {
+        Relational { relationships }
+    }
+
+    pub fn relationships(&self) -> &Vec<Relationship> {
+        &self.relationships
+    }
+
+    fn calculate_inner_bounds(
+        &self,
+        element: &Element,
+        box_bounds: &Rect,
+        context: &LayoutContext,
+    ) -> Rect {
+        let (top, bottom, left, right) = element.padding_in(context).as_tuple();
+        let (width, height, x, y) = box_bounds.as_tuple();
+
+        Rect::new(
+            width - left - right,
+            height - top - bottom,
+            x + left,
+            y + top,
+        )
+    }
+
+    fn calculate_box_bounds(
+        &self,
+        element: &Element,
+        available_bounds: &Rect,
+        outer_bounds: &Rect,
+        content: Dimensions,
+        context: &LayoutContext,
+    ) -> Rect {
+        let (top, bottom, left, right) = element.padding_in(context).as_tuple();
+        let inner = Dimensions::new(content.width + left + right, content.height + top + bottom);
+
+        Rect::from_dimensions_and_position(
+            element.sizing_in(context).calculate(
+                inner,
+                available_bounds.dimensions,
+                outer_bounds.dimensions,
+                context,
+            ),
+            available_bounds.position,
+        )
+    }
+
+    fn parent_value(inner_bounds: &Rect, attribute: Attribute) -> Float {
+        let (width, height, x, y) = inner_bounds.as_tuple();
+
+        match attribute {
+            Attribute::Left => x,
+            Attribute::Top => y,
+            Attribute::Width => width,
+            Attribute::Height => height,
+            Attribute::Right => x + width,
+            Attribute::Bottom => y + height,
+            Attribute::CenterX => x + width / 2.,
+            Attribute::CenterY => y + height / 2.,
+        }
+    }
+
+    /// Converts an expression to solver terms, failing if it references an unknown label
+    fn convert_expression(
+        expression: &LinearExpression,
+        labels: &HashMap<&str, usize>,
+        variables: &[ChildVariables],
+        inner_bounds: &Rect,
+    ) -> Result<Expression, RelationalError> {
+        let mut result = Expression::from_constant(expression.constant as f64);
+
+        for (coefficient, anchor) in &expression.terms {
+            match &anchor.label {
+                None => {
+                    result.constant +=
+                        (*coefficient * Self::parent_value(inner_bounds, anchor.attribute)) as f64
+                }
+                Some(label) => {
+                    let index = labels
+                        .get(label.as_str())
+                        .ok_or_else(|| RelationalError::UnknownLabel(label.clone()))?;
+
+                    for mut term in variables[*index].terms(anchor.attribute) {
+                        term.coefficient *= *coefficient as f64;
+                        result.terms.push(term);
+                    }
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Solves the rects of the children, along with the relationships that had to be left out
+    fn solve(
+        &self,
+        element: &Element,
+        inner_bounds: &Rect,
+        nested: &LayoutContext,
+    ) -> (Vec<Rect>, Vec<RelationalError>) {
+        let children = element.children();
+        let variables: Vec<_> = children.iter().map(|_| ChildVariables::new()).collect();
+
+        let mut labels = HashMap::new();
+        for (index, child) in children.iter().enumerate() {
+            if let Some(label) = child.label() {
+                labels.entry(label.as_str()).or_insert(index);
+            }
+        }
+
+        let mut solver = Solver::new();
+        let mut errors = Vec::new();
+        let (_, _, x, y) = inner_bounds.as_tuple();
+
+        let constrain =
+            |solver: &mut Solver, variable: Variable, relation, value: Float, strength| {
+                let expression = Expression::new(
+                    vec![Term {
+                        variable,
+                        coefficient: 1.,
+                    }],
+                    -value as f64,
+                );
+
+                // These never conflict with each other, only with user relationships
+                solver
+                    .add_constraint(Constraint::new(expression, relation, strength))
+                    .unwrap();
+            };
+
+        for (child, variables) in children.iter().zip(&variables) {
+            // Children prefer their intrinsic size at the start of the inner bounds,
+            // and keeping their size wins over keeping their position
+            let intrinsic = child
+                .calculate_in(inner_bounds.clone(), inner_bounds.clone(), nested)
+                .rect
+                .dimensions;
+
+            let defaults = [
+                (
+                    variables.width,
+                    RelationalOperator::GreaterOrEqual,
+                    0.,
+                    strength::REQUIRED,
+                ),
+                (
+                    variables.height,
+                    RelationalOperator::GreaterOrEqual,
+                    0.,
+                    strength::REQUIRED,
+                ),
+                (
+                    variables.left,
+                    RelationalOperator::GreaterOrEqual,
+                    x,
+                    strength::STRONG,
+                ),
+                (
+                    variables.top,
+                    RelationalOperator::GreaterOrEqual,
+                    y,
+                    strength::STRONG,
+                ),
+                (
+                    variables.width,
+                    RelationalOperator::Equal,
+                    intrinsic.width,
+                    strength::MEDIUM,
+                ),
+                (
+                    variables.height,
+                    RelationalOperator::Equal,
+                    intrinsic.height,
+                    strength::MEDIUM,
+                ),
+                (variables.left, RelationalOperator::Equal, x, strength::WEAK),
+                (variables.top, RelationalOperator::Equal, y, strength::WEAK),
+            ];
+
+            for (variable, relation, value, strength) in defaults.iter() {
+                constrain(&mut solver, *variable, *relation, *value, *strength);
+            }
+        }
+
+        for relationship in &self.relationships {
+            let convert = |expression| {
+                Self::convert_expression(expression, &labels, &variables, inner_bounds)
+            };
+
+            let (left, right) = match (convert(&relationship.left), convert(&relationship.right)) {
+                (Ok(left), Ok(right)) => (left, right),
+                (Err(error), _) | (_, Err(error)) => {
+                    errors.push(error);
+                    continue;
+                }
+            };
+
+            let mut expression = left;
+            expression.constant -= right.constant;
+            expression
+                .terms
+                .extend(right.terms.into_iter().map(|mut term| {
+                    term.coefficient = -term.coefficient;
+                    term
+                }));
+
+            let operator = match relationship.relation {
+                Relation::Equal => RelationalOperator::Equal,
+                Relation::AtMost => RelationalOperator::LessOrEqual,
+                Relation::AtLeast => RelationalOperator::GreaterOrEqual,
+            };
+
+            // Unsatisfiable required relationships are left out, the rest still apply
+            let constraint = Constraint::new(expression, operator, relationship.strength.value());
+
+            if let Err(AddConstraintError::UnsatisfiableConstraint) =
+                solver.add_constraint(constraint)
+            {
+                errors.push(RelationalError::Unsatisfiable(relationship.clone()));
+            }
+        }
+
+        let rects = variables
+            .iter()
+            .map(|v| {
+                let value = |variable| solver.get_value(variable) as Float;
+
+                Rect::new(value(v.width), value(v.height), value(v.left), value(v.top))
+            })
+            .collect();
+
+        (rects, errors)
+    }
+
+    /// Size of the element when its children are laid out without any bounds,
+    /// which is both its min-content and max-content size
+    pub fn content(&self, element: &Element, context: &LayoutContext) -> Dimensions {
+        let bounds = Rect::new(0., 0., 0., 0.);
+
+        self.layout(
+            element,
+            bounds.clone(),
+            bounds,
+            context,
+            &context.unbounded(),
+        )
+        .0
+        .rect
+        .dimensions
+    }
+
+    /// The relationships that are left out when the element is laid out in the available bounds,
+    /// because they refer to unknown labels or conflict with the required relationships
+    pub fn errors(
+        &self,
+        element: &Element,
+        available_bounds: Rect,
+        context: &LayoutContext,
+    ) -> Vec<RelationalError> {
+        let outer_bounds = available_bounds.clone();
+        let nested = element.nested_context(&available_bounds, &outer_bounds, context);
+
+        self.layout(element, available_bounds, outer_bounds, context, &nested)
+            .1
+    }
+
+    pub fn calculate(
+        &self,
+        element: &Element,
+        available_bounds: Rect,
+        outer_bounds: Rect,
+        context: &LayoutContext,
+    ) -> CalculatedElement {
+        let nested = element.nested_context(&available_bounds, &outer_bounds, context);
+        let (calculated, errors) =
+            self.layout(element, available_bounds, outer_bounds, context, &nested);
+
+        debug_assert!(
+            errors.is_empty(),
+            "relationships were left out: {}",
+            errors
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+
+        calculated
+    }
+
+    fn layout(
+        &self,
+        element: &Element,
+        available_bounds: Rect,
+        outer_bounds: Rect,
+        context: &LayoutContext,
+        nested: &LayoutContext,
+    ) -> (CalculatedElement, Vec<RelationalError>) {
+        let box_bounds = self.calculate_box_bounds(
+            element,
+            &available_bounds,
+            &outer_bounds,
+            Dimensions::new(0., 0.),
+            context,
+        );
+
+        let inner_bounds = self.calculate_inner_bounds(element, &box_bounds, context);
+        let (solved, errors) = self.solve(element, &inner_bounds, nested);
+
+        // The content extends to the furthest edge of any child
+        let content = solved.iter().fold(Dimensions::new(0., 0.), |acc, rect| {
+            let (width, height, x, y) = rect.as_tuple();
+
+            Dimensions::new(
+                acc.width.max(x + width - inner_bounds.position.x),
+                acc.height.max(y + height - inner_bounds.position.y),
+            )
+        });
+
+        let box_bounds =
+            self.calculate_box_bounds(element, &available_bounds, &outer_bounds, content, context);
+
+        // Children take on their solved rects, whatever their own sizing is
+        let solved_context = nested.solved();
+        let children = element
+            .children()
+            .iter()
+            .zip(solved)
+            .map(|(child, rect)| child.calculate_in(rect, inner_bounds.clone(), &solved_context))
+            .collect();
+
+        let calculated = CalculatedElement {
+            rect: box_bounds,
+            children,
+            text: None,
+        };
+
+        (calculated, errors)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::layout::{
+        context::LayoutContext, element::ElementBuilder, rect::Rect, relational::*, Direction::*,
+    };
+
+    #[test]
+    fn solves_sibling_relationships() {
+        let rect = Rect::new(200.0, 100.0, 0.0, 0.0);
+
+        let element = ElementBuilder::new()
+            .relational(vec![
+                Anchor::new("a", Attribute::Width).equals(50.),
+                Anchor::new("b", Attribute::Left).equals(Anchor::new("a", Attribute::Right) + 8.),
+                Anchor::new("b", Attribute::Right).equals(Anchor::parent(Attribute::Right)),
+            ])
+            .sizing("Stretch", "Stretch")
+            .pad_all(10.)
+            .children(vec![
+                ElementBuilder::new()
+                    .directional(Horizontal, 0.)
+                    .sizing("Stretch", "Fixed:20")
+                    .label("a")
+                    .build(),
+                ElementBuilder::new()
+                    .directional(Horizontal, 0.)
+                    .sizing("Stretch", "Fixed:20")
+                    .label("b")
+                    .build(),
+            ])
+            .build();
+
+        let result = element.calculate(rect.clone(), rect);
+        let (a, b) = (&result.children[0].rect, &result.children[1].rect);
+
+        assert_eq!(a.as_tuple(), (50.0, 20.0, 10.0, 10.0));
+        assert_eq!(b.as_tuple(), (122.0, 20.0, 68.0, 10.0));
+    }
+
+    #[test]
+    fn solves_inequalities() {
+        let rect = Rect::new(200.0, 100.0, 0.0, 0.0);
+
+        let element = ElementBuilder::new()
+            .relational(vec![
+                Anchor::new("panel", Attribute::Width)
+                    .at_least(Anchor::new("sidebar", Attribute::Width) * 2.),
+                Anchor::new("panel", Attribute::Left)
+                    .equals(Anchor::new("sidebar", Attribute::Right)),
+            ])
+            .sizing("Stretch", "Stretch")
+            .children(vec![
+                ElementBuilder::new()
+                    .directional(Horizontal, 0.)
+                    .sizing("Fixed:40", "Fixed:20")
+                    .label("sidebar")
+                    .build(),
+                ElementBuilder::new()
+                    .directional(Horizontal, 0.)
+                    .sizing("Stretch", "Fixed:20")
+                    .label("panel")
+                    .build(),
+            ])
+            .build();
+
+        let result = element.calculate(rect.clone(), rect);
+        let panel = &result.children[1].rect;
+
+        assert_eq!(panel.position.x, 40.0);
+        assert!(panel.dimensions.width >= 80.0);
+    }
+
+    #[test]
+    fn lays_children_out_at_solved_rects() {
+        let rect = Rect::new(200.0, 100.0, 0.0, 0.0);
+
+        let element = ElementBuilder::new()
+            .relational(vec![
+                Anchor::new("fixed", Attribute::Width).equals(100.),
+                Anchor::new("percent", Attribute::Top)
+                    .equals(Anchor::new("fixed", Attribute::Bottom)),
+                Anchor::new("percent", Attribute::Height).equals(30.),
+            ])
+            .sizing("Stretch", "Stretch")
+            .children(vec![
+                ElementBuilder::new()
+                    .directional(Horizontal, 0.)
+                    .sizing("Fixed:40", "Fixed:20")
+                    .label("fixed")
+                    .children(vec![ElementBuilder::new()
+                        .directional(Horizontal, 0.)
+                        .sizing("Stretch", "Stretch")
+                        .build()])
+                    .build(),
+                ElementBuilder::new()
+                    .directional(Horizontal, 0.)
+                    .sizing("Percent:0.5,_,_", "Percent:0.5,_,_")
+                    .label("percent")
+                    .build(),
+            ])
+            .build();
+
+        let result = element.calculate(rect.clone(), rect);
+        let (fixed, percent) = (&result.children[0], &result.children[1]);
+
+        // The solved size wins over the sizing of the child, and its children are laid out in it
+        assert_eq!(fixed.rect.as_tuple(), (100.0, 20.0, 0.0, 0.0));
+        assert_eq!(fixed.children[0].rect.as_tuple(), (100.0, 20.0, 0.0, 0.0));
+        assert_eq!(percent.rect.as_tuple(), (100.0, 30.0, 0.0, 20.0));
+    }
+
+    #[test]
+    fn reports_left_out_relationships() {
+        let rect = Rect::new(200.0, 100.0, 0.0, 0.0);
+        let conflict = Anchor::new("a", Attribute::Width).equals(60.);
+
+        let relationships = vec![
+            Anchor::new("a", Attribute::Width).equals(50.),
+            Anchor::new("a", Attribute::Left).equals(Anchor::new("b", Attribute::Right)),
+            conflict.clone(),
+        ];
+
+        let element = ElementBuilder::new()
+            .relational(relationships.clone())
+            .sizing("Stretch", "Stretch")
+            .children(vec![ElementBuilder::new()
+                .directional(Horizontal, 0.)
+                .sizing("Fixed:40", "Fixed:20")
+                .label("a")
+                .build()])
+            .build();
+
+        let errors =
+            Relational::new(relationships).errors(&element, rect, &LayoutContext::default());
+
+        assert_eq!(
+            errors,
+            vec![
+                RelationalError::UnknownLabel("b".into()),
+                RelationalError::Unsatisfiable(conflict),
+            ]
+        );
+        assert_eq!(errors[0].to_string(), "no child is labelled 'b'");
+    }
+}
@@ -0,0 +1,273 @@
+use crate::parsing::parse_sizing_unit;
+
+use super::{
+    alignment::{AlignUnit, Alignment},
+    common::{Direction, Float, Sizing},
+    context::LayoutContext,
+    dimension::Dimensions,
+    padding::Padding,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BreakpointTarget {
+    Viewport,
+
+    /// The space inside the padding of the box the parent takes on, or the bounds of the root element
+    Container,
+}
+
+#[derive(Clone, Debug)]
+/// Properties that replace those of an element while the size of the viewport
+/// or its container is within a range, where both ends of the range are included
+pub struct Breakpoint {
+    pub(crate) target: BreakpointTarget,
+    pub(crate) min: Dimensions,
+    pub(crate) max: Dimensions,
+    pub(crate) sizing: Option<Sizing>,
+    pub(crate) padding: Option<Padding>,
+    pub(crate) alignment: Option<Alignment>,
+
+    /// The direction and spacing of directional elements
+    pub(crate) direction: Option<Direction>,
+    pub(crate) spacing: Option<Float>,
+}
+
+impl Breakpoint {
+    fn new(target: BreakpointTarget) -> Breakpoint {
+        Breakpoint {
+            target,
+            min: Dimensions::new(0., 0.),
+            max: Dimensions::new(Float::INFINITY, Float::INFINITY),
+            sizing: None,
+            padding: None,
+            alignment: None,
+            direction: None,
+            spacing: None,
+        }
+    }
+
+    pub fn viewport() -> Breakpoint {
+        Breakpoint::new(BreakpointTarget::Viewport)
+    }
+
+    pub fn container() -> Breakpoint {
+        Breakpoint::new(BreakpointTarget::Container)
+    }
+
+    pub fn target(&self) -> BreakpointTarget {
+        self.target
+    }
+
+    pub fn min_width(mut self, width: Float) -> Self {
+        self.min.width = width;
+        self
+    }
+
+    pub fn max_width(mut self, width: Float) -> Self {
+        self.max.width = width;
+        self
+    }
+
+    pub fn min_height(mut self, height: Float) -> Self {
+        self.min.height = height;
+        self
+    }
+
+    pub fn max_height(mut self, height: Float) -> Self {
+        self.max.height = height;
+        self
+    }
+
+    /// Sets the sizing from units such as `Stretch` or `40px`, panicking if either can't be parsed
+    pub fn sizing(self, width: &str, height: &str) -> Self {
+        let width = parse_sizing_unit(width).unwrap_or_else(|error| panic!("{}", error));
+        let height = parse_sizing_unit(height).unwrap_or_else(|error| panic!("{}", error));
+
+        self.size(Sizing::new(width, height))
+    }
+
+    pub fn size(mut self, sizing: Sizing) -> Self {
+        self.sizing = Some(sizing);
+        self
+    }
+
+    pub fn padding(mut self, padding: Padding) -> Self {
+        self.padding = Some(padding);
+        self
+    }
+
+    pub fn pad_all(self, value: Float) -> Self {
+        self.padding(Padding::new(value, value, value, value))
+    }
+
+    pub fn align(mut self, horizontal: AlignUnit, vertical: AlignUnit) -> Self {
+        self.alignment = Some(Alignment::new(horizontal, vertical));
+        self
+    }
+
+    pub fn direction(mut self, direction: Direction) -> Self {
+        self.direction = Some(direction);
+        self
+    }
+
+    pub fn spacing(mut self, spacing: Float) -> Self {
+        self.spacing = Some(spacing);
+        self
+    }
+
+    /// Whether the size of the viewport or the container of the context is within the range
+    pub fn matches(&self, context: &LayoutContext) -> bool {
+        let size = match self.target {
+            BreakpointTarget::Viewport => context.viewport(),
+            BreakpointTarget::Container => context.container(),
+        };
+
+        (self.min.width..=self.max.width).contains(&size.width)
+            && (self.min.height..=self.max.height).contains(&size.height)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        Breakpoint, Dimensions, Direction::*, Element, ElementBuilder, Float, LayoutContext, Rect,
+    };
+
+    fn square(size: &str) -> ElementBuilder {
+        ElementBuilder::new()
+            .directional(Horizontal, 0.)
+            .sizing(size, size)
+    }
+
+    fn boxes(breakpoint: Breakpoint) -> Element {
+        ElementBuilder::new()
+            .directional(Horizontal, 10.)
+            .sizing("Stretch", "Stretch")
+            .pad_all(10.)
+            .breakpoint(breakpoint)
+            .children(vec![
+                square("100px")
+                    .breakpoint(
+                        Breakpoint::container()
+                            .max_width(300.)
+                            .sizing("50px", "50px"),
+                    )
+                    .breakpoint(
+                        Breakpoint::container()
+                            .max_width(100.)
+                            .sizing("20px", "20px"),
+                    )
+                    .build(),
+                square("100px").build(),
+            ])
+            .build()
+    }
+
+    fn calculate_in(
+        element: &Element,
+        width: Float,
+        context: &LayoutContext,
+    ) -> Vec<(Float, Float, Float, Float)> {
+        let rect = Rect::new(width, 400., 0., 0.);
+        let calculated = element.calculate_in(rect.clone(), rect, context);
+
+        calculated
+            .children
+            .iter()
+            .map(|c| c.rect.as_tuple())
+            .collect()
+    }
+
+    fn calculate(element: &Element, width: Float) -> Vec<(Float, Float, Float, Float)> {
+        calculate_in(
+            element,
+            width,
+            &LayoutContext::new(Dimensions::new(width, 400.)),
+        )
+    }
+
+    #[test]
+    fn applies_container_breakpoints() {
+        let element = boxes(
+            Breakpoint::container()
+                .max_width(400.)
+                .direction(Vertical)
+                .spacing(0.)
+                .pad_all(0.),
+        );
+
+        // The container of the child is the space inside the padding of its parent
+        assert_eq!(
+            calculate(&element, 800.),
+            vec![(100., 100., 10., 10.), (100., 100., 120., 10.)]
+        );
+        // The root matches its own bounds, and the last matching breakpoint applies
+        assert_eq!(
+            calculate(&element, 300.),
+            vec![(50., 50., 0., 0.), (100., 100., 0., 50.)]
+        );
+        assert_eq!(
+            calculate(&element, 100.),
+            vec![(20., 20., 0., 0.), (100., 100., 0., 20.)]
+        );
+
+        // Going back to a larger size restores the properties the element was built with
+        assert_eq!(
+            calculate(&element, 800.),
+            vec![(100., 100., 10., 10.), (100., 100., 120., 10.)]
+        );
+    }
+
+    #[test]
+    fn applies_viewport_breakpoints() {
+        let element = boxes(Breakpoint::viewport().max_width(600.).pad_all(0.));
+        let small = LayoutContext::new(Dimensions::new(500., 400.));
+
+        assert_eq!(
+            calculate_in(&element, 800., &small),
+            vec![(100., 100., 0., 0.), (100., 100., 110., 0.)]
+        );
+        assert_eq!(
+            calculate(&element, 800.),
+            vec![(100., 100., 10., 10.), (100., 100., 120., 10.)]
+        );
+    }
+
+    #[test]
+    fn matches_the_box_of_the_parent() {
+        let sidebar = |width: &str| {
+            ElementBuilder::new()
+                .directional(Vertical, 0.)
+                .sizing(width, "Stretch")
+                .pad_all(10.)
+                .children(vec![square("100px")
+                    .breakpoint(
+                        Breakpoint::container()
+                            .max_width(300.)
+                            .sizing("50px", "50px"),
+                    )
+                    .build()])
+                .build()
+        };
+
+        let window = |sidebar| {
+            ElementBuilder::new()
+                .directional(Horizontal, 0.)
+                .sizing("Stretch", "Stretch")
+                .children(vec![sidebar])
+                .build()
+        };
+
+        // Both sidebars are far narrower than the window they're in
+        for width in &["Fixed:240", "Collapse"] {
+            let element = window(sidebar(width));
+            let rect = Rect::new(800., 400., 0., 0.);
+            let calculated = element.calculate(rect.clone(), rect);
+
+            assert_eq!(
+                calculated.children[0].children[0].rect.as_tuple(),
+                (50., 50., 10., 10.)
+            );
+        }
+    }
+}
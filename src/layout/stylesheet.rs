@@ -0,0 +1,381 @@
+use crate::parsing::parse_sizing_unit;
+
+use super::{
+    alignment::{AlignUnit, Alignment},
+    common::{Sizing, SizingUnit},
+    element::{Element, ElementKind},
+    padding::Padding,
+    Float,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ElementState {
+    Hover,
+    Focus,
+}
+
+#[derive(Clone, Debug, Default)]
+/// The elements that are hovered and focused, by their labels
+pub struct StyleState {
+    hovered: Vec<String>,
+    focused: Option<String>,
+}
+
+impl StyleState {
+    pub fn new() -> StyleState {
+        StyleState::default()
+    }
+
+    /// Marks the element as hovered, along with any other hovered elements
+    pub fn hover(mut self, label: impl ToString) -> Self {
+        self.hovered.push(label.to_string());
+        self
+    }
+
+    pub fn focus(mut self, label: impl ToString) -> Self {
+        self.focused = Some(label.to_string());
+        self
+    }
+
+    fn has(&self, label: &str, state: ElementState) -> bool {
+        match state {
+            ElementState::Hover => self.hovered.iter().any(|l| l == label),
+            ElementState::Focus => self.focused.as_deref() == Some(label),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Combinator {
+    /// Any ancestor, written as whitespace
+    Descendant,
+
+    /// The parent, written as `>`
+    Child,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+/// Conditions on a single element, such as `directional.user:hover`
+pub struct CompoundSelector {
+    pub(crate) kind: Option<String>,
+    pub(crate) labels: Vec<String>,
+    pub(crate) states: Vec<ElementState>,
+}
+
+impl CompoundSelector {
+    /// Matches every element
+    pub fn new() -> CompoundSelector {
+        CompoundSelector::default()
+    }
+
+    /// The kind of the element, such as `directional` or the name of an extern kind
+    pub fn kind(mut self, kind: impl ToString) -> Self {
+        self.kind = Some(kind.to_string());
+        self
+    }
+
+    pub fn label(mut self, label: impl ToString) -> Self {
+        self.labels.push(label.to_string());
+        self
+    }
+
+    /// Only matches labelled elements, since states are tracked by label
+    pub fn state(mut self, state: ElementState) -> Self {
+        self.states.push(state);
+        self
+    }
+
+    fn matches(&self, subject: &Subject, state: &StyleState) -> bool {
+        let label = subject.label.as_deref();
+
+        self.kind.as_ref().is_none_or(|k| *k == subject.kind)
+            && self.labels.iter().all(|l| Some(l.as_str()) == label)
+            && self
+                .states
+                .iter()
+                .all(|s| label.is_some_and(|l| state.has(l, *s)))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Compound selectors joined by combinators, such as `.sidebar > .user`,
+/// which match the element that the last compound selector matches
+pub struct Selector {
+    /// Every compound selector with the combinator that joins it to the one before it
+    pub(crate) parts: Vec<(Combinator, CompoundSelector)>,
+}
+
+impl Selector {
+    pub fn new(subject: CompoundSelector) -> Selector {
+        Selector {
+            parts: vec![(Combinator::Descendant, subject)],
+        }
+    }
+
+    /// Matches descendants of the elements matched so far
+    pub fn descendant(mut self, subject: CompoundSelector) -> Self {
+        self.parts.push((Combinator::Descendant, subject));
+        self
+    }
+
+    /// Matches children of the elements matched so far
+    pub fn child(mut self, subject: CompoundSelector) -> Self {
+        self.parts.push((Combinator::Child, subject));
+        self
+    }
+
+    /// The number of labels and states, then the number of kinds, which are compared in order
+    pub fn specificity(&self) -> (usize, usize) {
+        self.parts.iter().fold((0, 0), |(labels, kinds), (_, c)| {
+            (
+                labels + c.labels.len() + c.states.len(),
+                kinds + c.kind.iter().count(),
+            )
+        })
+    }
+
+    fn matches(&self, subject: &Subject, ancestors: &[Subject], state: &StyleState) -> bool {
+        self.matches_part(self.parts.len() - 1, subject, ancestors, state)
+    }
+
+    fn matches_part(
+        &self,
+        index: usize,
+        subject: &Subject,
+        ancestors: &[Subject],
+        state: &StyleState,
+    ) -> bool {
+        let (combinator, compound) = &self.parts[index];
+
+        if !compound.matches(subject, state) {
+            return false;
+        }
+
+        if index == 0 {
+            return true;
+        }
+
+        match combinator {
+            Combinator::Child => match ancestors.split_last() {
+                Some((parent, rest)) => self.matches_part(index - 1, parent, rest, state),
+                None => false,
+            },
+            Combinator::Descendant => (0..ancestors.len())
+                .rev()
+                .any(|i| self.matches_part(index - 1, &ancestors[i], &ancestors[..i], state)),
+        }
+    }
+}
+
+/// What selectors are matched against
+struct Subject {
+    kind: String,
+    label: Option<String>,
+}
+
+impl Subject {
+    fn new(element: &Element) -> Subject {
+        let kind = match &element.kind {
+            ElementKind::Directional(_) => "directional".to_string(),
+            ElementKind::Relational(_) => "relational".to_string(),
+            ElementKind::Text(_) => "text".to_string(),
+            ElementKind::Inline(_) => "inline".to_string(),
+            ElementKind::Extern(e) => e.kind_name(),
+            ElementKind::None => "none".to_string(),
+        };
+
+        Subject {
+            kind,
+            label: element.label.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+/// Layout properties set by a rule, where the ones that aren't set are left as they are
+pub struct StyleProperties {
+    pub width: Option<SizingUnit>,
+    pub height: Option<SizingUnit>,
+    pub padding: Option<Padding>,
+    pub alignment: Option<Alignment>,
+
+    /// Spacing between the children of directional elements
+    pub spacing: Option<Float>,
+}
+
+impl StyleProperties {
+    fn apply(&self, element: &mut Element) {
+        if let Some(width) = &self.width {
+            element.sizing.width = width.clone();
+        }
+
+        if let Some(height) = &self.height {
+            element.sizing.height = height.clone();
+        }
+
+        if let Some(padding) = self.padding {
+            element.padding = padding;
+        }
+
+        if let Some(alignment) = self.alignment {
+            element.alignment = alignment;
+        }
+
+        if let (Some(spacing), ElementKind::Directional(d)) = (self.spacing, &mut element.kind) {
+            d.set_spacing(spacing);
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Rule {
+    pub(crate) selector: Selector,
+    pub(crate) properties: StyleProperties,
+}
+
+impl Rule {
+    pub fn new(selector: Selector) -> Rule {
+        Rule {
+            selector,
+            properties: StyleProperties::default(),
+        }
+    }
+
+    pub fn selector(&self) -> &Selector {
+        &self.selector
+    }
+
+    pub fn properties(&self) -> &StyleProperties {
+        &self.properties
+    }
+
+    /// Sets the sizing from units such as `Stretch` or `40px`, panicking if either can't be parsed
+    pub fn sizing(self, width: &str, height: &str) -> Self {
+        let width = parse_sizing_unit(width).unwrap_or_else(|error| panic!("{}", error));
+        let height = parse_sizing_unit(height).unwrap_or_else(|error| panic!("{}", error));
+
+        self.width(width).height(height)
+    }
+
+    pub fn width(mut self, width: SizingUnit) -> Self {
+        self.properties.width = Some(width);
+        self
+    }
+
+    pub fn height(mut self, height: SizingUnit) -> Self {
+        self.properties.height = Some(height);
+        self
+    }
+
+    pub fn padding(mut self, padding: Padding) -> Self {
+        self.properties.padding = Some(padding);
+        self
+    }
+
+    pub fn pad_all(self, value: Float) -> Self {
+        self.padding(Padding::new(value, value, value, value))
+    }
+
+    pub fn align(mut self, horizontal: AlignUnit, vertical: AlignUnit) -> Self {
+        self.properties.alignment = Some(Alignment::new(horizontal, vertical));
+        self
+    }
+
+    pub fn spacing(mut self, spacing: Float) -> Self {
+        self.properties.spacing = Some(spacing);
+        self
+    }
+}
+
+#[derive(Debug)]
+/// The properties an element was built with, which are restored before styles are applied again
+pub(crate) struct BaseStyle {
+    sizing: Sizing,
+    padding: Padding,
+    alignment: Alignment,
+    spacing: Option<Float>,
+}
+
+#[derive(Clone, Debug, Default)]
+/// Rules that set the properties of the elements their selectors match.
+/// The properties of rules with a higher specificity win, then those of rules added later,
+/// and any property set by a rule replaces the one the element was built with.
+pub struct Stylesheet {
+    pub(crate) rules: Vec<Rule>,
+}
+
+impl Stylesheet {
+    pub fn new() -> Stylesheet {
+        Stylesheet::default()
+    }
+
+    pub fn rule(mut self, rule: Rule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    pub fn rules(&self) -> &Vec<Rule> {
+        &self.rules
+    }
+
+    /// Resolves the styles of the element tree in the given state. It can be applied again
+    /// when the state changes, since elements are restored to how they were built first.
+    pub fn apply(&self, element: &mut Element, state: &StyleState) {
+        self.apply_with(element, &mut Vec::new(), state);
+    }
+
+    fn apply_with(&self, element: &mut Element, ancestors: &mut Vec<Subject>, state: &StyleState) {
+        Stylesheet::restore(element);
+
+        let subject = Subject::new(element);
+
+        let mut matching: Vec<(usize, &Rule)> = self
+            .rules
+            .iter()
+            .enumerate()
+            .filter(|(_, rule)| rule.selector.matches(&subject, ancestors, state))
+            .collect();
+
+        matching.sort_by_key(|(index, rule)| (rule.selector.specificity(), *index));
+
+        for (_, rule) in matching {
+            rule.properties.apply(element);
+        }
+
+        ancestors.push(subject);
+
+        for child in element.children.iter_mut() {
+            self.apply_with(child, ancestors, state);
+        }
+
+        ancestors.pop();
+    }
+
+    /// Restores the element to how it was built, remembering it the first time
+    fn restore(element: &mut Element) {
+        let spacing = match &mut element.kind {
+            ElementKind::Directional(d) => Some(d),
+            _ => None,
+        };
+
+        match &element.base_style {
+            Some(base) => {
+                element.sizing = base.sizing.clone();
+                element.padding = base.padding;
+                element.alignment = base.alignment;
+
+                if let (Some(d), Some(value)) = (spacing, base.spacing) {
+                    d.set_spacing(value);
+                }
+            }
+            None => {
+                element.base_style = Some(Box::new(BaseStyle {
+                    sizing: element.sizing.clone(),
+                    padding: element.padding,
+                    alignment: element.alignment,
+                    spacing: spacing.map(|d| d.spacing()),
+                }))
+            }
+        }
+    }
+}
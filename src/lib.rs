@@ -1,9 +1,11 @@
-#![feature(box_syntax)]
-
 mod layout;
 pub use layout::*;
 
 mod parsing;
+pub use parsing::*;
+
+mod text;
+pub use text::*;
 
 #[cfg(test)]
 mod mock;
@@ -1,13 +1,15 @@
-#![feature(box_syntax)]
-
 #[allow(dead_code)]
 mod layout;
 use std::time::Instant;
 
 mod parsing;
+pub use parsing::*;
 
 pub use layout::*;
 
+mod text;
+pub use text::*;
+
 mod mock;
 pub use mock::*;
 
@@ -16,7 +18,8 @@ use mock::layout::*;
 use gl::types::*;
 
 pub mod graphics;
-use graphics::rectangle_shape::{Float4, RGBATexture, RectangleShape};
+use graphics::rectangle_shape::{Float4, RectangleShape};
+use graphics::text_renderer::TextRenderer;
 
 fn create_rect_shape(rect: &layout::Rect) -> RectangleShape {
     let (width, height, x, y) = rect.as_tuple();
@@ -33,6 +36,44 @@ fn create_rect_shape(rect: &layout::Rect) -> RectangleShape {
     )
 }
 
+/// A banner along the top of the window that shows why the layout couldn't be loaded
+fn error_overlay(error: &ReloadError, style: &TextStyle) -> Element {
+    ElementBuilder::new()
+        .text(Text::new(error, style.clone()))
+        .sizing("Stretch", "Collapse")
+        .pad_all(12.)
+        .build()
+}
+
+/// Collects the labels of the elements under the cursor, from the outermost to the innermost
+fn labels_at(
+    element: &Element,
+    calculated: &CalculatedElement,
+    cursor: Position,
+    labels: &mut Vec<String>,
+) {
+    let (width, height, x, y) = calculated.rect.as_tuple();
+
+    if cursor.x >= x && cursor.y >= y && cursor.x < x + width && cursor.y < y + height {
+        labels.extend(element.label().cloned());
+    }
+
+    for (child, calculated) in element.children.iter().zip(&calculated.children) {
+        labels_at(child, calculated, cursor, labels);
+    }
+}
+
+fn style_state(hovered: &[String], focused: Option<&String>) -> StyleState {
+    let state = hovered
+        .iter()
+        .fold(StyleState::new(), |state, label| state.hover(label));
+
+    match focused {
+        Some(label) => state.focus(label),
+        None => state,
+    }
+}
+
 // // Returns a testing rect shape
 // fn create_rect_shape<'a>(rect: layout::Rect) -> RectangleShape<'a> {
 //     let (width, height, x, y) = rect.to_tuple();
@@ -50,7 +91,7 @@ fn create_rect_shape(rect: &layout::Rect) -> RectangleShape {
 //     rect
 // }
 
-use glutin::event::{Event, WindowEvent};
+use glutin::event::{Event, MouseButton, WindowEvent};
 use glutin::event_loop::{ControlFlow, EventLoop};
 use glutin::window::WindowBuilder;
 use glutin::ContextBuilder;
@@ -82,7 +123,13 @@ fn main() {
         windowed_context.get_pixel_format()
     );
 
-    let element = test_layout();
+    let stylesheet = stylesheet();
+    let mut element = test_layout();
+    stylesheet.apply(&mut element, &StyleState::new());
+
+    // The labels of the elements under the cursor, and of the one that was clicked last
+    let mut hovered: Vec<String> = Vec::new();
+    let mut focused: Option<String> = None;
 
     println!(
         "{:?}",
@@ -120,90 +167,105 @@ fn main() {
 
     // some font rendering tests
 
-    let font_data = std::fs::read("./assets/fonts/DejaVuSans.ttf").unwrap();
-    let font = rusttype::Font::try_from_bytes(&font_data).expect("Error constructing font");
+    let mut fonts = FontRegistry::new();
+    let font = fonts.load_directory("./assets/fonts").and_then(|loaded| {
+        for error in &loaded.failed {
+            eprintln!("Skipped a font: {}", error);
+        }
 
-    let test_string = "kroße krabbe pizza ist die pizza für dich und mich".chars();
-    let mut test_chars = test_string.map(|c| (c, font.glyph(c)));
+        fonts.collection(&[FontDescriptor::new("DejaVu Sans")])
+    });
 
-    let scale = rusttype::Scale::uniform(24.);
-    let start = rusttype::vector(200., 200.);
+    let font = match font {
+        Ok(font) => font,
+        Err(error) => {
+            eprintln!("Error loading fonts: {}", error);
+            return;
+        }
+    };
 
     const RED: Float4 = (1.0, 0.0, 0.0, 1.0);
     const TRANSPARENT: Float4 = (0.0, 0.0, 0.0, 0.0);
 
-    let MAX_WIDTH = 140.;
-
-    // last point in the string where a line-wrap is desirable
-    let mut last_breakpoint = None;
-    // stored glyphs
-    let mut calculated_glyphs = Vec::new();
+    const BLUE: Float4 = (0.3, 0.5, 1.0, 1.0);
+    const ERROR: Float4 = (0.6, 0.1, 0.1, 0.9);
 
-    // last glyph on this line, for calculating kerning
-    let mut last_glyph = None;
-    let mut x_offset = 0.;
-    let mut y_offset = 0.;
+    // Layouts given as `rust-gui layout.xml [style.css]` are loaded again whenever they change
+    let mut args = std::env::args().skip(1);
+    let mut reload = args.next().map(|layout| {
+        let parser = MarkupParser::new().text_style(TextStyle::new(font.clone(), 16.));
+        let reload = HotReload::new(parser, layout);
 
-    while let Some((c, g)) = test_chars.next() {
-        if c.is_whitespace() {
-            last_breakpoint = Some((test_chars.clone(), calculated_glyphs.len()));
+        match args.next() {
+            Some(stylesheet) => reload.stylesheet(stylesheet),
+            None => reload,
         }
+    });
 
-        let g = g.scaled(scale);
-        if let Some(last) = last_glyph {
-            x_offset += font.pair_kerning(scale, last, g.id());
-        }
+    let error_style = TextStyle::new(font.clone(), 16.).color((1.0, 1.0, 1.0, 1.0));
+    let mut overlay = None;
 
-        let w = g.h_metrics().advance_width;
-        let next = g.positioned(start + rusttype::point(x_offset, y_offset));
-        last_glyph = Some(next.id());
-        x_offset += w;
-        calculated_glyphs.push(next);
-
-        if x_offset > MAX_WIDTH {
-            x_offset = 0.;
-            let v_metrics = font.v_metrics(scale);
-            y_offset += (v_metrics.ascent - v_metrics.descent) + v_metrics.line_gap;
-            last_glyph = None;
-            if let Some((last, count)) = last_breakpoint.take() {
-                test_chars = last;
-                calculated_glyphs.truncate(count);
-            }
+    if let Some(reload) = &reload {
+        match reload.load() {
+            Ok(loaded) => element = loaded,
+            Err(error) => overlay = Some(error_overlay(&error, &error_style)),
         }
     }
 
-    let text = {
-        let rects = calculated_glyphs
-            .iter()
-            .flat_map(|g| {
-                let b = g.pixel_bounding_box()?;
-                let mut buffer: Vec<(u8, u8, u8, u8)> =
-                    vec![(0, 0, 0, 0); b.width() as usize * b.height() as usize];
-
-                g.draw(|x, y, v| {
-                    buffer[x as usize + y as usize * b.width() as usize] =
-                        (255, 0, 0, (v * 255.) as u8);
-                });
-
-                Some(RectangleShape::new(
-                    b.width() as f32,
-                    b.height() as f32,
-                    b.min.x as f32,
-                    b.min.y as f32,
-                    None,
-                    RED,
-                    TRANSPARENT,
-                    Some(RGBATexture::new(
-                        b.width() as usize,
-                        b.height() as usize,
-                        buffer.as_ptr() as *const u8,
-                    )),
-                ))
-            })
-            .collect::<Vec<_>>();
-
-        rects
-    };
+    let style = TextStyle::new(font, 24.).color(RED);
+    let text_element = ElementBuilder::new()
+        .text(Text::rich(vec![
+            TextSpan::new("kroße krabbe ", style.clone()),
+            TextSpan::new("pizza", style.clone().color(BLUE).underline()).link("pizza"),
+            TextSpan::new(" ist die pizza für dich und mich", style),
+        ]))
+        .sizing("Fixed:140", "Collapse")
+        .build();
+
+    let text_bounds = Rect::new(140., 800., 200., 200.);
+    let calculated_text = text_element.calculate(text_bounds.clone(), text_bounds);
+    let origin = calculated_text.rect.position;
+    let text_layout = calculated_text.text.unwrap();
+
+    let decorations = text_layout
+        .runs
+        .iter()
+        .flat_map(|run| {
+            run.underline
+                .iter()
+                .chain(&run.strikethrough)
+                .map(move |r| (r, run.color))
+        })
+        .map(|(decoration, color)| {
+            RectangleShape::new(
+                decoration.dimensions.width,
+                decoration.dimensions.height,
+                decoration.position.x + origin.x,
+                decoration.position.y + origin.y,
+                None,
+                color,
+                TRANSPARENT,
+                None,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let text_shader = graphics::shader::Shader::new();
+    text_shader
+        .attach(
+            &std::fs::read_to_string("assets/text.vs").unwrap(),
+            gl::VERTEX_SHADER,
+        )
+        .unwrap();
+    text_shader
+        .attach(
+            &std::fs::read_to_string("assets/text.fs").unwrap(),
+            gl::FRAGMENT_SHADER,
+        )
+        .unwrap();
+    text_shader.compile().unwrap();
+
+    let mut text_renderer = TextRenderer::new(1024, 4);
 
     el.run(move |event, _, control_flow| {
         *control_flow = ControlFlow::Wait;
@@ -224,10 +286,55 @@ fn main() {
                     }
                 }
                 WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+
+                // Layouts that are reloaded from a file keep their own stylesheet in the
+                // default state, so only the demo layout is restyled
+                WindowEvent::CursorMoved { position, .. } if reload.is_none() => {
+                    let rect = Rect::new(size.width as f32, size.height as f32, 0.0, 0.0);
+                    let cursor = Position::new(position.x as Float, position.y as Float);
+
+                    let mut labels = Vec::new();
+                    labels_at(
+                        &element,
+                        &element.calculate(rect.clone(), rect),
+                        cursor,
+                        &mut labels,
+                    );
+
+                    if labels != hovered {
+                        hovered = labels;
+                        stylesheet.apply(&mut element, &style_state(&hovered, focused.as_ref()));
+                    }
+                }
+                WindowEvent::CursorLeft { .. } if reload.is_none() => {
+                    hovered.clear();
+                    stylesheet.apply(&mut element, &style_state(&hovered, focused.as_ref()));
+                }
+                WindowEvent::MouseInput {
+                    state: glutin::event::ElementState::Pressed,
+                    button: MouseButton::Left,
+                    ..
+                } if reload.is_none() => {
+                    focused = hovered.last().cloned();
+                    stylesheet.apply(&mut element, &style_state(&hovered, focused.as_ref()));
+                }
                 _ => (),
             },
             Event::MainEventsCleared => {
                 // Application update code.
+                // The previous layout stays around until one without errors is loaded
+                match reload.as_mut().and_then(|r| r.poll()) {
+                    Some(Ok(loaded)) => {
+                        element = loaded;
+                        overlay = None;
+                    }
+                    Some(Err(error)) => {
+                        eprintln!("Error reloading the layout: {}", error);
+                        overlay = Some(error_overlay(&error, &error_style));
+                    }
+                    None => (),
+                }
+
                 windowed_context.window().request_redraw();
             }
             Event::RedrawRequested(_) => {
@@ -237,14 +344,22 @@ fn main() {
                 time = Instant::now();
 
                 if t > 1000 {
-                    println!("FPS: {}", i);
+                    let stats = TextCache::shared().stats();
+
+                    println!(
+                        "FPS: {}, text cache: {} texts, {} KiB, {:.1}% hits",
+                        i,
+                        stats.entries,
+                        stats.bytes / 1024,
+                        stats.hit_rate() * 100.
+                    );
                     i = 0;
                     t = 0;
                 }
 
                 let rect = Rect::new(size.width as f32, size.height as f32, 0.0, 0.0);
 
-                let calculated = element.calculate(rect.clone(), rect);
+                let calculated = element.calculate(rect.clone(), rect.clone());
                 let flattened = calculated.flatten();
 
                 unsafe {
@@ -256,15 +371,41 @@ fn main() {
                 box_shader.setUniform("fb_width", size.width as f32);
                 box_shader.setUniform("fb_height", size.height as f32);
 
+                text_renderer.begin_frame();
+                text_renderer.queue(&text_layout, origin);
+
                 for child in flattened {
                     let rect = create_rect_shape(&child.rect);
                     rect.draw(&box_shader);
+
+                    if let Some(text) = &child.text {
+                        text_renderer.queue(text, child.rect.position);
+                    }
                 }
 
-                for r in text.iter() {
+                for r in decorations.iter() {
                     r.draw(&box_shader);
                 }
 
+                if let Some(overlay) = &overlay {
+                    let calculated = overlay.calculate(rect.clone(), rect);
+                    let (width, height, x, y) = calculated.rect.as_tuple();
+
+                    RectangleShape::new(width, height, x, y, None, ERROR, TRANSPARENT, None)
+                        .draw(&box_shader);
+
+                    for child in calculated.flatten() {
+                        if let Some(text) = &child.text {
+                            text_renderer.queue(text, child.rect.position);
+                        }
+                    }
+                }
+
+                text_shader.bind();
+                text_shader.setUniform("fb_width", size.width as f32);
+                text_shader.setUniform("fb_height", size.height as f32);
+                text_renderer.draw();
+
                 windowed_context.swap_buffers().unwrap();
             }
             _ => (),
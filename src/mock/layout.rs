@@ -1,72 +1,62 @@
-use std::rc::Rc;
-
 use crate::{
-    parsing::parse_sizing_unit, AlignUnit, Alignment, CalculatedElement, Direction, Element,
-    ElementBuilder, ElementKind, ElementLayout, Padding, Sizing,
+    parse_stylesheet, AlignUnit, Breakpoint, Direction, Element, ElementBuilder, FontDescriptor,
+    FontHandle, FontRegistry, Stylesheet, Text, TextSpan, TextStyle, WhiteSpace,
 };
 use AlignUnit::*;
 use Direction::*;
 
 use rand::{thread_rng, Rng};
-use rusttype::Font;
 
 const LOREM_IPSUM: &'static str = "Suspendisse placerat lectus ultrices ipsum consectetur, et vestibulum augue luctus. Proin condimentum laoreet lacus volutpat accumsan. Aliquam commodo nulla eu elit porta, ac interdum nunc dictum. Maecenas et dui vel turpis feugiat ultrices sit amet ut augue. Vivamus enim orci, eleifend sit amet libero ut, blandit tristique orci. Donec iaculis iaculis ante, at accumsan enim congue vitae. Vivamus nec dapibus quam. Nulla lectus purus, fringilla sed eros et, hendrerit cursus sem. Integer euismod tellus turpis, at sagittis eros cursus vitae. Nulla facilisi. Ut id orci a nisl finibus tristique eu nec dui. Vestibulum vestibulum nunc quis varius blandit. Integer at erat non dolor pellentesque blandit nec vitae lorem. Praesent bibendum erat imperdiet pretium feugiat.";
 
+const SNIPPET: &'static str = "fn main() {\n\tlet krabbe = Krabbe::new();\n\tkrabbe.pizza();\n}";
+
+/// The look and spacing of every sidebar item
+const STYLESHEET: &'static str = "
+.server_sidebar > .server { width: 40px; height: 40px }
+.server:hover { padding: 4 }
+
+.sidebar > .user { width: Stretch; height: Collapse; spacing: 8 }
+.user > .avatar { width: 35px; height: 35px }
+.user > .details { width: Stretch; height: Collapse; spacing: 4; align: start,middle }
+";
+
+pub fn stylesheet() -> Stylesheet {
+    parse_stylesheet(STYLESHEET).expect("Error parsing the stylesheet")
+}
+
 pub fn header() -> Element {
-    ElementBuilder::new()
-        .directional(Horizontal, 16.)
-        .sizing("Stretch", "Fixed:60")
-        .label("header")
-        .children(vec![
-            ElementBuilder::new()
-                .directional(Horizontal, 0.)
-                .sizing("Fixed:50", "Stretch")
-                .build(),
-            ElementBuilder::new()
-                .directional(Horizontal, 0.)
-                .sizing("Fixed:300", "Stretch")
-                .align(End, Start)
-                .build(),
-            ElementBuilder::new()
-                .directional(Horizontal, 0.)
-                .sizing("Fixed:70", "Stretch")
-                .build(),
-            ElementBuilder::new()
-                .directional(Horizontal, 0.)
-                .sizing("Fixed:70", "Stretch")
-                .align(Middle, Start)
-                .build(),
-            ElementBuilder::new()
-                .directional(Horizontal, 0.)
-                .sizing("Fixed:100", "Stretch")
-                .align(Middle, Start)
-                .build(),
-            ElementBuilder::new()
-                .directional(Horizontal, 0.)
-                .sizing("Fixed:80", "Stretch")
-                .build(),
-        ])
-        .pad_all(16.0)
-        .build()
+    crate::layout! {
+        directional(Horizontal, 16.) {
+            width: Stretch;
+            height: 60px;
+            label: "header";
+            pad_all: 16.0;
+            breakpoint: Breakpoint::viewport().max_width(1000.).spacing(8.).pad_all(8.);
+
+            directional(Horizontal, 0.) { width: 50px; height: Stretch; }
+            directional(Horizontal, 0.) { width: 300px; height: Stretch; align: End, Start; }
+            directional(Horizontal, 0.) { width: 70px; height: Stretch; }
+            directional(Horizontal, 0.) { width: 70px; height: Stretch; align: Middle, Start; }
+            directional(Horizontal, 0.) { width: 100px; height: Stretch; align: Middle, Start; }
+            directional(Horizontal, 0.) { width: 80px; height: Stretch; }
+        }
+    }
 }
 
 fn server_sidebar() -> Element {
-    let children = (0..7)
-        .map(|_| {
-            ElementBuilder::new()
-                .directional(Horizontal, 0.)
-                .sizing("Fixed:40", "Fixed:40")
-                .build()
-        })
-        .collect();
-
-    ElementBuilder::new()
-        .directional(Vertical, 16.)
-        .sizing("Collapse", "Stretch")
-        .children(children)
-        .pad_all(16.)
-        .label("server_sidebar")
-        .build()
+    crate::layout! {
+        directional(Vertical, 16.) {
+            width: Collapse;
+            height: Stretch;
+            pad_all: 16.;
+            label: "server_sidebar";
+
+            for _ in 0..7 {
+                directional(Horizontal, 0.) { label: "server"; }
+            }
+        }
+    }
 }
 
 pub fn user_sidebar() -> Element {
@@ -75,16 +65,43 @@ pub fn user_sidebar() -> Element {
         .sizing("Fixed:240", "Stretch")
         .label("sidebar")
         .pad_all(16.)
+        // Narrow windows only show the avatars of the users
+        .breakpoint(
+            Breakpoint::viewport()
+                .max_width(1000.)
+                .sizing("67px", "Stretch"),
+        )
         .children(vec![user(), user(), user(), user(), user(), user(), user()])
         .build()
 }
 
+const NAMES: [&'static str; 7] = [
+    "Enitoni",
+    "Nyrox",
+    "krabbe",
+    "a user with an unreasonably long display name",
+    "pizza für dich und mich",
+    "ねこ",
+    "שלום krabbe",
+];
+
+/// A single line of text that is truncated with an ellipsis
+fn single_line(content: &str) -> ElementBuilder {
+    let style = FONT.with(|f| TextStyle::new(f.clone(), 14.));
+
+    ElementBuilder::new()
+        .text(Text::new(content, style).max_lines(1).ellipsis("…"))
+        .sizing("Stretch", "Collapse")
+}
+
 fn user() -> Element {
     let mut rng = thread_rng();
     let range = &rng.gen_range(1..=2);
 
-    let children = (0..*range)
-        .map(|_| {
+    let name = single_line(NAMES[rng.gen_range(0..NAMES.len())]).build();
+
+    let children = std::iter::once(name)
+        .chain((0..*range).map(|_| {
             let width = &rng.gen_range(0.2..1.0);
             let width = format!("Percent:{},_,_", width);
 
@@ -92,22 +109,20 @@ fn user() -> Element {
                 .directional(Horizontal, 0.)
                 .sizing(&width, "Fixed:12")
                 .build()
-        })
+        }))
         .collect();
 
     ElementBuilder::new()
-        .directional(Horizontal, 8.)
-        .sizing("Stretch", "Collapse")
+        .directional(Horizontal, 0.)
         .children(vec![
             ElementBuilder::new()
                 .directional(Horizontal, 0.)
-                .sizing("Fixed:35", "Fixed:35")
+                .label("avatar")
                 .build(),
             ElementBuilder::new()
-                .directional(Vertical, 4.)
-                .sizing("Stretch", "Collapse")
+                .directional(Vertical, 0.)
                 .children(children)
-                .align(Start, Middle)
+                .label("details")
                 .build(),
         ])
         .label("user")
@@ -120,9 +135,14 @@ fn channel_list() -> Element {
 
     let children = (0..*amount)
         .map(|_| {
+            let name = NAMES[rng.gen_range(0..NAMES.len())];
+
             ElementBuilder::new()
                 .directional(Horizontal, 0.)
                 .sizing("Stretch", "Fixed:33")
+                .children(vec![single_line(&format!("# {}", name))
+                    .align(Start, Middle)
+                    .build()])
                 .build()
         })
         .collect();
@@ -145,115 +165,84 @@ fn main_sidebar() -> Element {
 }
 
 thread_local! {
-    pub static FONT: Rc<Font<'static>> = Rc::new( {
-
-        let font_data = std::fs::read("./assets/fonts/DejaVuSans.ttf").unwrap();
-        rusttype::Font::try_from_vec(font_data).expect("Error constructing font")
-    }
-    )
-}
-
-struct TextLayoutElement {
-    font: Rc<Font<'static>>,
-    text: String,
-    scale: rusttype::Scale,
+    pub static FONT: FontHandle = {
+        let mut registry = FontRegistry::new();
+
+        registry
+            .load_directory("./assets/fonts")
+            .expect("Error loading fonts");
+        registry
+            .get(&FontDescriptor::new("DejaVu Sans"))
+            .expect("Error finding font")
+    };
+
+    /// A font with other glyphs and a larger line height, for testing fallback fonts
+    pub static FALLBACK_FONT: FontHandle = {
+        let mut registry = FontRegistry::new();
+
+        registry
+            .load_directory("./assets/fonts")
+            .expect("Error loading fonts");
+        registry
+            .get(&FontDescriptor::new("DejaVu Math TeX Gyre"))
+            .expect("Error finding font")
+    };
 }
 
-impl ElementLayout for TextLayoutElement {
-    fn kind_name(&self) -> String {
-        "Text Element".into()
-    }
-
-    fn calculate(
-        &self,
-        element: &Element,
-        available_bounds: crate::Rect,
-        outer_bounds: crate::Rect,
-    ) -> CalculatedElement {
-        let mut chars = self.text.chars().map(|c| (c, self.font.glyph(c)));
-        let max_width = available_bounds.dimensions.width;
-        let start = available_bounds.position;
-        let start = rusttype::vector(start.x, start.y);
-
-        let mut last_breakpoint = None;
-        // stored glyphs
-        let mut calculated_glyphs = Vec::new();
-
-        // last glyph on this line, for calculating kerning
-        let mut last_glyph = None;
-        let mut x_offset = 0.;
-        let mut y_offset = 0.;
-        let mut x_max: f32 = 0.;
-
-        let v_metrics = self.font.v_metrics(self.scale);
-        let line_height = (v_metrics.ascent - v_metrics.descent) + v_metrics.line_gap;
-
-        while let Some((c, g)) = chars.next() {
-            if c.is_whitespace() {
-                last_breakpoint = Some((chars.clone(), calculated_glyphs.len()));
-            }
-
-            let g = g.scaled(self.scale);
-            if let Some(last) = last_glyph {
-                x_offset += self.font.pair_kerning(self.scale, last, g.id());
-            }
-
-            let w = g.h_metrics().advance_width;
-            let next = g.positioned(start + rusttype::point(x_offset, y_offset));
-            last_glyph = Some(next.id());
-            x_offset += w;
-            calculated_glyphs.push(next);
-
-            if x_offset > max_width {
-                x_offset = 0.;
-                y_offset += line_height;
-                last_glyph = None;
-                if let Some((last, count)) = last_breakpoint.take() {
-                    chars = last;
-                    calculated_glyphs.truncate(count);
-                }
-            }
-
-            x_max = x_max.max(x_offset);
-        }
-
-        CalculatedElement::from_rect(crate::Rect::new(
-            x_max,
-            y_offset + line_height,
-            available_bounds.position.x,
-            available_bounds.position.y,
-        ))
-    }
-}
-
-fn message() -> Element {
+pub fn message() -> Element {
     let mut rng = thread_rng();
     let amount = &rng.gen_range(2..6);
 
-    let children = (0..*amount)
+    let mut children: Vec<Element> = (0..*amount)
         .map(|_| {
             let text_amount: usize = rng.gen_range(20..240);
+            let name = NAMES[rng.gen_range(0..NAMES.len())];
+
+            let style = FONT.with(|f| TextStyle::new(f.clone(), 14.));
+            let mention = style.clone().color((0.45, 0.55, 1.0, 1.0));
+            let code = style.clone().color((1.0, 0.6, 0.4, 1.0));
+
+            // Messages mention a user and quote some code in a chip in between the text
+            let spans = vec![
+                TextSpan::new(format!("@{}", name), mention).link(name),
+                TextSpan::new(" ", style.clone()),
+                TextSpan::new(&LOREM_IPSUM[0..text_amount / 2], style.clone()),
+                TextSpan::object(style.clone()),
+                TextSpan::new(&LOREM_IPSUM[text_amount / 2..text_amount], style),
+            ];
+
+            let chip = ElementBuilder::new()
+                .text(Text::new("krabbe.pizza()", code).white_space(WhiteSpace::Pre))
+                .sizing("Collapse", "Collapse")
+                .label("Code")
+                .build();
 
-            let text = FONT.with(|f| TextLayoutElement {
-                font: f.clone(),
-                scale: rusttype::Scale::uniform(14.),
-                text: (&LOREM_IPSUM)[0..text_amount].to_owned(),
-            });
-
-            Element {
-                kind: ElementKind::Extern(box text),
-                sizing: Sizing::new(
-                    parse_sizing_unit("Collapse").unwrap(),
-                    parse_sizing_unit("Collapse").unwrap(),
-                ),
-                padding: Padding::empty(),
-                alignment: Alignment::new(Start, Start),
-                children: Vec::new(),
-                label: Some("Text".to_owned()),
-            }
+            ElementBuilder::new()
+                .inline(Text::rich(spans))
+                .sizing("Collapse", "Collapse")
+                .label("Text")
+                .children(vec![chip])
+                .build()
         })
         .collect();
 
+    // Some messages end with a code snippet, which keeps its indentation and lines
+    if rng.gen_bool(0.3) {
+        let style = FONT.with(|f| TextStyle::new(f.clone(), 13.));
+
+        children.push(
+            ElementBuilder::new()
+                .text(
+                    Text::new(SNIPPET, style.color((1.0, 0.6, 0.4, 1.0)))
+                        .white_space(WhiteSpace::Pre)
+                        .tab_size(4.),
+                )
+                .sizing("Collapse", "Collapse")
+                .label("Code")
+                .build(),
+        );
+    }
+
     ElementBuilder::new()
         .directional(Horizontal, 8.)
         .sizing("Stretch", "Collapse")
@@ -298,7 +287,7 @@ pub fn chat_box() -> Element {
         .children(vec![
             ElementBuilder::new()
                 .directional(Horizontal, 0.)
-                .sizing("Percent:0.5,_,_", "Stretch")
+                .sizing("50%", "Stretch")
                 .label("percento")
                 .build(),
             ElementBuilder::new()
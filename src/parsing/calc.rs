@@ -0,0 +1,260 @@
+use crate::{CalcExpression, Float, LayoutContext};
+
+use super::{Failure, ParsingErrorKind::InvalidExpression};
+
+/// Whether an expression results in a size or a plain number
+#[derive(Clone, Copy, PartialEq)]
+enum Type {
+    Number,
+    Length,
+}
+
+/// Parses an expression such as `calc(100% - 2 * (1.5rem + 4px))`, where sizes can be added
+/// and subtracted, and multiplied and divided by numbers. Units are `px`, `%` and `rem`,
+/// and `content` and `available` are the size of the content and the available bounds.
+pub(super) fn calc_expression(input: &str) -> Result<CalcExpression, Failure> {
+    let inner = match input.strip_suffix(')') {
+        Some(inner) if input.starts_with("calc(") => inner,
+        _ => return Err((InvalidExpression, 0..input.len())),
+    };
+
+    let mut parser = Parser {
+        input: inner,
+        position: "calc(".len(),
+    };
+
+    let (expression, kind) = parser.sum()?;
+    parser.skip_whitespace();
+
+    if parser.position < inner.len() {
+        return Err((InvalidExpression, parser.position..inner.len()));
+    }
+
+    match kind {
+        Type::Length => Ok(expression),
+        Type::Number => Err((InvalidExpression, 0..input.len())),
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.input[self.position..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.input[self.position..];
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    /// Takes characters while they match, returning where they started
+    fn take_while(&mut self, f: impl Fn(usize, char) -> bool) -> usize {
+        let start = self.position;
+        let rest = &self.input[start..];
+        let length = rest
+            .char_indices()
+            .find(|(i, c)| !f(*i, *c))
+            .map_or(rest.len(), |(i, _)| i);
+
+        self.position += length;
+        start
+    }
+
+    fn sum(&mut self) -> Result<(CalcExpression, Type), Failure> {
+        let start = self.position;
+        let (mut left, kind) = self.product()?;
+
+        loop {
+            self.skip_whitespace();
+
+            let operator = match self.peek() {
+                Some(c) if c == '+' || c == '-' => c,
+                _ => return Ok((left, kind)),
+            };
+
+            self.position += 1;
+            let (right, right_kind) = self.product()?;
+
+            // Sizes can't be added to numbers
+            if right_kind != kind {
+                return Err((InvalidExpression, start..self.position));
+            }
+
+            left = match operator {
+                '+' => CalcExpression::Add(Box::new(left), Box::new(right)),
+                _ => CalcExpression::Subtract(Box::new(left), Box::new(right)),
+            };
+        }
+    }
+
+    fn product(&mut self) -> Result<(CalcExpression, Type), Failure> {
+        let start = self.position;
+        let (mut left, mut kind) = self.factor()?;
+
+        loop {
+            self.skip_whitespace();
+
+            let operator = match self.peek() {
+                Some(c) if c == '*' || c == '/' => c,
+                _ => return Ok((left, kind)),
+            };
+
+            self.position += 1;
+            self.skip_whitespace();
+
+            let right_start = self.position;
+            let (right, right_kind) = self.factor()?;
+
+            left = match (operator, kind, right_kind) {
+                ('*', Type::Length, Type::Length) => {
+                    return Err((InvalidExpression, start..self.position))
+                }
+                ('*', _, _) => {
+                    if right_kind == Type::Length {
+                        kind = Type::Length;
+                    }
+
+                    CalcExpression::Multiply(Box::new(left), Box::new(right))
+                }
+                // Numbers don't depend on any bounds, so a division by zero is known up front
+                (_, _, Type::Number)
+                    if right.evaluate(0., 0., 0., &LayoutContext::default()) != 0. =>
+                {
+                    CalcExpression::Divide(Box::new(left), Box::new(right))
+                }
+                _ => return Err((InvalidExpression, right_start..self.position)),
+            };
+        }
+    }
+
+    fn factor(&mut self) -> Result<(CalcExpression, Type), Failure> {
+        self.skip_whitespace();
+        let start = self.position;
+
+        match self.peek() {
+            Some('(') => {
+                self.position += 1;
+                let sum = self.sum()?;
+                self.skip_whitespace();
+
+                match self.peek() {
+                    Some(')') => {
+                        self.position += 1;
+                        Ok(sum)
+                    }
+                    _ => Err((InvalidExpression, start..self.position)),
+                }
+            }
+            Some(c) if c.is_alphabetic() => {
+                self.take_while(|_, c| c.is_alphabetic());
+
+                match &self.input[start..self.position] {
+                    "content" => Ok((CalcExpression::Content, Type::Length)),
+                    "available" => Ok((CalcExpression::Available, Type::Length)),
+                    _ => Err((InvalidExpression, start..self.position)),
+                }
+            }
+            Some(c) => {
+                self.take_while(|i, c| {
+                    c.is_ascii_digit() || c == '.' || (i == 0 && (c == '-' || c == '+'))
+                });
+
+                // Points at the unexpected character if there is no number at all
+                let end = match self.position == start {
+                    true => start + c.len_utf8(),
+                    false => self.position,
+                };
+
+                let number = self.input[start..self.position]
+                    .parse::<Float>()
+                    .map_err(|_| (InvalidExpression, start..end))?;
+
+                let unit_start = self.take_while(|_, c| c.is_alphabetic() || c == '%');
+
+                match &self.input[unit_start..self.position] {
+                    "" => Ok((CalcExpression::Number(number), Type::Number)),
+                    "px" => Ok((CalcExpression::Fixed(number), Type::Length)),
+                    "%" => Ok((CalcExpression::Percent(number), Type::Length)),
+                    "rem" => Ok((CalcExpression::Rem(number), Type::Length)),
+                    _ => Err((InvalidExpression, unit_start..self.position)),
+                }
+            }
+            None => Err((InvalidExpression, start..self.input.len())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{parse_constraint_unit, parse_sizing_unit, LayoutContext, SizingUnit};
+
+    fn evaluate(input: &str, content: f32, bound: f32, outer: f32) -> f32 {
+        parse_sizing_unit(input).unwrap().calculate(
+            content,
+            bound,
+            outer,
+            &LayoutContext::default(),
+        )
+    }
+
+    #[test]
+    fn evaluates_expressions() {
+        assert_eq!(evaluate("calc(100% - 240px - 16px)", 0., 0., 1000.), 744.);
+        assert_eq!(evaluate("calc(2 * (10px + 5%) / 4)", 0., 0., 200.), 10.);
+        assert_eq!(evaluate("calc(content + 8px*2)", 30., 0., 0.), 46.);
+        assert_eq!(evaluate("calc(available / 2 - -1px)", 0., 50., 0.), 26.);
+
+        // Sizes never end up negative
+        assert_eq!(evaluate("calc(10px - 50%)", 0., 0., 100.), 0.);
+
+        // Font sizes come from the context the expression is evaluated in
+        let rem = parse_sizing_unit("calc(1.5rem + 2rem)").unwrap();
+        let context = LayoutContext::default();
+        assert_eq!(rem.calculate(0., 0., 0., &context), 56.);
+        assert_eq!(rem.calculate(0., 0., 0., &context.font_size(10.)), 35.);
+
+        // Constraints evaluate everything against the value they constrain
+        let max = parse_constraint_unit("calc(50% + 10px)").unwrap();
+        assert_eq!(max.calculate(100., &context), 60.);
+        assert_eq!(
+            SizingUnit::Stretch(max).calculate(0., 200., 0., &context),
+            110.
+        );
+
+        // Without bounds, an expression that only depends on the content is still known
+        let unit = parse_sizing_unit("calc(content + 16px)").unwrap();
+        assert_eq!(unit.intrinsic(20., &context), 36.);
+        assert_eq!(
+            parse_sizing_unit("calc(50%)")
+                .unwrap()
+                .intrinsic(20., &context),
+            20.
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        let offending = |input: &str| {
+            parse_sizing_unit(input)
+                .unwrap_err()
+                .offending()
+                .to_string()
+        };
+
+        assert_eq!(offending("calc(10px * 5%)"), "10px * 5%");
+        assert_eq!(offending("calc(10px / 0)"), "0");
+        assert_eq!(offending("calc(10px / 2px)"), "2px");
+        assert_eq!(offending("calc(10px + 2)"), "10px + 2");
+        assert_eq!(offending("calc(10pt)"), "pt");
+        assert_eq!(offending("calc(1.5em)"), "em");
+        assert_eq!(offending("calc(10px 5px)"), "5px");
+        assert_eq!(offending("calc((10px + 5px)"), "(10px + 5px");
+        assert_eq!(offending("calc(10px + #)"), "#");
+        assert_eq!(offending("calc(2 * 3)"), "calc(2 * 3)");
+        assert_eq!(offending("calc(10px"), "calc(10px");
+    }
+}
@@ -0,0 +1,979 @@
+use std::{collections::HashMap, fmt};
+
+use crate::{
+    AlignUnit, Alignment, Breakpoint, BreakpointTarget, Color, ConstraintUnit, Direction,
+    Directional, Element, ElementKind, ElementLayout, Float, Inline, Padding, Relational, Sizing,
+    SizingUnit, Text, TextAlign, TextDirection, TextSpan, TextStyle, WhiteSpace,
+};
+
+use super::{parse_relationships, parse_sizing_unit};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MarkupErrorKind {
+    UnexpectedEnd,
+    UnexpectedCharacter(char),
+
+    /// Only text and inline elements may contain text
+    UnexpectedText,
+    UnknownKind(String),
+    UnknownAttribute(String),
+    UnknownEntity(String),
+    DuplicateAttribute(String),
+
+    /// The attribute and the value that couldn't be parsed
+    InvalidValue(String, String),
+
+    /// The name of the open element and the name of the closing tag
+    MismatchedClosingTag(String, String),
+
+    /// Text and inline elements can only be parsed with a text style
+    MissingTextStyle,
+
+    /// The extern kind and the reason its constructor gave for rejecting the attributes
+    InvalidExtern(String, String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// An error in a markup document, at a line and column that both start at 1
+pub struct MarkupError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: MarkupErrorKind,
+}
+
+impl fmt::Display for MarkupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use MarkupErrorKind::*;
+
+        write!(f, "{}:{}: ", self.line, self.column)?;
+
+        match &self.kind {
+            UnexpectedEnd => write!(f, "unexpected end of input"),
+            UnexpectedCharacter(c) => write!(f, "unexpected character '{}'", c),
+            UnexpectedText => write!(f, "only text and inline elements can contain text"),
+            UnknownKind(kind) => write!(f, "unknown element kind '{}'", kind),
+            UnknownAttribute(attribute) => write!(f, "unknown attribute '{}'", attribute),
+            UnknownEntity(entity) => write!(f, "unknown entity '&{};'", entity),
+            DuplicateAttribute(attribute) => write!(f, "duplicate attribute '{}'", attribute),
+            InvalidValue(attribute, value) => {
+                write!(f, "invalid value '{}' for '{}'", value, attribute)
+            }
+            MismatchedClosingTag(open, close) => {
+                write!(f, "expected '</{}>' but found '</{}>'", open, close)
+            }
+            MissingTextStyle => write!(f, "text elements need a text style to be parsed"),
+            InvalidExtern(kind, reason) => write!(f, "invalid '{}' element: {}", kind, reason),
+        }
+    }
+}
+
+use MarkupErrorKind::*;
+
+/// Parses element trees from markup such as
+///
+/// ```text
+/// <directional direction=vertical spacing=8 width=Stretch height=Collapse label=messages>
+///     <text width=Collapse height=Collapse>Hello &amp; welcome</text>
+///     <none width=Fixed:35 height=Fixed:35/>
+/// </directional>
+/// ```
+///
+/// Values are quoted when they contain whitespace, and `<!-- -->` comments are skipped.
+/// Text and inline elements take the text style of the parser, which `font-size`, `color`,
+/// `line-height`, `underline` and `strikethrough` change, and so do `<span>`s in their text,
+/// which can also be a `link`. Elements start with their breakpoints, such as
+/// `<breakpoint target=viewport max-width=1000 width=Fixed:67 height=Stretch/>`.
+pub struct MarkupParser {
+    text_style: Option<TextStyle>,
+    extern_kinds: HashMap<String, Box<ExternConstructor>>,
+}
+
+/// Creates the layout of an extern element from the attributes other than
+/// the sizing, padding, alignment and label, in the order they were written
+pub type ExternConstructor =
+    dyn Fn(&[(String, String)]) -> Result<Box<dyn ElementLayout>, String> + Send + Sync;
+
+impl Default for MarkupParser {
+    fn default() -> Self {
+        MarkupParser::new()
+    }
+}
+
+impl MarkupParser {
+    pub fn new() -> MarkupParser {
+        MarkupParser {
+            text_style: None,
+            extern_kinds: HashMap::new(),
+        }
+    }
+
+    /// The style of the content of text and inline elements
+    pub fn text_style(mut self, style: TextStyle) -> Self {
+        self.text_style = Some(style);
+        self
+    }
+
+    /// Parses elements with the given tag name as extern elements,
+    /// the counterpart of `ElementLayout::markup_attributes`
+    pub fn extern_kind<F>(mut self, name: impl ToString, constructor: F) -> Self
+    where
+        F: Fn(&[(String, String)]) -> Result<Box<dyn ElementLayout>, String>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.extern_kinds
+            .insert(name.to_string(), Box::new(constructor));
+        self
+    }
+
+    pub fn parse(&self, input: &str) -> Result<Element, MarkupError> {
+        let mut parser = Parser {
+            input,
+            position: 0,
+            text_style: self.text_style.as_ref(),
+            extern_kinds: &self.extern_kinds,
+        };
+
+        parser.skip_whitespace()?;
+        let element = parser.element()?;
+        parser.skip_whitespace()?;
+
+        match parser.peek() {
+            Some(c) => Err(parser.error(UnexpectedCharacter(c))),
+            None => Ok(element),
+        }
+    }
+}
+
+/// Parses markup without a text style, so it can't contain text or inline elements
+pub fn parse_markup(input: &str) -> Result<Element, MarkupError> {
+    MarkupParser::new().parse(input)
+}
+
+struct Attribute<'a> {
+    name: &'a str,
+    value: String,
+    position: usize,
+}
+
+/// What an element contains between its tags
+enum Content<'a> {
+    Children(Vec<Element>),
+
+    /// Text with the elements that flow in it, in place of object spans
+    Text(Vec<Piece<'a>>),
+}
+
+enum Piece<'a> {
+    Text(String),
+    Element(Box<Element>),
+
+    /// A `<span>` with the attributes that change its style, and what it contains
+    Span(Vec<Attribute<'a>>, Vec<Piece<'a>>),
+}
+
+/// The attributes of text and inline elements and spans that change their style
+const STYLE_ATTRIBUTES: [&str; 5] = [
+    "font-size",
+    "color",
+    "line-height",
+    "underline",
+    "strikethrough",
+];
+
+struct Parser<'a> {
+    input: &'a str,
+    position: usize,
+    text_style: Option<&'a TextStyle>,
+    extern_kinds: &'a HashMap<String, Box<ExternConstructor>>,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.position..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn eat(&mut self, expected: &str) -> bool {
+        if self.rest().starts_with(expected) {
+            self.position += expected.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), MarkupError> {
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.position += c.len_utf8();
+                Ok(())
+            }
+            Some(c) => Err(self.error(UnexpectedCharacter(c))),
+            None => Err(self.error(UnexpectedEnd)),
+        }
+    }
+
+    fn error(&self, kind: MarkupErrorKind) -> MarkupError {
+        self.error_at(self.position, kind)
+    }
+
+    fn error_at(&self, position: usize, kind: MarkupErrorKind) -> MarkupError {
+        let before = &self.input[..position];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+        MarkupError {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            kind,
+        }
+    }
+
+    /// Skips whitespace and comments
+    fn skip_whitespace(&mut self) -> Result<(), MarkupError> {
+        loop {
+            let trimmed = self.rest().trim_start();
+            self.position = self.input.len() - trimmed.len();
+
+            if !self.comment()? {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Skips a comment if it's next, but not the whitespace around it
+    fn comment(&mut self) -> Result<bool, MarkupError> {
+        if !self.eat("<!--") {
+            return Ok(false);
+        }
+
+        match self.rest().find("-->") {
+            Some(end) => {
+                self.position += end + 3;
+                Ok(true)
+            }
+            None => {
+                self.position = self.input.len();
+                Err(self.error(UnexpectedEnd))
+            }
+        }
+    }
+
+    fn identifier(&mut self) -> Result<&'a str, MarkupError> {
+        let rest = self.rest();
+        let length = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'))
+            .unwrap_or(rest.len());
+
+        if length == 0 {
+            return Err(match self.peek() {
+                Some(c) => self.error(UnexpectedCharacter(c)),
+                None => self.error(UnexpectedEnd),
+            });
+        }
+
+        self.position += length;
+        Ok(&rest[..length])
+    }
+
+    fn value(&mut self) -> Result<String, MarkupError> {
+        if self.eat("\"") {
+            let start = self.position;
+            let end = self
+                .rest()
+                .find('"')
+                .ok_or_else(|| self.error_at(self.input.len(), UnexpectedEnd))?;
+
+            self.position += end + 1;
+            return self.decode(start, &self.input[start..start + end]);
+        }
+
+        let rest = self.rest();
+        let length = rest
+            .find(|c: char| c.is_whitespace() || c == '>' || c == '/' || c == '"')
+            .unwrap_or(rest.len());
+
+        if length == 0 {
+            return Err(match self.peek() {
+                Some(c) => self.error(UnexpectedCharacter(c)),
+                None => self.error(UnexpectedEnd),
+            });
+        }
+
+        let start = self.position;
+        self.position += length;
+
+        self.decode(start, &rest[..length])
+    }
+
+    /// Replaces entities such as `&lt;` in text that starts at the given position
+    fn decode(&self, start: usize, text: &str) -> Result<String, MarkupError> {
+        let mut decoded = String::with_capacity(text.len());
+        let mut rest = text;
+
+        while let Some(index) = rest.find('&') {
+            decoded.push_str(&rest[..index]);
+
+            let entity_start = start + (text.len() - rest.len()) + index;
+            let end = rest[index..].find(';').ok_or_else(|| {
+                self.error_at(entity_start, UnknownEntity(rest[index + 1..].into()))
+            })?;
+            let entity = &rest[index + 1..index + end];
+
+            decoded.push(match entity {
+                "lt" => '<',
+                "gt" => '>',
+                "amp" => '&',
+                "quot" => '"',
+                "apos" => '\'',
+                _ => return Err(self.error_at(entity_start, UnknownEntity(entity.into()))),
+            });
+
+            rest = &rest[index + end + 1..];
+        }
+
+        decoded.push_str(rest);
+        Ok(decoded)
+    }
+
+    fn element(&mut self) -> Result<Element, MarkupError> {
+        let start = self.position;
+        self.expect('<')?;
+
+        let kind = self.identifier()?;
+
+        let known = matches!(
+            kind,
+            "none" | "directional" | "relational" | "text" | "inline"
+        );
+
+        if !known && !self.extern_kinds.contains_key(kind) {
+            return Err(self.error_at(start + 1, UnknownKind(kind.into())));
+        }
+
+        let (attributes, closed) = self.attributes()?;
+        let holds_text = matches!(kind, "text" | "inline");
+        let mut breakpoints = Vec::new();
+
+        let content = match (closed, holds_text) {
+            (true, true) => Content::Text(Vec::new()),
+            (true, false) => Content::Children(Vec::new()),
+            (false, true) => Content::Text(self.pieces(kind, &mut breakpoints)?),
+            (false, false) => Content::Children(self.children(kind, &mut breakpoints)?),
+        };
+
+        self.build(start, kind, attributes, content, breakpoints)
+    }
+
+    /// Parses the attributes of a `<breakpoint/>` tag, which has no content
+    fn breakpoint_tag(&mut self) -> Result<Vec<Attribute<'a>>, MarkupError> {
+        self.position += "<breakpoint".len();
+
+        match self.attributes()? {
+            (attributes, true) => Ok(attributes),
+            (_, false) => Err(self.error_at(self.position - 1, UnexpectedCharacter('>'))),
+        }
+    }
+
+    /// Parses the attributes of a tag up to its end, and whether it closes itself
+    fn attributes(&mut self) -> Result<(Vec<Attribute<'a>>, bool), MarkupError> {
+        let mut attributes: Vec<Attribute> = Vec::new();
+
+        loop {
+            self.skip_whitespace()?;
+
+            if self.eat("/>") {
+                return Ok((attributes, true));
+            }
+
+            if self.eat(">") {
+                return Ok((attributes, false));
+            }
+
+            let position = self.position;
+            let name = self.identifier()?;
+
+            if attributes.iter().any(|a| a.name == name) {
+                return Err(self.error_at(position, DuplicateAttribute(name.into())));
+            }
+
+            self.skip_whitespace()?;
+            self.expect('=')?;
+            self.skip_whitespace()?;
+
+            attributes.push(Attribute {
+                name,
+                value: self.value()?,
+                position,
+            });
+        }
+    }
+
+    fn children(
+        &mut self,
+        kind: &str,
+        breakpoints: &mut Vec<Vec<Attribute<'a>>>,
+    ) -> Result<Vec<Element>, MarkupError> {
+        let mut children = Vec::new();
+
+        loop {
+            self.skip_whitespace()?;
+
+            if self.closing_tag(kind)? {
+                return Ok(children);
+            }
+
+            match self.peek() {
+                Some('<') if self.is_tag("breakpoint") => breakpoints.push(self.breakpoint_tag()?),
+                Some('<') => children.push(self.element()?),
+                Some(_) => return Err(self.error(UnexpectedText)),
+                None => return Err(self.error(UnexpectedEnd)),
+            }
+        }
+    }
+
+    fn pieces(
+        &mut self,
+        kind: &str,
+        breakpoints: &mut Vec<Vec<Attribute<'a>>>,
+    ) -> Result<Vec<Piece<'a>>, MarkupError> {
+        let mut pieces = Vec::new();
+
+        loop {
+            // Whitespace around comments is part of the text
+            if self.comment()? {
+                continue;
+            }
+
+            if self.closing_tag(kind)? {
+                return Ok(pieces);
+            }
+
+            match self.peek() {
+                Some('<') if self.is_tag("breakpoint") => breakpoints.push(self.breakpoint_tag()?),
+                Some('<') if self.is_tag("span") => {
+                    self.position += "<span".len();
+
+                    let (attributes, closed) = self.attributes()?;
+                    let content = match closed {
+                        true => Vec::new(),
+                        false => self.pieces("span", breakpoints)?,
+                    };
+
+                    pieces.push(Piece::Span(attributes, content));
+                }
+                Some('<') => pieces.push(Piece::Element(Box::new(self.element()?))),
+                Some(_) => {
+                    let start = self.position;
+                    let length = self.rest().find('<').unwrap_or(self.rest().len());
+                    self.position += length;
+
+                    let text = self.decode(start, &self.input[start..start + length])?;
+                    pieces.push(Piece::Text(text));
+                }
+                None => return Err(self.error(UnexpectedEnd)),
+            }
+        }
+    }
+
+    /// Whether a tag with the name is next
+    fn is_tag(&self, name: &str) -> bool {
+        let rest = self.rest();
+
+        rest.starts_with('<')
+            && rest[1..].starts_with(name)
+            && !rest[1 + name.len()..]
+                .starts_with(|c: char| c.is_alphanumeric() || c == '-' || c == '_')
+    }
+
+    /// Parses the closing tag of the element if it's next
+    fn closing_tag(&mut self, kind: &str) -> Result<bool, MarkupError> {
+        let start = self.position;
+
+        if !self.eat("</") {
+            return Ok(false);
+        }
+
+        let name = self.identifier()?;
+
+        if name != kind {
+            return Err(self.error_at(start, MismatchedClosingTag(kind.into(), name.into())));
+        }
+
+        self.skip_whitespace()?;
+        self.expect('>')?;
+
+        Ok(true)
+    }
+
+    fn build(
+        &self,
+        start: usize,
+        kind: &str,
+        attributes: Vec<Attribute>,
+        content: Content,
+        breakpoints: Vec<Vec<Attribute>>,
+    ) -> Result<Element, MarkupError> {
+        let mut element = Element {
+            kind: ElementKind::None,
+            sizing: Sizing {
+                width: SizingUnit::Collapse(ConstraintUnit::None),
+                height: SizingUnit::Collapse(ConstraintUnit::None),
+            },
+            padding: Padding::empty(),
+            alignment: Alignment::new(AlignUnit::Start, AlignUnit::Start),
+            children: Vec::new(),
+            label: None,
+            base_style: None,
+            breakpoints: Vec::new(),
+        };
+
+        let mut direction = Direction::Horizontal;
+        let mut spacing = 0.;
+        let mut parallel = false;
+        let mut relationships = Vec::new();
+        let mut white_space = WhiteSpace::Normal;
+        let mut tab_size = 8.;
+        let mut text_direction = TextDirection::Auto;
+        let mut text_align = TextAlign::Start;
+        let mut max_lines = None;
+        let mut ellipsis = None;
+        let mut style_attributes = Vec::new();
+        let mut extern_attributes = Vec::new();
+
+        for Attribute {
+            name,
+            value,
+            position,
+        } in attributes
+        {
+            let invalid = || self.error_at(position, InvalidValue(name.into(), value.clone()));
+
+            match (kind, name) {
+                (_, "width") => {
+                    element.sizing.width = parse_sizing_unit(&value).map_err(|_| invalid())?
+                }
+                (_, "height") => {
+                    element.sizing.height = parse_sizing_unit(&value).map_err(|_| invalid())?
+                }
+                (_, "padding") => element.padding = parse_padding(&value).ok_or_else(invalid)?,
+                (_, "align") => element.alignment = parse_alignment(&value).ok_or_else(invalid)?,
+                (_, "label") => element.label = Some(value.clone()),
+                ("directional", "direction") => {
+                    direction = parse_direction(&value).ok_or_else(invalid)?
+                }
+                ("directional", "spacing") => spacing = value.parse().map_err(|_| invalid())?,
+                ("directional", "parallel") => parallel = value.parse().map_err(|_| invalid())?,
+                ("relational", "relationships") => {
+                    relationships = parse_relationships(&value).map_err(|_| invalid())?
+                }
+                ("text", "white-space") | ("inline", "white-space") => {
+                    white_space = match value.as_str() {
+                        "normal" => WhiteSpace::Normal,
+                        "pre" => WhiteSpace::Pre,
+                        "pre-wrap" => WhiteSpace::PreWrap,
+                        "nowrap" => WhiteSpace::NoWrap,
+                        _ => return Err(invalid()),
+                    }
+                }
+                ("text", "tab-size") | ("inline", "tab-size") => {
+                    tab_size = value.parse().map_err(|_| invalid())?
+                }
+                ("text", "direction") | ("inline", "direction") => {
+                    text_direction = match value.as_str() {
+                        "auto" => TextDirection::Auto,
+                        "ltr" => TextDirection::LeftToRight,
+                        "rtl" => TextDirection::RightToLeft,
+                        _ => return Err(invalid()),
+                    }
+                }
+                ("text", "text-align") | ("inline", "text-align") => {
+                    text_align = match value.as_str() {
+                        "start" => TextAlign::Start,
+                        "center" => TextAlign::Center,
+                        "end" => TextAlign::End,
+                        "justify" => TextAlign::Justify,
+                        _ => return Err(invalid()),
+                    }
+                }
+                ("text", "max-lines") | ("inline", "max-lines") => {
+                    max_lines = Some(value.parse().map_err(|_| invalid())?)
+                }
+                ("text", "ellipsis") | ("inline", "ellipsis") => ellipsis = Some(value.clone()),
+                ("text", _) | ("inline", _) if STYLE_ATTRIBUTES.contains(&name) => style_attributes
+                    .push(Attribute {
+                        name,
+                        value,
+                        position,
+                    }),
+                (kind, _) if self.extern_kinds.contains_key(kind) => {
+                    extern_attributes.push((name.to_string(), value.clone()))
+                }
+                _ => return Err(self.error_at(position, UnknownAttribute(name.into()))),
+            }
+        }
+
+        element.breakpoints = breakpoints
+            .iter()
+            .map(|attributes| self.build_breakpoint(&element.sizing, attributes))
+            .collect::<Result<_, _>>()?;
+
+        element.kind = match (kind, content) {
+            ("none", Content::Children(children)) => {
+                element.children = children;
+                ElementKind::None
+            }
+            ("directional", Content::Children(children)) => {
+                element.children = children;
+                ElementKind::Directional(Directional::new(direction, spacing).parallel(parallel))
+            }
+            ("relational", Content::Children(children)) => {
+                element.children = children;
+                ElementKind::Relational(Relational::new(relationships))
+            }
+            (kind, Content::Children(children)) => {
+                let constructor = &self.extern_kinds[kind];
+                let layout = constructor(&extern_attributes).map_err(|reason| {
+                    self.error_at(start + 1, InvalidExtern(kind.into(), reason))
+                })?;
+
+                element.children = children;
+                ElementKind::Extern(layout)
+            }
+            (_, Content::Text(pieces)) => {
+                let mut style = self
+                    .text_style
+                    .ok_or_else(|| self.error_at(start, MissingTextStyle))?
+                    .clone();
+
+                for attribute in &style_attributes {
+                    self.style_attribute(&mut style, attribute)?;
+                }
+
+                let mut spans = Vec::new();
+
+                self.spans(
+                    (kind, start),
+                    pieces,
+                    &style,
+                    None,
+                    &mut spans,
+                    &mut element.children,
+                )?;
+
+                let mut text = Text::rich(spans)
+                    .white_space(white_space)
+                    .tab_size(tab_size)
+                    .direction(text_direction)
+                    .align(text_align);
+
+                text.max_lines = max_lines;
+                text.ellipsis = ellipsis;
+
+                if kind == "inline" {
+                    ElementKind::Inline(Inline::new(text))
+                } else {
+                    ElementKind::Text(text)
+                }
+            }
+        };
+
+        Ok(element)
+    }
+
+    /// Turns the pieces of a text or inline element of the kind at the start into spans
+    /// with the style and link of the spans they're in, and collects the inline children
+    fn spans(
+        &self,
+        (kind, start): (&str, usize),
+        pieces: Vec<Piece>,
+        style: &TextStyle,
+        link: Option<&String>,
+        spans: &mut Vec<TextSpan>,
+        children: &mut Vec<Element>,
+    ) -> Result<(), MarkupError> {
+        let span = |span: TextSpan| match link {
+            Some(link) => span.link(link),
+            None => span,
+        };
+
+        for piece in pieces {
+            match piece {
+                Piece::Text(text) => spans.push(span(TextSpan::new(text, style.clone()))),
+                Piece::Element(child) if kind == "inline" => {
+                    spans.push(span(TextSpan::object(style.clone())));
+                    children.push(*child);
+                }
+                Piece::Element(_) => return Err(self.error_at(start, UnexpectedText)),
+                Piece::Span(attributes, pieces) => {
+                    let mut style = style.clone();
+                    let mut link = link;
+
+                    for attribute in &attributes {
+                        match attribute.name {
+                            "link" => link = Some(&attribute.value),
+                            _ => self.style_attribute(&mut style, attribute)?,
+                        }
+                    }
+
+                    self.spans((kind, start), pieces, &style, link, spans, children)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// A breakpoint of an element with the sizing, which is what the sizing of the breakpoint
+    /// falls back to if only its width or height is given
+    fn build_breakpoint(
+        &self,
+        sizing: &Sizing,
+        attributes: &[Attribute],
+    ) -> Result<Breakpoint, MarkupError> {
+        let mut breakpoint = Breakpoint::container();
+        let mut width = None;
+        let mut height = None;
+
+        for Attribute {
+            name,
+            value,
+            position,
+        } in attributes
+        {
+            let invalid =
+                || self.error_at(*position, InvalidValue(name.to_string(), value.clone()));
+            let number = || value.parse::<Float>().map_err(|_| invalid());
+
+            match *name {
+                "target" => {
+                    breakpoint.target = match value.as_str() {
+                        "viewport" => BreakpointTarget::Viewport,
+                        "container" => BreakpointTarget::Container,
+                        _ => return Err(invalid()),
+                    }
+                }
+                "min-width" => breakpoint.min.width = number()?,
+                "max-width" => breakpoint.max.width = number()?,
+                "min-height" => breakpoint.min.height = number()?,
+                "max-height" => breakpoint.max.height = number()?,
+                "width" => width = Some(parse_sizing_unit(value).map_err(|_| invalid())?),
+                "height" => height = Some(parse_sizing_unit(value).map_err(|_| invalid())?),
+                "padding" => breakpoint.padding = Some(parse_padding(value).ok_or_else(invalid)?),
+                "align" => breakpoint.alignment = Some(parse_alignment(value).ok_or_else(invalid)?),
+                "direction" => {
+                    breakpoint.direction = Some(parse_direction(value).ok_or_else(invalid)?)
+                }
+                "spacing" => breakpoint.spacing = Some(number()?),
+                _ => return Err(self.error_at(*position, UnknownAttribute(name.to_string()))),
+            }
+        }
+
+        if width.is_some() || height.is_some() {
+            breakpoint.sizing = Some(Sizing::new(
+                width.unwrap_or_else(|| sizing.width.clone()),
+                height.unwrap_or_else(|| sizing.height.clone()),
+            ));
+        }
+
+        Ok(breakpoint)
+    }
+
+    /// Changes the style by one of the `STYLE_ATTRIBUTES`
+    fn style_attribute(
+        &self,
+        style: &mut TextStyle,
+        attribute: &Attribute,
+    ) -> Result<(), MarkupError> {
+        let Attribute {
+            name,
+            value,
+            position,
+        } = attribute;
+
+        let invalid = || self.error_at(*position, InvalidValue(name.to_string(), value.clone()));
+
+        match *name {
+            "font-size" => style.size = value.parse().map_err(|_| invalid())?,
+            "color" => style.color = parse_color(value).ok_or_else(invalid)?,
+            "line-height" => style.line_height = value.parse().map_err(|_| invalid())?,
+            "underline" => style.underline = value.parse().map_err(|_| invalid())?,
+            "strikethrough" => style.strikethrough = value.parse().map_err(|_| invalid())?,
+            _ => return Err(self.error_at(*position, UnknownAttribute(name.to_string()))),
+        }
+
+        Ok(())
+    }
+}
+
+/// The red, green, blue and alpha channels, such as `1,0.5,0,1`
+fn parse_color(value: &str) -> Option<Color> {
+    let channels = value
+        .split(',')
+        .map(|v| v.trim().parse::<Float>().ok())
+        .collect::<Option<Vec<_>>>()?;
+
+    match channels.as_slice() {
+        [red, green, blue, alpha] => Some((*red, *green, *blue, *alpha)),
+        _ => None,
+    }
+}
+
+/// One value for every side, or the top, bottom, left and right
+pub(super) fn parse_padding(value: &str) -> Option<Padding> {
+    let values = value
+        .split(',')
+        .map(|v| v.trim().parse::<Float>().ok())
+        .collect::<Option<Vec<_>>>()?;
+
+    match values.as_slice() {
+        [all] => Some(Padding::new(*all, *all, *all, *all)),
+        [top, bottom, left, right] => Some(Padding::new(*top, *bottom, *left, *right)),
+        _ => None,
+    }
+}
+
+fn parse_direction(value: &str) -> Option<Direction> {
+    match value {
+        "horizontal" => Some(Direction::Horizontal),
+        "vertical" => Some(Direction::Vertical),
+        _ => None,
+    }
+}
+
+/// The horizontal and vertical alignment, such as `start,end`
+pub(super) fn parse_alignment(value: &str) -> Option<Alignment> {
+    let unit = |unit: &str| match unit.trim() {
+        "start" => Some(AlignUnit::Start),
+        "middle" => Some(AlignUnit::Middle),
+        "end" => Some(AlignUnit::End),
+        _ => None,
+    };
+
+    let mut units = value.split(',');
+    let horizontal = unit(units.next()?)?;
+    let vertical = unit(units.next()?)?;
+
+    match units.next() {
+        Some(_) => None,
+        None => Some(Alignment::new(horizontal, vertical)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{mock::layout::FONT, ConstraintUnit, ElementKind, SizingUnit, TextStyle};
+
+    use super::*;
+
+    #[test]
+    fn parses_element_trees() {
+        let element = parse_markup(
+            r#"
+            <!-- The sidebar -->
+            <directional direction=vertical spacing=8 width="Stretch:400" height=Collapse
+                padding=4,4,8,8 align=middle,end label="side bar">
+                <none width=Fixed:35 height=Percent:0.5,_,_/>
+                <directional parallel=true></directional>
+            </directional>
+            "#,
+        )
+        .unwrap();
+
+        assert!(matches!(element.kind, ElementKind::Directional(_)));
+        assert_eq!(element.label(), Some(&"side bar".to_string()));
+        assert_eq!(
+            element.sizing().width,
+            SizingUnit::Stretch(ConstraintUnit::Fixed(400.))
+        );
+        assert_eq!(element.padding().as_tuple(), (4., 4., 8., 8.));
+        assert!(matches!(
+            element.alignment().as_tuple(),
+            (AlignUnit::Middle, AlignUnit::End)
+        ));
+
+        let children = element.children();
+        assert_eq!(children.len(), 2);
+        assert_eq!(children[0].sizing().width, SizingUnit::Fixed(35.));
+        assert_eq!(
+            children[0].sizing().height,
+            SizingUnit::Percent(0.5, ConstraintUnit::None, ConstraintUnit::None)
+        );
+    }
+
+    #[test]
+    fn parses_text_and_inline_elements() {
+        let parser = MarkupParser::new().text_style(FONT.with(|f| TextStyle::new(f.clone(), 16.)));
+
+        let element = parser
+            .parse("<inline>pizza &amp; <none width=Fixed:20/> krabbe</inline>")
+            .unwrap();
+
+        match &element.kind {
+            ElementKind::Inline(inline) => {
+                assert_eq!(inline.text().content(), "pizza & \u{fffc} krabbe")
+            }
+            kind => panic!("expected an inline element, found {:?}", kind),
+        }
+
+        assert_eq!(element.children().len(), 1);
+
+        let element = parser
+            .parse("<text white-space=pre>  a &lt; b</text>")
+            .unwrap();
+        assert!(matches!(&element.kind, ElementKind::Text(t) if t.content() == "  a < b"));
+
+        // Comments in text are skipped, but not the whitespace around them
+        let element = parser.parse("<text>a <!-- c --> b</text>").unwrap();
+        assert!(matches!(&element.kind, ElementKind::Text(t) if t.content() == "a  b"));
+
+        let element = parser
+            .parse("<text white-space=pre>a<!-- c -->\n    b</text>")
+            .unwrap();
+        assert!(matches!(&element.kind, ElementKind::Text(t) if t.content() == "a\n    b"));
+
+        assert_eq!(
+            parse_markup("<text>pizza</text>").unwrap_err().kind,
+            MissingTextStyle
+        );
+    }
+
+    #[test]
+    fn reports_errors_with_lines_and_columns() {
+        let error = |input: &str| parse_markup(input).unwrap_err();
+
+        let unknown = error("<directional>\n  <pizza/>\n</directional>");
+        assert_eq!((unknown.line, unknown.column), (2, 4));
+        assert_eq!(unknown.kind, UnknownKind("pizza".into()));
+        assert_eq!(unknown.to_string(), "2:4: unknown element kind 'pizza'");
+
+        let invalid = error("<none\n width=Fixed:pizza/>");
+        assert_eq!((invalid.line, invalid.column), (2, 2));
+        assert_eq!(
+            invalid.kind,
+            InvalidValue("width".into(), "Fixed:pizza".into())
+        );
+
+        let mismatched = error("<directional><none></directional>");
+        assert_eq!(mismatched.column, 20);
+        assert_eq!(
+            mismatched.kind,
+            MismatchedClosingTag("none".into(), "directional".into())
+        );
+
+        assert_eq!(
+            error("<none spacing=4/>").kind,
+            UnknownAttribute("spacing".into())
+        );
+        assert_eq!(
+            error("<directional>text</directional>").kind,
+            UnexpectedText
+        );
+        assert_eq!(error("<directional>").kind, UnexpectedEnd);
+        assert_eq!(error("<none/><none/>").kind, UnexpectedCharacter('<'));
+    }
+}
@@ -1,95 +1,479 @@
-#[derive(Clone, Debug)]
-pub enum ParsingError<'a> {
-    NotASizingUnit(&'a str),
-    InvalidConstraintSyntax(&'a str),
-    NotEnoughArgumentsToSizing(&'a str),
-}
+mod calc;
+mod markup;
+mod relationship;
+mod reload;
+mod stylesheet;
+mod writer;
+
+pub use markup::*;
+pub use relationship::*;
+pub use reload::*;
+pub use stylesheet::*;
+pub use writer::*;
 
-use ParsingError::*;
+use std::{error::Error, fmt, ops::Range};
 
 use crate::{ConstraintUnit, Float, SizingUnit};
 
-pub fn parse_constraint_unit<'a>(input: &'a str) -> Result<ConstraintUnit, ParsingError<'a>> {
-    if input == "_" {
-        return Ok(ConstraintUnit::None);
+use calc::calc_expression;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParsingErrorKind {
+    NotASizingUnit,
+    InvalidConstraintSyntax,
+    NotEnoughArgumentsToSizing,
+    InvalidExpression,
+    InvalidSelector,
+    InvalidRelationship,
+}
+
+use ParsingErrorKind::*;
+
+#[derive(Clone, Debug, PartialEq)]
+/// A sizing or constraint unit that couldn't be parsed, with a copy of the input
+/// so that it can be kept around after the input is gone
+pub struct ParsingError {
+    pub kind: ParsingErrorKind,
+    pub input: String,
+
+    /// The byte range of the input that couldn't be parsed
+    pub range: Range<usize>,
+}
+
+impl ParsingError {
+    fn new(input: &str, (kind, range): Failure) -> ParsingError {
+        ParsingError {
+            kind,
+            input: input.to_string(),
+            range,
+        }
     }
 
-    let (input, is_percent) = match input.find('%') {
-        Some(b) => {
-            if &input[(b + 1)..] != "" {
-                Err(InvalidConstraintSyntax(input))?
-            }
+    /// The part of the input that couldn't be parsed
+    pub fn offending(&self) -> &str {
+        &self.input[self.range.clone()]
+    }
+}
 
-            (&input[..b], true)
+impl fmt::Display for ParsingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            NotASizingUnit => write!(f, "'{}' is not a sizing unit", self.offending())?,
+            InvalidConstraintSyntax => write!(f, "invalid constraint '{}'", self.offending())?,
+            NotEnoughArgumentsToSizing => write!(f, "not enough arguments to sizing")?,
+            InvalidExpression => write!(f, "invalid expression '{}'", self.offending())?,
+            InvalidSelector => write!(f, "invalid selector '{}'", self.offending())?,
+            InvalidRelationship => write!(f, "invalid relationship '{}'", self.offending())?,
         }
+
+        write!(
+            f,
+            " in '{}' at {}..{}",
+            self.input, self.range.start, self.range.end
+        )
+    }
+}
+
+impl Error for ParsingError {}
+
+/// The kind of error and where it is, relative to the input being parsed
+type Failure = (ParsingErrorKind, Range<usize>);
+
+/// Moves a failure in a part of the input that starts at the given position
+fn shifted(start: usize) -> impl Fn(Failure) -> Failure {
+    move |(kind, range)| (kind, range.start + start..range.end + start)
+}
+
+/// Parses a constraint such as `_`, `300`, `300px`, `50%`, `auto` or `calc(50% + 8px)`.
+/// A percentage is of the value being constrained, so `50%` is half of it.
+pub fn parse_constraint_unit(input: &str) -> Result<ConstraintUnit, ParsingError> {
+    constraint_unit(input).map_err(|failure| ParsingError::new(input, failure))
+}
+
+/// Parses a sizing unit, either as `Fixed:60`, `Percent:0.5,_,_`, `Collapse` or `Stretch:300`,
+/// or in the CSS-like shorthand `60px`, `50%`, `1fr`, `auto`, `min(200px, 30%)`,
+/// `max(200px, 30%)`, `clamp(100px, 50%, 600px)` and `calc(100% - 16px)`
+pub fn parse_sizing_unit(input: &str) -> Result<SizingUnit, ParsingError> {
+    sizing_unit(input).map_err(|failure| ParsingError::new(input, failure))
+}
+
+fn constraint_unit(input: &str) -> Result<ConstraintUnit, Failure> {
+    if input == "_" || input == "auto" {
+        return Ok(ConstraintUnit::None);
+    }
+
+    if input.starts_with("calc(") {
+        return calc_expression(input).map(|e| ConstraintUnit::Calc(Box::new(e)));
+    }
+
+    if let Some(pixels) = input.strip_suffix("px") {
+        return pixels
+            .parse::<Float>()
+            .map(ConstraintUnit::Fixed)
+            .map_err(|_| (InvalidConstraintSyntax, 0..input.len()));
+    }
+
+    let invalid = || (InvalidConstraintSyntax, 0..input.len());
+
+    let (number, is_percent) = match input.find('%') {
+        Some(b) if b + 1 == input.len() => (&input[..b], true),
+        Some(_) => return Err(invalid()),
         None => (input, false),
     };
 
-    input
-        .parse::<Float>()
-        .map(|f| {
-            if is_percent {
-                ConstraintUnit::Percent(f)
-            } else {
-                ConstraintUnit::Fixed(f)
-            }
-        })
-        .map_err(|_| InvalidConstraintSyntax(input))
+    if is_percent {
+        return parse_percent(number)
+            .map(ConstraintUnit::Percent)
+            .ok_or_else(invalid);
+    }
+
+    let value = number.parse::<Float>().map_err(|_| invalid())?;
+    Ok(ConstraintUnit::Fixed(value))
 }
 
-pub fn parse_sizing_unit<'a>(input: &'a str) -> Result<SizingUnit, ParsingError<'a>> {
-    let delim = input.find(':');
+/// Reads the number before a `%` as a fraction. It's divided with more precision than
+/// a `Float` has, so that every fraction can be written as a percentage and read back.
+pub(super) fn parse_percent(number: &str) -> Option<Float> {
+    number
+        .parse::<f64>()
+        .ok()
+        .map(|percent| (percent / 100.) as Float)
+}
 
-    let sizing = match delim {
-        Some(b) => &input[..b],
-        None => input,
+fn sizing_unit(input: &str) -> Result<SizingUnit, Failure> {
+    let (sizing, arguments) = match input.find(':') {
+        Some(b) => (&input[..b], Some((b + 1, &input[(b + 1)..]))),
+        None => (input, None),
     };
 
-    match sizing {
-        "Percent" => match delim {
-            Some(b) => {
-                let constraint_string = &input[(b + 1)..];
+    let missing = (NotEnoughArgumentsToSizing, 0..input.len());
+
+    match (sizing, arguments) {
+        ("Percent", Some((start, arguments))) => {
+            let (size, min, max) = match arguments.splitn(3, ',').collect::<Vec<_>>()[..] {
+                [size, min, max] => (size, min, max),
+                _ => return Err(missing),
+            };
+
+            let min_start = start + size.len() + 1;
+            let max_start = min_start + min.len() + 1;
+
+            let size = size
+                .parse::<Float>()
+                .map_err(|_| (InvalidConstraintSyntax, start..start + size.len()))?;
+            let min = constraint_unit(min).map_err(shifted(min_start))?;
+            let max = constraint_unit(max).map_err(shifted(max_start))?;
 
-                let next = constraint_string
-                    .find(',')
-                    .ok_or(NotEnoughArgumentsToSizing(input))?;
-                let size = constraint_string[..next]
-                    .parse::<Float>()
-                    .map_err(|_| InvalidConstraintSyntax(&constraint_string[..next]))?;
+            Ok(SizingUnit::Percent(size, min, max))
+        }
+        ("Stretch", None) => Ok(SizingUnit::Stretch(ConstraintUnit::None)),
+        ("Stretch", Some((start, argument))) => constraint_unit(argument)
+            .map(SizingUnit::Stretch)
+            .map_err(shifted(start)),
+        ("Collapse", None) => Ok(SizingUnit::Collapse(ConstraintUnit::None)),
+        ("Collapse", Some((start, argument))) => constraint_unit(argument)
+            .map(SizingUnit::Collapse)
+            .map_err(shifted(start)),
+        ("Fixed", Some((start, argument))) => argument
+            .parse::<Float>()
+            .map(SizingUnit::Fixed)
+            .map_err(|_| (InvalidConstraintSyntax, start..input.len())),
+        ("Percent", None) | ("Fixed", None) => Err(missing),
+        _ => shorthand(input).unwrap_or(Err((NotASizingUnit, 0..sizing.len()))),
+    }
+}
+
+/// A length in the shorthand syntax, where percentages are of the outer bounds
+#[derive(Clone, Copy)]
+enum Length {
+    Pixels(Float),
+    Percent(Float),
+}
+
+fn length(input: &str) -> Result<Length, Failure> {
+    let invalid = || (InvalidConstraintSyntax, 0..input.len());
+
+    if let Some(pixels) = input.strip_suffix("px") {
+        pixels.parse().map(Length::Pixels).map_err(|_| invalid())
+    } else if let Some(percent) = input.strip_suffix('%') {
+        parse_percent(percent)
+            .map(Length::Percent)
+            .ok_or_else(invalid)
+    } else {
+        Err(invalid())
+    }
+}
 
-                let constraint_string = &constraint_string[(next + 1)..];
-                let next = constraint_string
-                    .find(',')
-                    .ok_or(NotEnoughArgumentsToSizing(input))?;
-                let min_constraint = parse_constraint_unit(&constraint_string[..next])?;
+/// Parses the shorthand syntax, or returns `None` if the input isn't written in it
+fn shorthand(input: &str) -> Option<Result<SizingUnit, Failure>> {
+    if input == "auto" {
+        return Some(Ok(SizingUnit::Collapse(ConstraintUnit::None)));
+    }
 
-                let constraint_string = &constraint_string[(next + 1)..];
-                let max_constraint = parse_constraint_unit(&constraint_string)?;
+    if let Some(fraction) = input.strip_suffix("fr") {
+        return Some(
+            fraction
+                .parse::<Float>()
+                .map(SizingUnit::Fraction)
+                .map_err(|_| (InvalidConstraintSyntax, 0..input.len())),
+        );
+    }
+
+    if let Some(open) = input.find('(') {
+        let name = &input[..open];
+
+        if name == "calc" {
+            return Some(calc_expression(input).map(|e| SizingUnit::Calc(Box::new(e))));
+        }
 
-                Ok(SizingUnit::Percent(size, min_constraint, max_constraint))
+        if !matches!(name, "min" | "max" | "clamp") {
+            return None;
+        }
+
+        return Some(function(name, open, input));
+    }
+
+    if input.ends_with("px") || input.ends_with('%') {
+        return Some(length(input).map(|length| match length {
+            Length::Pixels(pixels) => SizingUnit::Fixed(pixels),
+            Length::Percent(percent) => {
+                SizingUnit::Percent(percent, ConstraintUnit::None, ConstraintUnit::None)
+            }
+        }));
+    }
+
+    None
+}
+
+/// Maps `min`, `max` and `clamp` onto a percentage constrained by the other lengths,
+/// or onto a fixed size if all of the lengths are in pixels
+fn function(name: &str, open: usize, input: &str) -> Result<SizingUnit, Failure> {
+    let close = match input.strip_suffix(')') {
+        Some(_) => input.len() - 1,
+        None => return Err((InvalidConstraintSyntax, open..input.len())),
+    };
+
+    let mut arguments = Vec::new();
+    let mut start = open + 1;
+
+    for argument in input[open + 1..close].split(',') {
+        let leading = argument.len() - argument.trim_start().len();
+        let range = start + leading..start + leading + argument.trim().len();
+
+        arguments.push((
+            length(argument.trim()).map_err(shifted(range.start))?,
+            range,
+        ));
+        start += argument.len() + 1;
+    }
+
+    let missing = (NotEnoughArgumentsToSizing, 0..input.len());
+
+    if name == "clamp" {
+        let (min, value, max) = match arguments[..] {
+            [(min, _), (value, ref range), (max, _)] => (min, (value, range.clone()), max),
+            _ => return Err(missing),
+        };
+
+        return match value {
+            (Length::Pixels(value), _) => {
+                let pixels = |bound: Length| match bound {
+                    Length::Pixels(pixels) => Ok(pixels),
+                    // A fixed size can't depend on the outer bounds
+                    Length::Percent(_) => Err((InvalidConstraintSyntax, 0..input.len())),
+                };
+
+                Ok(SizingUnit::Fixed(value.min(pixels(max)?).max(pixels(min)?)))
             }
-            None => Err(NotEnoughArgumentsToSizing(input)),
-        },
-        "Stretch" => match delim {
-            Some(b) => Ok(SizingUnit::Stretch(parse_constraint_unit(
-                &input[(b + 1)..],
-            )?)),
-            None => Ok(SizingUnit::Stretch(ConstraintUnit::None)),
-        },
-        "Collapse" => match delim {
-            Some(b) => Ok(SizingUnit::Collapse(parse_constraint_unit(
-                &input[(b + 1)..],
-            )?)),
-            None => Ok(SizingUnit::Collapse(ConstraintUnit::None)),
-        },
-        "Fixed" => match delim {
-            Some(b) => Ok(SizingUnit::Fixed(
-                input[(b + 1)..]
-                    .parse::<Float>()
-                    .map_err(|_| InvalidConstraintSyntax(&input[(b + 1)..]))?,
-            )),
-            None => Err(NotEnoughArgumentsToSizing(input)),
-        },
-        _ => Err(NotASizingUnit(sizing))?,
+            (Length::Percent(percent), range) => {
+                // Constraints on a percentage are relative to the percentage itself
+                let bound = |bound: Length| match bound {
+                    Length::Pixels(pixels) => Ok(ConstraintUnit::Fixed(pixels)),
+                    Length::Percent(_) if percent == 0. => {
+                        Err((InvalidConstraintSyntax, range.clone()))
+                    }
+                    Length::Percent(p) => Ok(ConstraintUnit::Percent(p / percent)),
+                };
+
+                Ok(SizingUnit::Percent(percent, bound(min)?, bound(max)?))
+            }
+        };
+    }
+
+    if arguments.len() < 2 {
+        return Err(missing);
+    }
+
+    let pick = |a: Float, b: Float| if name == "min" { a.min(b) } else { a.max(b) };
+
+    let pixels = arguments
+        .iter()
+        .filter_map(|(length, _)| match length {
+            Length::Pixels(pixels) => Some(*pixels),
+            Length::Percent(_) => None,
+        })
+        .reduce(pick);
+
+    let percent = arguments
+        .iter()
+        .filter_map(|(length, _)| match length {
+            Length::Percent(percent) => Some(*percent),
+            Length::Pixels(_) => None,
+        })
+        .reduce(pick);
+
+    Ok(match (pixels, percent) {
+        (Some(pixels), None) => SizingUnit::Fixed(pixels),
+        (None, Some(percent)) => {
+            SizingUnit::Percent(percent, ConstraintUnit::None, ConstraintUnit::None)
+        }
+        (Some(pixels), Some(percent)) if name == "min" => {
+            SizingUnit::Percent(percent, ConstraintUnit::None, ConstraintUnit::Fixed(pixels))
+        }
+        (Some(pixels), Some(percent)) => {
+            SizingUnit::Percent(percent, ConstraintUnit::Fixed(pixels), ConstraintUnit::None)
+        }
+        (None, None) => unreachable!("there are at least two arguments"),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ElementBuilder, LayoutContext, Sizing};
+
+    #[test]
+    fn points_at_invalid_input() {
+        let error = parse_sizing_unit("Strech:20").unwrap_err();
+        assert_eq!((error.kind, error.range.clone()), (NotASizingUnit, 0..6));
+        assert_eq!(error.offending(), "Strech");
+
+        let error = parse_sizing_unit("Percent:0.5,_,3O%").unwrap_err();
+        assert_eq!(error.kind, InvalidConstraintSyntax);
+        assert_eq!(error.offending(), "3O%");
+
+        let error = parse_sizing_unit("Collapse:1x").unwrap_err();
+        assert_eq!(error.range, 9..11);
+        assert_eq!(
+            error.to_string(),
+            "invalid constraint '1x' in 'Collapse:1x' at 9..11"
+        );
+
+        let error = parse_sizing_unit("Percent:0.5").unwrap_err();
+        assert_eq!(error.kind, NotEnoughArgumentsToSizing);
+
+        // The error owns its input, so it outlives it
+        let error: Box<dyn Error> = {
+            let input = String::from("Fixed:");
+            Box::new(parse_sizing_unit(&input).unwrap_err())
+        };
+        assert!(error.to_string().starts_with("invalid constraint ''"));
+    }
+
+    #[test]
+    fn parses_shorthand() {
+        let parse = |input| parse_sizing_unit(input).unwrap();
+        let none = || ConstraintUnit::None;
+
+        assert_eq!(parse("60px"), SizingUnit::Fixed(60.));
+        assert_eq!(parse("50%"), SizingUnit::Percent(0.5, none(), none()));
+        assert_eq!(parse("1.5fr"), SizingUnit::Fraction(1.5));
+        assert_eq!(parse("auto"), SizingUnit::Collapse(none()));
+        assert_eq!(
+            parse("Stretch:600px"),
+            SizingUnit::Stretch(ConstraintUnit::Fixed(600.))
+        );
+
+        assert_eq!(
+            parse("min(200px, 30%)"),
+            SizingUnit::Percent(0.3, none(), ConstraintUnit::Fixed(200.))
+        );
+        assert_eq!(
+            parse("max(200px,30%, 10%)"),
+            SizingUnit::Percent(0.3, ConstraintUnit::Fixed(200.), none())
+        );
+        assert_eq!(parse("min(20px, 30px)"), SizingUnit::Fixed(20.));
+        assert_eq!(
+            parse("clamp(100px, 50%, 600px)"),
+            SizingUnit::Percent(
+                0.5,
+                ConstraintUnit::Fixed(100.),
+                ConstraintUnit::Fixed(600.)
+            )
+        );
+        assert_eq!(
+            parse("clamp(25%, 50%, 600px)"),
+            SizingUnit::Percent(
+                0.5,
+                ConstraintUnit::Percent(0.5),
+                ConstraintUnit::Fixed(600.)
+            )
+        );
+        assert_eq!(parse("clamp(100px, 50px, 600px)"), SizingUnit::Fixed(100.));
+
+        // Clamped percentages are constrained by the other lengths
+        let clamp = parse("clamp(100px, 50%, 600px)");
+        let context = LayoutContext::default();
+        assert_eq!(clamp.calculate(0., 0., 100., &context), 100.);
+        assert_eq!(clamp.calculate(0., 0., 800., &context), 400.);
+        assert_eq!(clamp.calculate(0., 0., 2000., &context), 600.);
+
+        let error = parse_sizing_unit("clamp(100px, 50%, 6O0px)").unwrap_err();
+        assert_eq!(error.offending(), "6O0px");
+
+        let error = parse_sizing_unit("min(200px)").unwrap_err();
+        assert_eq!(error.kind, NotEnoughArgumentsToSizing);
+
+        let error = parse_sizing_unit("min(200px, 30%").unwrap_err();
+        assert_eq!(error.offending(), "(200px, 30%");
+    }
+
+    #[test]
+    fn resolves_percentages_alike() {
+        let context = LayoutContext::default();
+        let size = |input| {
+            parse_sizing_unit(input)
+                .unwrap()
+                .calculate(0., 200., 200., &context)
+        };
+
+        // A percentage is of the same value whether it's a size or a constraint
+        assert_eq!(size("50%"), 100.);
+        assert_eq!(size("Stretch:50%"), 100.);
+        assert_eq!(size("Percent:1,_,50%"), 100.);
+        assert_eq!(size("min(300px, 50%)"), 100.);
+        assert_eq!(size("max(50px, 10%)"), 50.);
+        assert_eq!(
+            parse_constraint_unit("50%")
+                .unwrap()
+                .calculate(200., &context),
+            100.
+        );
+
+        assert_eq!(
+            parse_sizing_unit("Stretch:50%").unwrap(),
+            crate::layout!(@sizing Stretch(50%))
+        );
+    }
+
+    #[test]
+    fn builds_sizing_fallibly() {
+        let error = ElementBuilder::new()
+            .try_sizing("Stretch", "Strech")
+            .err()
+            .unwrap();
+        assert_eq!(error.input, "Strech");
+
+        let parsed = ElementBuilder::new()
+            .try_sizing("Fixed:35", "Stretch")
+            .unwrap()
+            .build();
+        let typed = ElementBuilder::new()
+            .size(Sizing::stretch())
+            .width(SizingUnit::Fixed(35.))
+            .build();
+
+        assert_eq!(parsed.sizing().width, typed.sizing().width);
+        assert_eq!(parsed.sizing().height, typed.sizing().height);
     }
 }
@@ -0,0 +1,398 @@
+use std::{fmt, ops::Range};
+
+use super::{shifted, Failure, ParsingError, ParsingErrorKind::InvalidRelationship};
+use crate::{Anchor, Attribute, Float, LinearExpression, Relation, Relationship, Strength};
+
+/// Labels that can be written without quotes
+fn is_identifier(label: &str) -> bool {
+    label.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && label != "parent"
+        && label
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+fn attribute_name(attribute: Attribute) -> &'static str {
+    match attribute {
+        Attribute::Left => "left",
+        Attribute::Right => "right",
+        Attribute::Top => "top",
+        Attribute::Bottom => "bottom",
+        Attribute::Width => "width",
+        Attribute::Height => "height",
+        Attribute::CenterX => "center-x",
+        Attribute::CenterY => "center-y",
+    }
+}
+
+const ATTRIBUTES: [Attribute; 8] = [
+    Attribute::Left,
+    Attribute::Right,
+    Attribute::Top,
+    Attribute::Bottom,
+    Attribute::Width,
+    Attribute::Height,
+    Attribute::CenterX,
+    Attribute::CenterY,
+];
+
+const STRENGTHS: [(Strength, &str); 4] = [
+    (Strength::Required, "required"),
+    (Strength::Strong, "strong"),
+    (Strength::Medium, "medium"),
+    (Strength::Weak, "weak"),
+];
+
+impl fmt::Display for Anchor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.label() {
+            None => write!(f, "parent")?,
+            Some(label) if is_identifier(label) => write!(f, "{}", label)?,
+            Some(label) => write!(f, "'{}'", label.replace('\\', "\\\\").replace('\'', "\\'"))?,
+        }
+
+        write!(f, ".{}", attribute_name(self.attribute()))
+    }
+}
+
+impl fmt::Display for LinearExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (coefficient, anchor)) in self.terms().iter().enumerate() {
+            let negative = coefficient.is_sign_negative();
+
+            match (i, negative) {
+                (0, true) => write!(f, "-")?,
+                (0, false) => {}
+                (_, true) => write!(f, " - ")?,
+                (_, false) => write!(f, " + ")?,
+            }
+
+            match coefficient.abs() {
+                1. => write!(f, "{}", anchor)?,
+                c => write!(f, "{}*{}", c, anchor)?,
+            }
+        }
+
+        let constant = self.constant();
+
+        match (self.terms().is_empty(), constant) {
+            (true, _) => write!(f, "{}", constant),
+            (false, 0.) => Ok(()),
+            (false, c) if c < 0. => write!(f, " - {}", -c),
+            (false, c) => write!(f, " + {}", c),
+        }
+    }
+}
+
+impl fmt::Display for Relationship {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (left, relation, right, strength) = self.as_tuple();
+
+        let relation = match relation {
+            Relation::Equal => "=",
+            Relation::AtMost => "<=",
+            Relation::AtLeast => ">=",
+        };
+
+        write!(f, "{} {} {}", left, relation, right)?;
+
+        match STRENGTHS.iter().find(|(s, _)| *s == strength) {
+            Some((Strength::Required, _)) | None => Ok(()),
+            Some((_, name)) => write!(f, " !{}", name),
+        }
+    }
+}
+
+/// The characters outside of quoted labels with their byte index, which is where
+/// separators and operators are looked for
+fn unquoted(input: &str) -> impl Iterator<Item = (usize, char)> + '_ {
+    let mut quoted = false;
+    let mut escaped = false;
+
+    input.char_indices().filter(move |(_, c)| {
+        if escaped {
+            escaped = false;
+            return false;
+        }
+
+        match c {
+            '\\' if quoted => {
+                escaped = true;
+                false
+            }
+            '\'' => {
+                quoted = !quoted;
+                false
+            }
+            _ => !quoted,
+        }
+    })
+}
+
+/// The range without the whitespace at either end
+fn trimmed(input: &str, range: Range<usize>) -> Range<usize> {
+    let part = &input[range.clone()];
+    let start = range.start + part.len() - part.trim_start().len();
+
+    start..start + part.trim().len()
+}
+
+/// Parses relationships separated by semicolons, such as
+/// `avatar.left = parent.left + 8; name.left >= avatar.right + 4 !strong`.
+pub fn parse_relationships(input: &str) -> Result<Vec<Relationship>, ParsingError> {
+    let mut ends: Vec<usize> = unquoted(input)
+        .filter(|(_, c)| *c == ';')
+        .map(|(i, _)| i)
+        .collect();
+    ends.push(input.len());
+
+    let mut start = 0;
+    let mut relationships = Vec::new();
+
+    for end in ends {
+        let part = &input[start..end];
+
+        if !part.trim().is_empty() {
+            let relationship = relationship(part)
+                .map_err(shifted(start))
+                .map_err(|failure| ParsingError::new(input, failure))?;
+
+            relationships.push(relationship);
+        }
+
+        start = end + 1;
+    }
+
+    Ok(relationships)
+}
+
+pub fn parse_relationship(input: &str) -> Result<Relationship, ParsingError> {
+    relationship(input).map_err(|failure| ParsingError::new(input, failure))
+}
+
+/// Parses a sum of constants and anchors that are optionally multiplied by a constant
+pub fn parse_expression(input: &str) -> Result<LinearExpression, ParsingError> {
+    expression(input, 0..input.len()).map_err(|failure| ParsingError::new(input, failure))
+}
+
+fn relationship(input: &str) -> Result<Relationship, Failure> {
+    let (end, strength) = match unquoted(input).filter(|(_, c)| *c == '!').last() {
+        Some((i, _)) => {
+            let name = trimmed(input, i + 1..input.len());
+            let (strength, _) = STRENGTHS
+                .iter()
+                .find(|(_, n)| *n == &input[name.clone()])
+                .ok_or((InvalidRelationship, name))?;
+
+            (i, *strength)
+        }
+        None => (input.len(), Strength::Required),
+    };
+
+    let (position, relation, length) = unquoted(&input[..end])
+        .find_map(|(i, c)| match (c, input[i + 1..end].starts_with('=')) {
+            ('<', true) => Some((i, Relation::AtMost, 2)),
+            ('>', true) => Some((i, Relation::AtLeast, 2)),
+            ('=', _) => Some((i, Relation::Equal, 1)),
+            _ => None,
+        })
+        .ok_or_else(|| (InvalidRelationship, trimmed(input, 0..end)))?;
+
+    let left = expression(input, 0..position)?;
+    let right = expression(input, position + length..end)?;
+
+    Ok(Relationship::new(left, relation, right).strength(strength))
+}
+
+/// Parses the expression in the range of the input, which fails as a whole
+fn expression(input: &str, range: Range<usize>) -> Result<LinearExpression, Failure> {
+    let range = trimmed(input, range);
+
+    linear_expression(&input[range.clone()]).ok_or((InvalidRelationship, range))
+}
+
+fn linear_expression(input: &str) -> Option<LinearExpression> {
+    let mut rest = input.trim();
+    let mut expression = LinearExpression::default();
+    let mut sign = 1.;
+    let mut first = true;
+
+    loop {
+        if let Some(r) = rest.strip_prefix('-') {
+            sign = -sign;
+            rest = r.trim_start();
+        } else if let Some(r) = rest.strip_prefix('+').filter(|_| !first) {
+            rest = r.trim_start();
+        } else if !first {
+            return None;
+        }
+
+        let (term, r) = parse_term(rest)?;
+        rest = r.trim_start();
+
+        expression = match term {
+            Term::Constant(c) => expression + sign * c,
+            Term::Anchor(c, anchor) => expression + anchor * (sign * c),
+        };
+
+        if rest.is_empty() {
+            return Some(expression);
+        }
+
+        // Only the sign of a term is carried over from the operator before it
+        sign = 1.;
+        first = false;
+
+        if !(rest.starts_with('+') || rest.starts_with('-')) {
+            return None;
+        }
+    }
+}
+
+enum Term {
+    Constant(Float),
+    Anchor(Float, Anchor),
+}
+
+/// Parses a constant, an anchor, or both multiplied in either order
+fn parse_term(input: &str) -> Option<(Term, &str)> {
+    let (first, rest) = parse_factor(input)?;
+    let rest = rest.trim_start();
+
+    let (second, rest) = match rest.strip_prefix('*') {
+        Some(r) => {
+            let (second, rest) = parse_factor(r.trim_start())?;
+            (Some(second), rest)
+        }
+        None => (None, rest),
+    };
+
+    let term = match (first, second) {
+        (Factor::Constant(c), None) => Term::Constant(c),
+        (Factor::Anchor(a), None) => Term::Anchor(1., a),
+        (Factor::Constant(c), Some(Factor::Anchor(a)))
+        | (Factor::Anchor(a), Some(Factor::Constant(c))) => Term::Anchor(c, a),
+        (Factor::Constant(a), Some(Factor::Constant(b))) => Term::Constant(a * b),
+        (Factor::Anchor(_), Some(Factor::Anchor(_))) => return None,
+    };
+
+    Some((term, rest))
+}
+
+enum Factor {
+    Constant(Float),
+    Anchor(Anchor),
+}
+
+fn parse_factor(input: &str) -> Option<(Factor, &str)> {
+    let number_length = input
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E'))
+        .unwrap_or(input.len());
+
+    if number_length > 0 && input.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+        let number = input[..number_length].parse().ok()?;
+        return Some((Factor::Constant(number), &input[number_length..]));
+    }
+
+    let (label, rest) = match input.strip_prefix('\'') {
+        Some(quoted) => {
+            let mut label = String::new();
+            let mut chars = quoted.char_indices();
+
+            let end = loop {
+                match chars.next()? {
+                    (i, '\'') => break i,
+                    (_, '\\') => label.push(chars.next()?.1),
+                    (_, c) => label.push(c),
+                }
+            };
+
+            (Some(label), &quoted[end + 1..])
+        }
+        None => {
+            let end = input
+                .find(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'))
+                .unwrap_or(input.len());
+
+            match &input[..end] {
+                "" => return None,
+                "parent" => (None, &input[end..]),
+                label => (Some(label.to_string()), &input[end..]),
+            }
+        }
+    };
+
+    let rest = rest.strip_prefix('.')?;
+    let end = rest
+        .find(|c: char| !(c.is_alphanumeric() || c == '-'))
+        .unwrap_or(rest.len());
+
+    let attribute = *ATTRIBUTES
+        .iter()
+        .find(|a| attribute_name(**a) == &rest[..end])?;
+
+    let anchor = match label {
+        Some(label) => Anchor::new(label, attribute),
+        None => Anchor::parent(attribute),
+    };
+
+    Some((Factor::Anchor(anchor), &rest[end..]))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trips_relationships() {
+        let relationships = vec![
+            Anchor::new("a", Attribute::Width).equals(50.),
+            Anchor::new("b", Attribute::Left).equals(Anchor::new("a", Attribute::Right) + 8.),
+            Anchor::new("side bar", Attribute::CenterX)
+                .at_least(Anchor::parent(Attribute::Width) * 0.5 - 4.)
+                .strength(Strength::Weak),
+            (Anchor::new("b", Attribute::Right) * -2. - Anchor::new("a", Attribute::Top))
+                .at_most(-3.5),
+        ];
+
+        let written: Vec<String> = relationships.iter().map(|r| r.to_string()).collect();
+
+        assert_eq!(written[1], "b.left = a.right + 8");
+        assert_eq!(
+            written[2],
+            "'side bar'.center-x >= 0.5*parent.width - 4 !weak"
+        );
+        assert_eq!(written[3], "-2*b.right - a.top <= -3.5");
+
+        let parsed = parse_relationships(&written.join("; ")).unwrap();
+        assert_eq!(parsed, relationships);
+
+        let error = parse_relationships("a.left = 4; a.pizza = 2").unwrap_err();
+        assert_eq!(error.kind, InvalidRelationship);
+        assert_eq!(error.range, 12..19);
+        assert_eq!(error.offending(), "a.pizza");
+
+        let error = parse_relationships("a.left = 4 !loud").unwrap_err();
+        assert_eq!(error.offending(), "loud");
+    }
+
+    #[test]
+    fn round_trips_quoted_labels() {
+        for label in &["a;b", "a=b", "x!y", "it's", "a<=b", "back\\slash", "'"] {
+            let relationship = Anchor::new(label, Attribute::Left)
+                .at_least(Anchor::new("b", Attribute::Right) + 2.)
+                .strength(Strength::Strong);
+            let written = format!("{}; b.top = 0", relationship);
+
+            let parsed = parse_relationships(&written).unwrap();
+            assert_eq!(parsed[0], relationship, "{}", written);
+            assert_eq!(parsed.len(), 2);
+        }
+
+        assert_eq!(
+            Anchor::new("it's", Attribute::Top).to_string(),
+            "'it\\'s'.top"
+        );
+        assert!(parse_relationships("'a;b.left = 4").is_err());
+    }
+}
@@ -0,0 +1,243 @@
+use std::{
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{Element, StyleState};
+
+use super::{parse_stylesheet, MarkupError, MarkupParser, StylesheetError};
+
+/// Watches files by polling their modification times, so it doesn't need any platform services
+pub struct FileWatcher {
+    /// Every file with its modification time when it was last polled, if it could be read
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+    interval: Duration,
+    last_poll: Option<Instant>,
+}
+
+impl Default for FileWatcher {
+    fn default() -> Self {
+        FileWatcher::new()
+    }
+}
+
+impl FileWatcher {
+    pub fn new() -> FileWatcher {
+        FileWatcher {
+            files: Vec::new(),
+            interval: Duration::from_millis(250),
+            last_poll: None,
+        }
+    }
+
+    /// How often the files are checked at most (by default 250 milliseconds)
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn watch(mut self, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let modified = FileWatcher::modified(&path);
+
+        self.files.push((path, modified));
+        self
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.files.iter().map(|(path, _)| path.as_path())
+    }
+
+    /// Whether any file was modified, created or removed since the last poll.
+    /// Polling again before the interval has passed doesn't check the files.
+    pub fn poll(&mut self) -> bool {
+        let now = Instant::now();
+
+        if let Some(last_poll) = self.last_poll {
+            if now.duration_since(last_poll) < self.interval {
+                return false;
+            }
+        }
+
+        self.last_poll = Some(now);
+
+        let mut changed = false;
+
+        for (path, modified) in self.files.iter_mut() {
+            let current = FileWatcher::modified(path);
+
+            if current != *modified {
+                *modified = current;
+                changed = true;
+            }
+        }
+
+        changed
+    }
+
+    fn modified(path: &Path) -> Option<SystemTime> {
+        fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+}
+
+#[derive(Debug)]
+/// A layout or stylesheet file that couldn't be loaded, with its path
+pub enum ReloadError {
+    Io(PathBuf, io::Error),
+    Markup(PathBuf, MarkupError),
+    Stylesheet(PathBuf, StylesheetError),
+}
+
+impl fmt::Display for ReloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReloadError::Io(path, error) => write!(f, "{}: {}", path.display(), error),
+            ReloadError::Markup(path, error) => write!(f, "{}:{}", path.display(), error),
+            ReloadError::Stylesheet(path, error) => write!(f, "{}:{}", path.display(), error),
+        }
+    }
+}
+
+impl Error for ReloadError {}
+
+/// Loads an element tree from a markup file and an optional stylesheet,
+/// and loads it again whenever either of the files changes
+pub struct HotReload {
+    parser: MarkupParser,
+    layout: PathBuf,
+    stylesheet: Option<PathBuf>,
+    watcher: FileWatcher,
+}
+
+impl HotReload {
+    pub fn new(parser: MarkupParser, layout: impl Into<PathBuf>) -> HotReload {
+        let layout = layout.into();
+
+        HotReload {
+            parser,
+            watcher: FileWatcher::new().watch(layout.clone()),
+            layout,
+            stylesheet: None,
+        }
+    }
+
+    /// The stylesheet that is applied to the layout, in the default state
+    pub fn stylesheet(mut self, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+
+        self.watcher = self.watcher.watch(path.clone());
+        self.stylesheet = Some(path);
+        self
+    }
+
+    /// How often the files are checked for changes at most
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.watcher = self.watcher.interval(interval);
+        self
+    }
+
+    /// Parses the files as they are now
+    pub fn load(&self) -> Result<Element, ReloadError> {
+        let read = |path: &Path| {
+            fs::read_to_string(path).map_err(|error| ReloadError::Io(path.to_path_buf(), error))
+        };
+
+        let mut element = self
+            .parser
+            .parse(&read(&self.layout)?)
+            .map_err(|error| ReloadError::Markup(self.layout.clone(), error))?;
+
+        if let Some(path) = &self.stylesheet {
+            let stylesheet = parse_stylesheet(&read(path)?)
+                .map_err(|error| ReloadError::Stylesheet(path.clone(), error))?;
+
+            stylesheet.apply(&mut element, &StyleState::new());
+        }
+
+        Ok(element)
+    }
+
+    /// Parses the files again if any of them changed since the last poll
+    pub fn poll(&mut self) -> Option<Result<Element, ReloadError>> {
+        match self.watcher.poll() {
+            true => Some(self.load()),
+            false => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        fs::{self, File},
+        path::PathBuf,
+        time::{Duration, SystemTime},
+    };
+
+    use crate::{MarkupParser, SizingUnit};
+
+    use super::{HotReload, ReloadError};
+
+    fn temporary(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("rust-gui-{}-{}", std::process::id(), name));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    /// Writes the file with a later modification time, since writes
+    /// in quick succession can end up with the same one
+    fn rewrite(path: &PathBuf, contents: &str, seconds: u64) {
+        fs::write(path, contents).unwrap();
+
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(seconds))
+            .unwrap();
+    }
+
+    #[test]
+    fn reloads_changed_files() {
+        let layout = temporary("layout", "<none width=10px height=10px label=box/>");
+        let stylesheet = temporary("stylesheet", ".box { width: 20px; }");
+
+        let mut reload = HotReload::new(MarkupParser::new(), &layout)
+            .stylesheet(&stylesheet)
+            .interval(Duration::from_secs(0));
+
+        let element = reload.load().unwrap();
+        assert_eq!(element.sizing.width, SizingUnit::Fixed(20.));
+        assert!(reload.poll().is_none());
+
+        rewrite(&stylesheet, ".box { width: 30px; }", 10);
+        let element = reload.poll().unwrap().unwrap();
+        assert_eq!(element.sizing.width, SizingUnit::Fixed(30.));
+        assert!(reload.poll().is_none());
+
+        // Errors are reported with the file they're in, and the next change is loaded again
+        rewrite(&layout, "<none width=10px", 20);
+
+        match reload.poll() {
+            Some(Err(ReloadError::Markup(path, error))) => {
+                assert_eq!(path, layout);
+                assert_eq!((error.line, error.column), (1, 17));
+            }
+            other => panic!(
+                "expected a markup error, got {:?}",
+                other.map(|r| r.is_ok())
+            ),
+        }
+
+        rewrite(&layout, "<none width=10px height=15px label=box/>", 30);
+        let element = reload.poll().unwrap().unwrap();
+        assert_eq!(element.sizing.height, SizingUnit::Fixed(15.));
+
+        fs::remove_file(&stylesheet).unwrap();
+        assert!(matches!(reload.poll(), Some(Err(ReloadError::Io(..)))));
+
+        fs::remove_file(&layout).unwrap();
+    }
+}
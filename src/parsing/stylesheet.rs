@@ -0,0 +1,472 @@
+use std::fmt;
+
+use crate::{Combinator, CompoundSelector, ElementState, Rule, Selector, Stylesheet};
+
+use super::{
+    parse_alignment, parse_padding, parse_sizing_unit, ParsingError,
+    ParsingErrorKind::InvalidSelector,
+};
+
+/// Parses a selector such as `.sidebar > directional.user:hover`,
+/// where `*` matches every element
+pub fn parse_selector(input: &str) -> Result<Selector, ParsingError> {
+    let invalid = |start: usize, end: usize| {
+        // Points at the last character if the selector ends early, or at the unexpected one
+        let last = input.char_indices().last().map_or(0, |(i, _)| i);
+        let start = start.min(last);
+        let end = match end > start {
+            true => end,
+            false => start + input[start..].chars().next().map_or(0, char::len_utf8),
+        };
+
+        ParsingError {
+            kind: InvalidSelector,
+            input: input.to_string(),
+            range: start..end,
+        }
+    };
+
+    let mut parts = Vec::new();
+    let mut combinator = Combinator::Descendant;
+    let mut position = 0;
+
+    loop {
+        let (compound, end) =
+            compound_selector(input, position).map_err(|(start, end)| invalid(start, end))?;
+
+        parts.push((combinator, compound));
+
+        let rest = &input[end..];
+        let trimmed = rest.trim_start();
+        position = end + (rest.len() - trimmed.len());
+
+        if trimmed.is_empty() {
+            break;
+        }
+
+        combinator = match trimmed.strip_prefix('>') {
+            Some(after) => {
+                position += 1 + (after.len() - after.trim_start().len());
+                Combinator::Child
+            }
+            // Compound selectors are only separated by whitespace or combinators
+            None if position == end => return Err(invalid(position, position)),
+            None => Combinator::Descendant,
+        };
+    }
+
+    let (_, first) = parts.remove(0);
+    let mut selector = Selector::new(first);
+
+    for (combinator, compound) in parts {
+        selector = match combinator {
+            Combinator::Descendant => selector.descendant(compound),
+            Combinator::Child => selector.child(compound),
+        };
+    }
+
+    Ok(selector)
+}
+
+fn is_name(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_'
+}
+
+/// Parses a compound selector at the given position, returning where it ends,
+/// or the range of what couldn't be parsed
+fn compound_selector(
+    input: &str,
+    start: usize,
+) -> Result<(CompoundSelector, usize), (usize, usize)> {
+    let name_end = |from: usize| {
+        input[from..]
+            .find(|c: char| !is_name(c))
+            .map_or(input.len(), |i| from + i)
+    };
+
+    let mut compound = CompoundSelector::new();
+    let mut position = start;
+
+    if input[position..].starts_with('*') {
+        position += 1;
+    } else if input[position..].starts_with(is_name) {
+        let end = name_end(position);
+        compound = compound.kind(&input[position..end]);
+        position = end;
+    }
+
+    loop {
+        let prefix = match input[position..].chars().next() {
+            Some(c) if c == '.' || c == ':' => c,
+            _ => break,
+        };
+
+        let end = name_end(position + 1);
+        let name = &input[position + 1..end];
+
+        compound = match (prefix, name) {
+            (_, "") => return Err((position, end)),
+            ('.', label) => compound.label(label),
+            (_, "hover") => compound.state(ElementState::Hover),
+            (_, "focus") => compound.state(ElementState::Focus),
+            _ => return Err((position, end)),
+        };
+
+        position = end;
+    }
+
+    if position == start {
+        return Err((start, start));
+    }
+
+    Ok((compound, position))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum StylesheetErrorKind {
+    UnexpectedEnd,
+    UnexpectedCharacter(char),
+    InvalidSelector(String),
+    UnknownProperty(String),
+
+    /// The property and the value that couldn't be parsed
+    InvalidValue(String, String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// An error in a stylesheet, at a line and column that both start at 1
+pub struct StylesheetError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: StylesheetErrorKind,
+}
+
+impl fmt::Display for StylesheetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use StylesheetErrorKind::*;
+
+        write!(f, "{}:{}: ", self.line, self.column)?;
+
+        match &self.kind {
+            UnexpectedEnd => write!(f, "unexpected end of input"),
+            UnexpectedCharacter(c) => write!(f, "unexpected character '{}'", c),
+            InvalidSelector(selector) => write!(f, "invalid selector '{}'", selector),
+            UnknownProperty(property) => write!(f, "unknown property '{}'", property),
+            InvalidValue(property, value) => {
+                write!(f, "invalid value '{}' for '{}'", value, property)
+            }
+        }
+    }
+}
+
+/// Parses rules such as
+///
+/// ```text
+/// /* Every user in the sidebar */
+/// .sidebar > .user, .member {
+///     width: Stretch;
+///     height: 40px;
+///     padding: 4,4,8,8;
+///     align: start,middle;
+///     spacing: 8;
+/// }
+/// ```
+///
+/// A rule with several selectors is added once for every selector, in order.
+pub fn parse_stylesheet(input: &str) -> Result<Stylesheet, StylesheetError> {
+    let error_at = |position: usize, kind: StylesheetErrorKind| {
+        let before = &input[..position];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+        StylesheetError {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            kind,
+        }
+    };
+
+    let skip_whitespace = |mut position: usize| -> Result<usize, StylesheetError> {
+        loop {
+            let rest = &input[position..];
+            position += rest.len() - rest.trim_start().len();
+
+            if !input[position..].starts_with("/*") {
+                return Ok(position);
+            }
+
+            match input[position..].find("*/") {
+                Some(end) => position += end + 2,
+                None => return Err(error_at(input.len(), StylesheetErrorKind::UnexpectedEnd)),
+            }
+        }
+    };
+
+    let mut stylesheet = Stylesheet::new();
+    let mut position = skip_whitespace(0)?;
+
+    while position < input.len() {
+        let open = match input[position..].find('{') {
+            Some(i) => position + i,
+            None => return Err(error_at(input.len(), StylesheetErrorKind::UnexpectedEnd)),
+        };
+
+        let mut selectors = Vec::new();
+        let mut start = position;
+
+        for selector in input[position..open].split(',') {
+            let leading = selector.len() - selector.trim_start().len();
+
+            let parsed = parse_selector(selector.trim()).map_err(|error| {
+                error_at(
+                    start + leading + error.range.start,
+                    StylesheetErrorKind::InvalidSelector(selector.trim().into()),
+                )
+            })?;
+
+            selectors.push(parsed);
+            start += selector.len() + 1;
+        }
+
+        // Properties are collected once, then shared by a rule for every selector
+        let mut rule = Rule::new(selectors[0].clone());
+        position = skip_whitespace(open + 1)?;
+
+        loop {
+            match input[position..].chars().next() {
+                Some('}') => {
+                    position += 1;
+                    break;
+                }
+                Some(_) => {}
+                None => return Err(error_at(position, StylesheetErrorKind::UnexpectedEnd)),
+            }
+
+            let name_start = position;
+            let name_end = input[position..]
+                .find(|c: char| !is_name(c))
+                .map_or(input.len(), |i| position + i);
+            let name = &input[name_start..name_end];
+
+            position = skip_whitespace(name_end)?;
+
+            match input[position..].chars().next() {
+                Some(':') if !name.is_empty() => position += 1,
+                Some(c) => {
+                    return Err(error_at(
+                        position,
+                        StylesheetErrorKind::UnexpectedCharacter(c),
+                    ))
+                }
+                None => return Err(error_at(position, StylesheetErrorKind::UnexpectedEnd)),
+            }
+
+            let value_end = input[position..]
+                .find([';', '}'])
+                .map_or(input.len(), |i| position + i);
+            let raw = &input[position..value_end];
+            let value = raw.trim();
+            let value_start = position + (raw.len() - raw.trim_start().len());
+
+            let invalid = || {
+                error_at(
+                    value_start,
+                    StylesheetErrorKind::InvalidValue(name.into(), value.into()),
+                )
+            };
+
+            rule = match name {
+                "width" | "height" => {
+                    let unit = parse_sizing_unit(value).map_err(|error| {
+                        error_at(
+                            value_start + error.range.start,
+                            StylesheetErrorKind::InvalidValue(name.into(), value.into()),
+                        )
+                    })?;
+
+                    match name {
+                        "width" => rule.width(unit),
+                        _ => rule.height(unit),
+                    }
+                }
+                "padding" => rule.padding(parse_padding(value).ok_or_else(invalid)?),
+                "align" => {
+                    let (horizontal, vertical) =
+                        parse_alignment(value).ok_or_else(invalid)?.as_tuple();
+                    rule.align(horizontal, vertical)
+                }
+                "spacing" => rule.spacing(value.parse().map_err(|_| invalid())?),
+                _ => {
+                    return Err(error_at(
+                        name_start,
+                        StylesheetErrorKind::UnknownProperty(name.into()),
+                    ))
+                }
+            };
+
+            position = value_end;
+
+            if input[position..].starts_with(';') {
+                position += 1;
+            }
+
+            position = skip_whitespace(position)?;
+        }
+
+        for selector in selectors {
+            stylesheet = stylesheet.rule(Rule {
+                selector,
+                properties: rule.properties.clone(),
+            });
+        }
+
+        position = skip_whitespace(position)?;
+    }
+
+    Ok(stylesheet)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    fn sidebar() -> Element {
+        let user = |label: &str| {
+            ElementBuilder::new()
+                .directional(Direction::Horizontal, 0.)
+                .sizing("Fixed:10", "Fixed:10")
+                .label(label)
+                .children(vec![ElementBuilder::new()
+                    .directional(Direction::Horizontal, 0.)
+                    .label("avatar")
+                    .build()])
+                .build()
+        };
+
+        ElementBuilder::new()
+            .directional(Direction::Vertical, 0.)
+            .sizing("Stretch", "Stretch")
+            .label("sidebar")
+            .children(vec![user("user"), user("krabbe")])
+            .build()
+    }
+
+    #[test]
+    fn parses_selectors() {
+        let selector = parse_selector("directional.sidebar > .user:hover  .avatar").unwrap();
+
+        assert_eq!(
+            selector,
+            Selector::new(CompoundSelector::new().kind("directional").label("sidebar"))
+                .child(
+                    CompoundSelector::new()
+                        .label("user")
+                        .state(ElementState::Hover)
+                )
+                .descendant(CompoundSelector::new().label("avatar"))
+        );
+        assert_eq!(selector.specificity(), (4, 1));
+
+        assert_eq!(parse_selector("*").unwrap().specificity(), (0, 0));
+        assert_eq!(
+            parse_selector(".user:active").unwrap_err().offending(),
+            ":active"
+        );
+        assert_eq!(parse_selector(".user >").unwrap_err().offending(), ">");
+        assert_eq!(parse_selector(".user$").unwrap_err().offending(), "$");
+    }
+
+    #[test]
+    fn cascades_by_specificity_and_order() {
+        let stylesheet = parse_stylesheet(
+            "
+            /* Every user in the sidebar */
+            .sidebar > directional { height: 40px; padding: 4 }
+            .sidebar .user, .krabbe { height: 50px; spacing: 8 }
+            directional { height: 10px; width: Stretch }
+            .user:hover { height: calc(100% - 8px); align: middle,end }
+            .sidebar > .avatar { width: 1px }
+            ",
+        )
+        .unwrap();
+
+        let mut element = sidebar();
+        stylesheet.apply(&mut element, &StyleState::new());
+
+        let user = &element.children()[0];
+        let krabbe = &element.children()[1];
+
+        assert_eq!(user.sizing().height, SizingUnit::Fixed(50.));
+        assert_eq!(
+            user.sizing().width,
+            SizingUnit::Stretch(ConstraintUnit::None)
+        );
+        assert_eq!(user.padding().as_tuple(), (4., 4., 4., 4.));
+
+        // A more specific rule wins over one that comes later
+        assert_eq!(krabbe.sizing().height, SizingUnit::Fixed(40.));
+
+        // The avatar isn't a child of the sidebar
+        let avatar = &user.children()[0];
+        assert_eq!(
+            avatar.sizing().width,
+            SizingUnit::Stretch(ConstraintUnit::None)
+        );
+
+        // Changing the state restores the properties that no longer apply
+        stylesheet.apply(&mut element, &StyleState::new().hover("user"));
+        let user = &element.children()[0];
+        assert!(matches!(user.sizing().height, SizingUnit::Calc(_)));
+        assert!(matches!(
+            user.alignment().as_tuple(),
+            (AlignUnit::Middle, AlignUnit::End)
+        ));
+
+        stylesheet.apply(&mut element, &StyleState::new().focus("user"));
+        let user = &element.children()[0];
+        assert_eq!(user.sizing().height, SizingUnit::Fixed(50.));
+        assert!(matches!(
+            user.alignment().as_tuple(),
+            (AlignUnit::Start, AlignUnit::Start)
+        ));
+
+        let rect = Rect::new(100., 200., 0., 0.);
+        let calculated = element.calculate(rect.clone(), rect);
+        assert_eq!(calculated.children[1].rect.position.y, 50.);
+    }
+
+    #[test]
+    fn styles_the_mock_sidebar() {
+        let mut sidebar = mock::layout::user_sidebar();
+        mock::layout::stylesheet().apply(&mut sidebar, &StyleState::new());
+
+        let user = &sidebar.children()[0];
+        assert_eq!(
+            user.sizing().width,
+            SizingUnit::Stretch(ConstraintUnit::None)
+        );
+        assert_eq!(user.children()[0].sizing().width, SizingUnit::Fixed(35.));
+    }
+
+    #[test]
+    fn reports_positioned_errors() {
+        let error = parse_stylesheet(".user {\n  width: Strech;\n}").unwrap_err();
+        assert_eq!((error.line, error.column), (2, 10));
+        assert_eq!(
+            error.to_string(),
+            "2:10: invalid value 'Strech' for 'width'"
+        );
+
+        let error = parse_stylesheet(".user {\n  colour: red;\n}").unwrap_err();
+        assert_eq!(
+            error.kind,
+            StylesheetErrorKind::UnknownProperty("colour".into())
+        );
+        assert_eq!((error.line, error.column), (2, 3));
+
+        let error = parse_stylesheet(".a, .b:pressed { width: auto }").unwrap_err();
+        assert_eq!((error.line, error.column), (1, 7));
+
+        let error = parse_stylesheet(".user { width: auto").unwrap_err();
+        assert_eq!(error.kind, StylesheetErrorKind::UnexpectedEnd);
+    }
+}
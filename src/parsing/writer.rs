@@ -0,0 +1,717 @@
+use std::fmt::{self, Write};
+
+use crate::{
+    AlignUnit, Alignment, Breakpoint, BreakpointTarget, CalcExpression, ConstraintUnit, Direction,
+    Element, ElementKind, Float, Padding, SizingUnit, Text, TextAlign, TextDirection, TextStyle,
+    WhiteSpace, OBJECT_REPLACEMENT,
+};
+
+use super::parse_percent;
+
+impl fmt::Display for ConstraintUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConstraintUnit::None => write!(f, "_"),
+            ConstraintUnit::Fixed(value) => write!(f, "{}", value),
+            ConstraintUnit::Percent(value) => write!(f, "{}%", percentage(*value)),
+            ConstraintUnit::Calc(expression) => write!(f, "calc({})", expression),
+        }
+    }
+}
+
+/// The shortest percentage that is read back as the same fraction
+fn percentage(fraction: Float) -> String {
+    let percent = fraction as f64 * 100.;
+
+    (0..17)
+        .map(|digits| format!("{:.*}", digits, percent))
+        .find(|written| parse_percent(written) == Some(fraction))
+        .unwrap_or_else(|| percent.to_string())
+}
+
+impl CalcExpression {
+    /// Operators that bind tighter have a higher precedence, operands have the highest
+    fn precedence(&self) -> u8 {
+        match self {
+            CalcExpression::Add(_, _) | CalcExpression::Subtract(_, _) => 1,
+            CalcExpression::Multiply(_, _) | CalcExpression::Divide(_, _) => 2,
+            _ => 3,
+        }
+    }
+}
+
+/// Written without the surrounding `calc()`, with only the parentheses needed to keep the tree
+impl fmt::Display for CalcExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (left, operator, right) = match self {
+            CalcExpression::Number(n) => return write!(f, "{}", n),
+            CalcExpression::Fixed(n) => return write!(f, "{}px", n),
+            CalcExpression::Percent(p) => return write!(f, "{}%", p),
+            CalcExpression::Rem(rem) => return write!(f, "{}rem", rem),
+            CalcExpression::Content => return write!(f, "content"),
+            CalcExpression::Available => return write!(f, "available"),
+            CalcExpression::Add(a, b) => (a, "+", b),
+            CalcExpression::Subtract(a, b) => (a, "-", b),
+            CalcExpression::Multiply(a, b) => (a, "*", b),
+            CalcExpression::Divide(a, b) => (a, "/", b),
+        };
+
+        match left.precedence() < self.precedence() {
+            true => write!(f, "({})", left)?,
+            false => write!(f, "{}", left)?,
+        }
+
+        write!(f, " {} ", operator)?;
+
+        // Operators are parsed from left to right, so an operand on the right keeps its own
+        match right.precedence() <= self.precedence() {
+            true => write!(f, "({})", right),
+            false => write!(f, "{}", right),
+        }
+    }
+}
+
+/// Written in the format that `parse_sizing_unit` reads, such as `Percent:0.5,_,300`
+impl fmt::Display for SizingUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SizingUnit::Percent(value, min, max) => write!(f, "Percent:{},{},{}", value, min, max),
+            SizingUnit::Fixed(value) => write!(f, "Fixed:{}", value),
+            SizingUnit::Collapse(ConstraintUnit::None) => write!(f, "Collapse"),
+            SizingUnit::Collapse(constraint) => write!(f, "Collapse:{}", constraint),
+            SizingUnit::Stretch(ConstraintUnit::None) => write!(f, "Stretch"),
+            SizingUnit::Stretch(constraint) => write!(f, "Stretch:{}", constraint),
+            SizingUnit::Fraction(fraction) => write!(f, "{}fr", fraction),
+            SizingUnit::Calc(expression) => write!(f, "calc({})", expression),
+        }
+    }
+}
+
+/// One value if every side is the same, otherwise the top, bottom, left and right
+impl fmt::Display for Padding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.as_tuple() {
+            (top, bottom, left, right) if top == bottom && top == left && top == right => {
+                write!(f, "{}", top)
+            }
+            (top, bottom, left, right) => write!(f, "{},{},{},{}", top, bottom, left, right),
+        }
+    }
+}
+
+/// The horizontal and vertical alignment, such as `start,end`
+impl fmt::Display for Alignment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unit = |unit: AlignUnit| match unit {
+            AlignUnit::Start => "start",
+            AlignUnit::Middle => "middle",
+            AlignUnit::End => "end",
+        };
+
+        let (horizontal, vertical) = self.as_tuple();
+        write!(f, "{},{}", unit(horizontal), unit(vertical))
+    }
+}
+
+/// Writes the element tree as markup that `MarkupParser` reads back into the same tree,
+/// with every element on its own line except for the children that flow in inline text.
+/// Text styles are written without their fonts, which come from the parser.
+pub fn write_markup(element: &Element) -> String {
+    let mut output = String::new();
+    write_element(&mut output, element, Some(0));
+    output
+}
+
+/// Writes the element at the indentation level, or on a single line if there is none
+fn write_element(output: &mut String, element: &Element, indent: Option<usize>) {
+    let (name, mut attributes) = match &element.kind {
+        ElementKind::None => ("none".to_string(), Vec::new()),
+        ElementKind::Directional(d) => {
+            let mut attributes = vec![
+                (
+                    "direction".to_string(),
+                    direction_name(d.direction()).to_string(),
+                ),
+                ("spacing".to_string(), d.spacing().to_string()),
+            ];
+
+            if d.is_parallel() {
+                attributes.push(("parallel".to_string(), "true".to_string()));
+            }
+
+            ("directional".to_string(), attributes)
+        }
+        ElementKind::Relational(r) => {
+            let relationships: Vec<String> =
+                r.relationships().iter().map(|r| r.to_string()).collect();
+
+            let attributes = match relationships.is_empty() {
+                true => Vec::new(),
+                false => vec![("relationships".to_string(), relationships.join("; "))],
+            };
+
+            ("relational".to_string(), attributes)
+        }
+        ElementKind::Text(t) => ("text".to_string(), text_attributes(t)),
+        ElementKind::Inline(i) => ("inline".to_string(), text_attributes(i.text())),
+        ElementKind::Extern(e) => (e.kind_name(), e.markup_attributes()),
+    };
+
+    attributes.push(("width".to_string(), element.sizing.width.to_string()));
+    attributes.push(("height".to_string(), element.sizing.height.to_string()));
+
+    if element.padding.as_tuple() != (0., 0., 0., 0.) {
+        attributes.push(("padding".to_string(), element.padding.to_string()));
+    }
+
+    if !matches!(
+        element.alignment.as_tuple(),
+        (AlignUnit::Start, AlignUnit::Start)
+    ) {
+        attributes.push(("align".to_string(), element.alignment.to_string()));
+    }
+
+    if let Some(label) = &element.label {
+        attributes.push(("label".to_string(), label.clone()));
+    }
+
+    let indentation = "  ".repeat(indent.unwrap_or(0));
+    output.push_str(&indentation);
+    write!(output, "<{}", name).unwrap();
+
+    for (attribute, value) in &attributes {
+        write!(output, " {}={}", attribute, attribute_value(value)).unwrap();
+    }
+
+    let text = match &element.kind {
+        ElementKind::Text(t) => Some(t),
+        ElementKind::Inline(i) => Some(i.text()),
+        _ => None,
+    };
+
+    match text {
+        // Whitespace is part of the content, so none is added around it
+        Some(text) => {
+            output.push('>');
+
+            for breakpoint in &element.breakpoints {
+                write_breakpoint(output, breakpoint);
+            }
+
+            let base = base_style(text);
+            let mut children = element.children.iter();
+
+            for span in text.spans().iter().filter(|s| !s.content().is_empty()) {
+                let mut attributes = match base {
+                    Some(base) => style_attributes(span.style(), base),
+                    None => Vec::new(),
+                };
+
+                if let Some(link) = span.target() {
+                    attributes.push(("link".to_string(), link.clone()));
+                }
+
+                if !attributes.is_empty() {
+                    output.push_str("<span");
+
+                    for (attribute, value) in &attributes {
+                        write!(output, " {}={}", attribute, attribute_value(value)).unwrap();
+                    }
+
+                    output.push('>');
+                }
+
+                for (i, piece) in span.content().split(OBJECT_REPLACEMENT).enumerate() {
+                    if i > 0 {
+                        if let Some(child) = children.next() {
+                            write_element(output, child, None);
+                        }
+                    }
+
+                    output.push_str(&escape(piece, false));
+                }
+
+                if !attributes.is_empty() {
+                    output.push_str("</span>");
+                }
+            }
+        }
+        None if element.children.is_empty() && element.breakpoints.is_empty() => {
+            output.push_str("/>");
+            return;
+        }
+        None => {
+            output.push('>');
+
+            for breakpoint in &element.breakpoints {
+                if let Some(level) = indent {
+                    output.push('\n');
+                    output.push_str(&"  ".repeat(level + 1));
+                }
+
+                write_breakpoint(output, breakpoint);
+            }
+
+            for child in &element.children {
+                match indent {
+                    Some(level) => {
+                        output.push('\n');
+                        write_element(output, child, Some(level + 1));
+                    }
+                    None => write_element(output, child, None),
+                }
+            }
+
+            if indent.is_some() {
+                output.push('\n');
+                output.push_str(&indentation);
+            }
+        }
+    }
+
+    write!(output, "</{}>", name).unwrap();
+}
+
+/// Writes the range of the breakpoint and the properties it replaces
+fn write_breakpoint(output: &mut String, breakpoint: &Breakpoint) {
+    let target = match breakpoint.target {
+        BreakpointTarget::Viewport => "viewport",
+        BreakpointTarget::Container => "container",
+    };
+
+    let mut attributes = vec![("target", target.to_string())];
+
+    let range = [
+        ("min-width", breakpoint.min.width),
+        ("max-width", breakpoint.max.width),
+        ("min-height", breakpoint.min.height),
+        ("max-height", breakpoint.max.height),
+    ];
+
+    // The range is unbounded by default
+    for (name, value) in range.iter() {
+        if *value != 0. && value.is_finite() {
+            attributes.push((name, value.to_string()));
+        }
+    }
+
+    if let Some(sizing) = &breakpoint.sizing {
+        attributes.push(("width", sizing.width.to_string()));
+        attributes.push(("height", sizing.height.to_string()));
+    }
+
+    if let Some(padding) = &breakpoint.padding {
+        attributes.push(("padding", padding.to_string()));
+    }
+
+    if let Some(alignment) = &breakpoint.alignment {
+        attributes.push(("align", alignment.to_string()));
+    }
+
+    if let Some(direction) = breakpoint.direction {
+        attributes.push(("direction", direction_name(direction).to_string()));
+    }
+
+    if let Some(spacing) = breakpoint.spacing {
+        attributes.push(("spacing", spacing.to_string()));
+    }
+
+    output.push_str("<breakpoint");
+
+    for (attribute, value) in &attributes {
+        write!(output, " {}={}", attribute, attribute_value(value)).unwrap();
+    }
+
+    output.push_str("/>");
+}
+
+fn direction_name(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Horizontal => "horizontal",
+        Direction::Vertical => "vertical",
+    }
+}
+
+/// The attributes of text and inline elements that aren't at their defaults
+fn text_attributes(text: &Text) -> Vec<(String, String)> {
+    let mut attributes = Vec::new();
+    let mut push = |name: &str, value: String| attributes.push((name.to_string(), value));
+
+    match text.white_space {
+        WhiteSpace::Normal => {}
+        WhiteSpace::Pre => push("white-space", "pre".into()),
+        WhiteSpace::PreWrap => push("white-space", "pre-wrap".into()),
+        WhiteSpace::NoWrap => push("white-space", "nowrap".into()),
+    }
+
+    if text.tab_size != 8. {
+        push("tab-size", text.tab_size.to_string());
+    }
+
+    match text.direction {
+        TextDirection::Auto => {}
+        TextDirection::LeftToRight => push("direction", "ltr".into()),
+        TextDirection::RightToLeft => push("direction", "rtl".into()),
+    }
+
+    match text.align {
+        TextAlign::Start => {}
+        TextAlign::Center => push("text-align", "center".into()),
+        TextAlign::End => push("text-align", "end".into()),
+        TextAlign::Justify => push("text-align", "justify".into()),
+    }
+
+    if let Some(max_lines) = text.max_lines {
+        push("max-lines", max_lines.to_string());
+    }
+
+    if let Some(ellipsis) = &text.ellipsis {
+        push("ellipsis", ellipsis.clone());
+    }
+
+    // The size has no default, so it's always written
+    if let Some(style) = base_style(text) {
+        let defaults = TextStyle::new(style.font.clone(), Float::NAN);
+        attributes.extend(style_attributes(style, &defaults));
+    }
+
+    attributes
+}
+
+/// The style that the element is written with, which spans with other styles change.
+/// Objects can't be in spans, so it's their style if there are any, or else the style
+/// of the most content.
+fn base_style(text: &Text) -> Option<&TextStyle> {
+    let spans = text.spans();
+
+    let object = spans
+        .iter()
+        .find(|s| s.content().contains(OBJECT_REPLACEMENT));
+
+    let most_content = || {
+        spans.iter().rev().max_by_key(|span| {
+            spans
+                .iter()
+                .filter(|s| style_attributes(s.style(), span.style()).is_empty())
+                .map(|s| s.content().len())
+                .sum::<usize>()
+        })
+    };
+
+    object.or_else(most_content).map(|s| s.style())
+}
+
+/// The attributes of the style that differ from the base style
+fn style_attributes(style: &TextStyle, base: &TextStyle) -> Vec<(String, String)> {
+    let mut attributes = Vec::new();
+    let mut push = |name: &str, value: String| attributes.push((name.to_string(), value));
+
+    if style.size != base.size {
+        push("font-size", style.size.to_string());
+    }
+
+    if style.color != base.color {
+        let (red, green, blue, alpha) = style.color;
+        push("color", format!("{},{},{},{}", red, green, blue, alpha));
+    }
+
+    if style.line_height != base.line_height {
+        push("line-height", style.line_height.to_string());
+    }
+
+    if style.underline != base.underline {
+        push("underline", style.underline.to_string());
+    }
+
+    if style.strikethrough != base.strikethrough {
+        push("strikethrough", style.strikethrough.to_string());
+    }
+
+    attributes
+}
+
+/// Quotes values that would otherwise end early or be empty
+fn attribute_value(value: &str) -> String {
+    let bare = !value.is_empty()
+        && !value
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '>' | '/' | '"' | '='));
+
+    match bare {
+        true => escape(value, true),
+        false => format!("\"{}\"", escape(value, true)),
+    }
+}
+
+fn escape(text: &str, attribute: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' if attribute => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{mock::layout::FONT, *};
+
+    fn parser() -> MarkupParser {
+        MarkupParser::new().text_style(FONT.with(|f| TextStyle::new(f.clone(), 16.)))
+    }
+
+    /// Writes the element, checking that parsing it back results in the same markup
+    fn round_trip(parser: &MarkupParser, element: &Element) -> String {
+        let written = write_markup(element);
+        let parsed = parser.parse(&written).unwrap();
+
+        assert_eq!(write_markup(&parsed), written);
+        written
+    }
+
+    #[test]
+    fn writes_sizing_units() {
+        let units = [
+            "Percent:0.5,_,300",
+            "Percent:1,10%,_",
+            "Fixed:35",
+            "Collapse",
+            "Collapse:0.25%",
+            "Stretch",
+            "Stretch:120.5",
+            "1.5fr",
+            "calc(100% - 2 * (1.5rem + 4px) - 16px)",
+            "Stretch:calc(50% + 10px)",
+            "calc(-4px - -2px / 2)",
+        ];
+
+        for unit in units.iter() {
+            assert_eq!(parse_sizing_unit(unit).unwrap().to_string(), *unit);
+        }
+
+        assert_eq!(ConstraintUnit::Percent(0.007).to_string(), "0.7%");
+        assert_eq!(Padding::new(4., 4., 4., 4.).to_string(), "4");
+        assert_eq!(Padding::new(1., 2., 3., 4.5).to_string(), "1,2,3,4.5");
+    }
+
+    #[test]
+    fn round_trips_constraint_percentages() {
+        let thousandths = (0..=1000).map(|i| i as Float / 1000.);
+
+        // Fractions with every kind of mantissa, from tiny to many times the value
+        let spread = (0..2000u32).map(|i| Float::from_bits(0x3000_0000 + i * 0x7_3A1F));
+
+        for fraction in thousandths.chain(spread) {
+            let unit = ConstraintUnit::Percent(fraction);
+            let written = unit.to_string();
+
+            assert_eq!(
+                parse_constraint_unit(&written).unwrap(),
+                unit,
+                "{}",
+                written
+            );
+        }
+    }
+
+    #[test]
+    fn round_trips_markup() {
+        let markup = r#"<directional direction=vertical spacing=8 parallel=true width=Stretch height=Collapse padding=1,2,3,4 label="chat box">
+  <relational relationships="avatar.left = parent.left + 8; avatar.width = 35 !strong" width=Stretch height=Fixed:35>
+    <none width=Fixed:35 height=Fixed:35 label=avatar/>
+  </relational>
+  <text white-space=pre tab-size=4 text-align=justify max-lines=2 ellipsis=… font-size=16 width=Stretch height=Collapse align=middle,end>  a &lt;b&gt; &amp; "c"
+	d</text>
+  <inline direction=rtl font-size=16 line-height=1.5 width=Percent:0.5,_,300 height=Collapse>see <span color=0.45,0.55,1,1 link=krabbe>@krabbe</span> and <text font-size=13 underline=true width=Collapse height=Collapse>code</text> <span font-size=12 strikethrough=true>here</span></inline>
+</directional>"#;
+
+        let parser = parser();
+        let element = parser.parse(markup).unwrap();
+
+        assert_eq!(round_trip(&parser, &element), markup);
+    }
+
+    #[test]
+    fn round_trips_the_mock_chat() {
+        let element = mock::layout::chat_box();
+        let written = round_trip(&parser(), &element);
+
+        assert!(written.starts_with("<directional"));
+        assert_eq!(
+            written.matches("<text").count(),
+            written.matches("</text>").count()
+        );
+    }
+
+    #[test]
+    fn round_trips_breakpoints() {
+        let markup = r#"<directional direction=horizontal spacing=8 width=Stretch height=Stretch>
+  <breakpoint target=container min-width=200 max-width=600.5 max-height=400 direction=vertical spacing=0/>
+  <breakpoint target=viewport min-height=100 width=Fixed:67 height=Stretch padding=4 align=middle,end/>
+  <text font-size=16 width=Stretch height=Collapse><breakpoint target=container max-width=300 width=Stretch height=Fixed:20/>pizza</text>
+  <none width=Fixed:10 height=Fixed:10>
+    <breakpoint target=container width=Fixed:5 height=Fixed:10/>
+  </none>
+</directional>"#;
+
+        let parser = parser();
+        let element = parser.parse(markup).unwrap();
+
+        assert_eq!(element.breakpoints.len(), 2);
+        assert_eq!(round_trip(&parser, &element), markup);
+
+        // The sidebar only shows the avatars in narrow windows
+        let sidebar = mock::layout::user_sidebar();
+        let written = round_trip(&parser, &sidebar);
+        let parsed = parser.parse(&written).unwrap();
+
+        assert!(written.contains(
+            "<breakpoint target=viewport max-width=1000 width=Fixed:67 height=Stretch/>"
+        ));
+
+        for (window, width) in &[(800., 67.), (1200., 240.)] {
+            let rect = Rect::new(*window, 600., 0., 0.);
+            let calculated = parsed.calculate(rect.clone(), rect.clone());
+
+            assert_eq!(calculated.rect.dimensions.width, *width);
+            assert_eq!(
+                calculated.rect.as_tuple(),
+                sidebar.calculate(rect.clone(), rect).rect.as_tuple()
+            );
+        }
+
+        let error = parser
+            .parse("<none><breakpoint target=window/></none>")
+            .unwrap_err();
+        assert_eq!(
+            error.kind,
+            MarkupErrorKind::InvalidValue("target".into(), "window".into())
+        );
+    }
+
+    #[test]
+    fn round_trips_text_styles() {
+        let element = mock::layout::message();
+        let parser = parser();
+        let written = round_trip(&parser, &element);
+
+        fn texts(element: &Element) -> Vec<&Text> {
+            let mut texts = Vec::new();
+            let mut elements = vec![element];
+
+            while let Some(element) = elements.pop() {
+                match &element.kind {
+                    ElementKind::Text(t) => texts.push(t),
+                    ElementKind::Inline(i) => texts.push(i.text()),
+                    _ => {}
+                }
+
+                elements.extend(element.children.iter());
+            }
+
+            texts
+        }
+
+        // Spans next to each other with the same style can be parsed as one
+        let spans = |text: &Text| -> Vec<_> {
+            text.spans()
+                .iter()
+                .flat_map(|s| {
+                    let (size, color) = (s.style().size, s.style().color);
+                    let link = s.target().cloned();
+
+                    s.content()
+                        .chars()
+                        .map(move |c| (c, size, color, link.clone()))
+                })
+                .collect()
+        };
+
+        let original = texts(&element);
+        let parsed = parser.parse(&written).unwrap();
+        let parsed = texts(&parsed);
+
+        assert_eq!(original.len(), parsed.len());
+
+        for (original, parsed) in original.iter().zip(&parsed) {
+            assert_eq!(spans(original), spans(parsed));
+        }
+
+        // Mentions are links in the color of mentions
+        assert!(written.contains("<span color=0.45,0.55,1,1 link="));
+    }
+
+    #[derive(Debug)]
+    struct Spacer {
+        gap: Float,
+    }
+
+    impl ElementLayout for Spacer {
+        fn kind_name(&self) -> String {
+            "spacer".into()
+        }
+
+        fn calculate(
+            &self,
+            _element: &Element,
+            available_bounds: Rect,
+            _outer_bounds: Rect,
+            _context: &LayoutContext,
+        ) -> CalculatedElement {
+            CalculatedElement::empty(Dimensions::new(
+                self.gap,
+                available_bounds.dimensions.height,
+            ))
+        }
+
+        fn markup_attributes(&self) -> Vec<(String, String)> {
+            vec![("gap".into(), self.gap.to_string())]
+        }
+    }
+
+    #[test]
+    fn round_trips_extern_kinds() {
+        let parser = parser().extern_kind("spacer", |attributes| match attributes {
+            [(name, gap)] if name == "gap" => match gap.parse() {
+                Ok(gap) => Ok(Box::new(Spacer { gap }) as Box<dyn ElementLayout>),
+                Err(_) => Err(format!("'{}' isn't a gap", gap)),
+            },
+            _ => Err("expected a gap".into()),
+        });
+
+        let element = Element {
+            kind: ElementKind::Extern(Box::new(Spacer { gap: 12.5 })),
+            sizing: Sizing::new(
+                SizingUnit::Fixed(12.5),
+                SizingUnit::Stretch(ConstraintUnit::None),
+            ),
+            padding: Padding::empty(),
+            alignment: Alignment::new(AlignUnit::Start, AlignUnit::Start),
+            children: Vec::new(),
+            label: None,
+            base_style: None,
+            breakpoints: Vec::new(),
+        };
+
+        assert_eq!(
+            round_trip(&parser, &element),
+            "<spacer gap=12.5 width=Fixed:12.5 height=Stretch/>"
+        );
+
+        let error = parser.parse("<spacer gap=wide/>").unwrap_err();
+        assert_eq!(
+            error.kind,
+            MarkupErrorKind::InvalidExtern("spacer".into(), "'wide' isn't a gap".into())
+        );
+
+        // Without a constructor the kind isn't known
+        assert!(parse_markup("<spacer gap=1/>").is_err());
+    }
+}
@@ -0,0 +1,103 @@
+use std::ops::Range;
+
+use unicode_bidi::{BidiInfo, Level};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// The base direction of the paragraphs in a text
+pub enum TextDirection {
+    /// Detected from the first strong character of every paragraph, left to right if there is none
+    Auto,
+    LeftToRight,
+    RightToLeft,
+}
+
+impl TextDirection {
+    fn level(&self) -> Option<Level> {
+        match self {
+            TextDirection::Auto => None,
+            TextDirection::LeftToRight => Some(Level::ltr()),
+            TextDirection::RightToLeft => Some(Level::rtl()),
+        }
+    }
+}
+
+/// The resolved embedding levels of a text, used to reorder its lines once they are wrapped
+pub struct BidiText<'a> {
+    info: BidiInfo<'a>,
+}
+
+impl<'a> BidiText<'a> {
+    pub fn new(content: &'a str, direction: TextDirection) -> BidiText<'a> {
+        BidiText {
+            info: BidiInfo::new(content, direction.level()),
+        }
+    }
+
+    /// Whether the paragraph the byte index is in goes from right to left
+    pub fn is_rtl_paragraph(&self, index: usize) -> bool {
+        self.info
+            .paragraphs
+            .iter()
+            .find(|p| p.range.contains(&index))
+            .or_else(|| self.info.paragraphs.last())
+            .is_some_and(|p| p.level.is_rtl())
+    }
+
+    /// Splits a line, given as a byte range, into runs of the same direction in visual order,
+    /// with trailing whitespace moved to the end of the line in the paragraph's direction
+    pub fn visual_runs(&self, line: Range<usize>) -> Vec<(Range<usize>, bool)> {
+        if line.is_empty() {
+            return Vec::new();
+        }
+
+        // Text without any right to left characters stays in logical order
+        if !self.info.has_rtl() {
+            return vec![(line, false)];
+        }
+
+        let paragraph = self
+            .info
+            .paragraphs
+            .iter()
+            .find(|p| p.range.contains(&line.start));
+
+        // A line outside of the text has no levels, so it stays in logical order
+        let paragraph = match paragraph {
+            Some(paragraph) => paragraph,
+            None => return vec![(line, false)],
+        };
+
+        let (levels, runs) = self.info.visual_runs(paragraph, line);
+
+        runs.into_iter()
+            .map(|run| {
+                let rtl = levels[run.start].is_rtl();
+                (run, rtl)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BidiText, TextDirection};
+
+    #[test]
+    fn reorders_runs_of_lines() {
+        let content = "pizza שלום krabbe";
+        let bidi = BidiText::new(content, TextDirection::Auto);
+
+        assert!(!bidi.is_rtl_paragraph(0));
+        assert_eq!(
+            bidi.visual_runs(0..content.len()),
+            vec![(0..6, false), (6..14, true), (14..content.len(), false)]
+        );
+
+        // Lines outside of the text are left to right instead of panicking
+        let end = content.len();
+        assert_eq!(
+            bidi.visual_runs(end + 1..end + 3),
+            vec![(end + 1..end + 3, false)]
+        );
+    }
+}
@@ -0,0 +1,405 @@
+use std::{
+    collections::HashMap,
+    mem::size_of,
+    sync::{Arc, Mutex, MutexGuard, OnceLock},
+};
+
+use crate::{Float, Rect};
+
+use super::{
+    layout_text, min_content_width, FontCollection, InlineObject, LaidOutGlyph, Text, TextAlign,
+    TextDirection, TextLayout, TextLine, TextRun, WhiteSpace,
+};
+
+/// The memory the shared cache may use before evicting texts
+const DEFAULT_MAX_BYTES: usize = 16 * 1024 * 1024;
+
+/// Layouts kept per text, since a text is usually only measured at a few widths
+const MAX_WIDTHS: usize = 8;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+
+    /// Texts removed to stay within the memory bound
+    pub evictions: usize,
+    pub entries: usize,
+
+    /// Estimated memory used by the cached texts and layouts
+    pub bytes: usize,
+}
+
+impl CacheStats {
+    pub fn hit_rate(&self) -> Float {
+        match self.hits + self.misses {
+            0 => 0.,
+            total => self.hits as Float / total as Float,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct SpanKey {
+    content: String,
+
+    /// Fonts are compared by identity, they're kept alive by the entry
+    fonts: Vec<usize>,
+    size: u32,
+    line_height: u32,
+    color: [u32; 4],
+    underline: bool,
+    strikethrough: bool,
+    link: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+/// Everything about a text that its layout depends on, except for the width
+struct TextKey {
+    spans: Vec<SpanKey>,
+    white_space: WhiteSpace,
+    tab_size: u32,
+    direction: TextDirection,
+    align: TextAlign,
+    max_lines: Option<usize>,
+    ellipsis: Option<String>,
+
+    /// The width, height and baseline of every inline object
+    objects: Vec<[u32; 3]>,
+}
+
+impl TextKey {
+    fn new(text: &Text, objects: &[InlineObject]) -> TextKey {
+        let spans = text
+            .spans
+            .iter()
+            .map(|span| {
+                let style = &span.style;
+                let (r, g, b, a) = style.color;
+
+                SpanKey {
+                    content: span.content.clone(),
+                    fonts: style
+                        .font
+                        .fonts()
+                        .iter()
+                        .map(|f| Arc::as_ptr(f) as usize)
+                        .collect(),
+                    size: style.size.to_bits(),
+                    line_height: style.line_height.to_bits(),
+                    color: [r.to_bits(), g.to_bits(), b.to_bits(), a.to_bits()],
+                    underline: style.underline,
+                    strikethrough: style.strikethrough,
+                    link: span.link.clone(),
+                }
+            })
+            .collect();
+
+        TextKey {
+            spans,
+            white_space: text.white_space,
+            tab_size: text.tab_size.to_bits(),
+            direction: text.direction,
+            align: text.align,
+            max_lines: text.max_lines,
+            ellipsis: text.ellipsis.clone(),
+            objects: objects
+                .iter()
+                .map(|o| {
+                    [
+                        o.dimensions.width.to_bits(),
+                        o.dimensions.height.to_bits(),
+                        o.baseline.to_bits(),
+                    ]
+                })
+                .collect(),
+        }
+    }
+
+    fn bytes(&self) -> usize {
+        let strings = |s: &Option<String>| s.as_ref().map_or(0, String::len);
+
+        size_of::<TextKey>()
+            + strings(&self.ellipsis)
+            + self.objects.len() * size_of::<[u32; 3]>()
+            + self
+                .spans
+                .iter()
+                .map(|s| {
+                    size_of::<SpanKey>()
+                        + s.content.len()
+                        + s.fonts.len() * size_of::<usize>()
+                        + strings(&s.link)
+                })
+                .sum::<usize>()
+    }
+}
+
+fn layout_bytes(layout: &TextLayout) -> usize {
+    size_of::<TextLayout>()
+        + layout.glyphs.len() * size_of::<LaidOutGlyph>()
+        + layout.lines.len() * size_of::<TextLine>()
+        + layout.objects.len() * size_of::<Option<Rect>>()
+        + layout
+            .runs
+            .iter()
+            .map(|r| size_of::<TextRun>() + r.link.as_ref().map_or(0, String::len))
+            .sum::<usize>()
+}
+
+struct CacheEntry {
+    /// Keeps the fonts of the key alive, so that their addresses are never reused
+    _fonts: Vec<FontCollection>,
+
+    /// Layouts by the bits of the width they were wrapped at, oldest first
+    layouts: Vec<(u32, TextLayout)>,
+    min_content_width: Option<Float>,
+    bytes: usize,
+    last_used: u64,
+}
+
+impl CacheEntry {
+    /// Adds the layout at the width, replacing the one another thread may have added
+    /// since the lookup missed, or else the oldest one if there's no room left
+    fn push_layout(&mut self, width: u32, layout: TextLayout) {
+        let existing = self.layouts.iter().position(|(w, _)| *w == width);
+
+        let replaced = match existing {
+            Some(index) => Some(self.layouts.remove(index)),
+            None if self.layouts.len() == MAX_WIDTHS => Some(self.layouts.remove(0)),
+            None => None,
+        };
+
+        if let Some((_, replaced)) = replaced {
+            self.bytes -= layout_bytes(&replaced);
+        }
+
+        self.layouts.push((width, layout));
+    }
+}
+
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<TextKey, CacheEntry>,
+    tick: u64,
+    stats: CacheStats,
+}
+
+/// Caches shaped and wrapped texts by their content, fonts, styles and width,
+/// evicting the least recently used texts when it grows past its memory bound.
+/// Layouts are computed outside of the lock, so it can be shared between threads.
+pub struct TextCache {
+    max_bytes: usize,
+    state: Mutex<CacheState>,
+}
+
+impl TextCache {
+    pub fn new(max_bytes: usize) -> TextCache {
+        TextCache {
+            max_bytes,
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    /// The cache that text elements consult
+    pub fn shared() -> &'static TextCache {
+        static SHARED: OnceLock<TextCache> = OnceLock::new();
+        SHARED.get_or_init(|| TextCache::new(DEFAULT_MAX_BYTES))
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.state().stats
+    }
+
+    pub fn clear(&self) {
+        let mut state = self.state();
+
+        state.entries.clear();
+        state.stats.entries = 0;
+        state.stats.bytes = 0;
+    }
+
+    pub fn layout(&self, text: &Text, objects: &[InlineObject], max_width: Float) -> TextLayout {
+        let key = TextKey::new(text, objects);
+        let width = max_width.to_bits();
+
+        let cached = self.lookup(&key, |entry| {
+            entry
+                .layouts
+                .iter()
+                .find(|(w, _)| *w == width)
+                .map(|(_, layout)| layout.clone())
+        });
+
+        if let Some(layout) = cached {
+            return layout;
+        }
+
+        let layout = layout_text(text, objects, max_width);
+        let bytes = layout_bytes(&layout);
+
+        self.insert(key, text, bytes, |entry| {
+            entry.push_layout(width, layout.clone())
+        });
+
+        layout
+    }
+
+    pub fn min_content_width(&self, text: &Text, objects: &[InlineObject]) -> Float {
+        let key = TextKey::new(text, objects);
+
+        if let Some(width) = self.lookup(&key, |entry| entry.min_content_width) {
+            return width;
+        }
+
+        let width = min_content_width(text, objects);
+        self.insert(key, text, 0, |entry| entry.min_content_width = Some(width));
+
+        width
+    }
+
+    fn state(&self) -> MutexGuard<'_, CacheState> {
+        // The state is always consistent between statements, so a panic elsewhere can't corrupt it
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn lookup<T>(&self, key: &TextKey, f: impl FnOnce(&CacheEntry) -> Option<T>) -> Option<T> {
+        let mut state = self.state();
+        state.tick += 1;
+
+        let tick = state.tick;
+        let result = state.entries.get_mut(key).and_then(|entry| {
+            entry.last_used = tick;
+            f(entry)
+        });
+
+        match result {
+            Some(_) => state.stats.hits += 1,
+            None => state.stats.misses += 1,
+        }
+
+        result
+    }
+
+    /// Adds to the entry of the key, which takes `bytes` more memory afterwards,
+    /// then evicts texts until the cache is within its bound again
+    fn insert(&self, key: TextKey, text: &Text, bytes: usize, f: impl FnOnce(&mut CacheEntry)) {
+        let mut state = self.state();
+        let tick = state.tick;
+
+        let entry = state
+            .entries
+            .entry(key)
+            .or_insert_with_key(|key| CacheEntry {
+                _fonts: text.spans.iter().map(|s| s.style.font.clone()).collect(),
+                layouts: Vec::new(),
+                min_content_width: None,
+                bytes: key.bytes(),
+                last_used: tick,
+            });
+
+        entry.bytes += bytes;
+        f(entry);
+
+        state.stats.bytes = state.entries.values().map(|e| e.bytes).sum();
+        state.stats.entries = state.entries.len();
+
+        while state.stats.bytes > self.max_bytes {
+            let oldest = state
+                .entries
+                .iter()
+                .min_by_key(|(_, e)| e.last_used)
+                .map(|(k, _)| k.clone());
+
+            match oldest.and_then(|k| state.entries.remove(&k)) {
+                Some(evicted) => {
+                    state.stats.bytes -= evicted.bytes;
+                    state.stats.entries -= 1;
+                    state.stats.evictions += 1;
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{mock::layout::FONT, text::*};
+
+    use super::{layout_bytes, TextKey};
+
+    fn text(content: &str, size: f32) -> Text {
+        Text::new(content, FONT.with(|f| TextStyle::new(f.clone(), size)))
+    }
+
+    #[test]
+    fn reuses_layouts() {
+        let cache = TextCache::new(1024 * 1024);
+        let pizza = text("pizza krabbe", 16.);
+
+        let first = cache.layout(&pizza, &[], 100.);
+        let second = cache.layout(&pizza, &[], 100.);
+
+        assert_eq!(first.glyphs.len(), second.glyphs.len());
+        assert_eq!(first.dimensions.as_tuple(), second.dimensions.as_tuple());
+        assert_eq!((cache.stats().hits, cache.stats().misses), (1, 1));
+
+        // A different width or style is laid out again
+        cache.layout(&pizza, &[], 50.);
+        cache.layout(&text("pizza krabbe", 18.), &[], 100.);
+        cache.min_content_width(&pizza, &[]);
+        cache.min_content_width(&pizza, &[]);
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (2, 4));
+        assert_eq!(stats.entries, 2);
+        assert_eq!(stats.hit_rate(), 1. / 3.);
+
+        cache.clear();
+        assert_eq!(cache.stats().bytes, 0);
+    }
+
+    #[test]
+    fn evicts_least_recently_used_texts() {
+        let measure = TextCache::new(1024 * 1024);
+        measure.layout(&text("pizza", 16.), &[], 100.);
+
+        let entry = measure.stats().bytes;
+        let cache = TextCache::new(entry * 2);
+
+        cache.layout(&text("pizza", 16.), &[], 100.);
+        cache.layout(&text("steak", 16.), &[], 100.);
+        cache.layout(&text("pizza", 16.), &[], 100.);
+        cache.layout(&text("wurst", 16.), &[], 100.);
+
+        let stats = cache.stats();
+        assert_eq!(stats.evictions, 1);
+        assert_eq!(stats.entries, 2);
+        assert!(stats.bytes <= entry * 2);
+
+        // The text used least recently was evicted, the other one is still cached
+        cache.layout(&text("pizza", 16.), &[], 100.);
+        assert_eq!(cache.stats().hits, 2);
+        cache.layout(&text("steak", 16.), &[], 100.);
+        assert_eq!(cache.stats().misses, 4);
+    }
+
+    #[test]
+    fn replaces_layouts_of_racing_misses() {
+        let cache = TextCache::new(1024 * 1024);
+        let pizza = text("pizza", 16.);
+
+        let layout = cache.layout(&pizza, &[], 100.);
+        let bytes = cache.stats().bytes;
+
+        // Another thread missed the same width before the layout was inserted
+        let key = TextKey::new(&pizza, &[]);
+        cache.insert(key.clone(), &pizza, layout_bytes(&layout), |entry| {
+            entry.push_layout(100f32.to_bits(), layout.clone())
+        });
+
+        assert_eq!(cache.stats().bytes, bytes);
+        assert_eq!(cache.state().entries[&key].layouts.len(), 1);
+    }
+}
@@ -0,0 +1,225 @@
+use std::ops::Range;
+
+use crate::{Float, Position, Rect};
+
+use super::{LaidOutGlyph, TextLayout, TextLine};
+
+impl LaidOutGlyph {
+    /// Whether the glyph can be pointed at, which excludes ellipses and zero width characters
+    /// such as newlines and combining marks, whose grapheme is covered by another glyph
+    fn is_selectable(&self) -> bool {
+        !self.cluster.is_empty() && self.advance > 0.
+    }
+
+    /// The x position of the boundary before the glyph's grapheme
+    fn leading_edge(&self) -> Float {
+        let x = self.rect.position.x;
+
+        if self.rtl {
+            x + self.advance
+        } else {
+            x
+        }
+    }
+
+    /// The x position of the boundary after the glyph's grapheme
+    fn trailing_edge(&self) -> Float {
+        let x = self.rect.position.x;
+
+        if self.rtl {
+            x
+        } else {
+            x + self.advance
+        }
+    }
+}
+
+impl TextLayout {
+    fn line_glyphs(&self, line: &TextLine) -> &[LaidOutGlyph] {
+        &self.glyphs[line.glyphs.clone()]
+    }
+
+    /// Returns the grapheme boundary closest to a point relative to the origin of the text,
+    /// as a byte index. Points outside the text resolve to the closest line.
+    pub fn hit_test(&self, point: Position) -> usize {
+        let line = match self
+            .lines
+            .iter()
+            .find(|l| point.y < l.rect.position.y + l.rect.dimensions.height)
+            .or_else(|| self.lines.last())
+        {
+            Some(line) => line,
+            None => return 0,
+        };
+
+        let glyphs = self.line_glyphs(line);
+        let selectable = || glyphs.iter().filter(|g| g.is_selectable());
+
+        // The glyph under the point, or the closest one at either end of the line
+        let glyph = selectable()
+            .find(|g| point.x < g.rect.position.x + g.advance)
+            .or_else(|| selectable().next_back());
+
+        match glyph {
+            Some(glyph) => {
+                let before_middle = point.x < glyph.rect.position.x + glyph.advance / 2.;
+
+                // The left half is before the grapheme, unless the glyph goes from right to left
+                if before_middle != glyph.rtl {
+                    glyph.cluster.start
+                } else {
+                    glyph.cluster.end
+                }
+            }
+            // Lines without visible glyphs, such as empty lines, only have a single boundary
+            None => glyphs
+                .iter()
+                .map(|g| g.cluster.start)
+                .min()
+                .unwrap_or_else(|| self.text_end()),
+        }
+    }
+
+    /// Returns a rect with no width at the grapheme boundary with the given byte index,
+    /// over the full height of its line. Returns None if the index is not visible,
+    /// such as when it is cut off by an ellipsis.
+    pub fn caret_rect(&self, index: usize) -> Option<Rect> {
+        let caret = |x: Float, line: &TextLine| {
+            Rect::new(0., line.rect.dimensions.height, x, line.rect.position.y)
+        };
+
+        // A boundary between two lines is at the start of the second line
+        for line in &self.lines {
+            let glyphs = self.line_glyphs(line);
+
+            if let Some(glyph) = glyphs
+                .iter()
+                .find(|g| g.cluster.start == index && !g.cluster.is_empty())
+            {
+                return Some(caret(glyph.leading_edge(), line));
+            }
+        }
+
+        for line in &self.lines {
+            let glyphs = self.line_glyphs(line);
+
+            if let Some(glyph) = glyphs.iter().find(|g| g.cluster.end == index) {
+                return Some(caret(glyph.trailing_edge(), line));
+            }
+        }
+
+        // An empty text still has a caret at its start
+        match self.lines.first() {
+            Some(line) if index == 0 && self.glyphs.is_empty() => Some(caret(0., line)),
+            _ => None,
+        }
+    }
+
+    /// Returns the rects covering the graphemes in the byte range, one for every part
+    /// of a line that is visually contiguous, so mixed direction lines can have several
+    pub fn selection_rects(&self, range: Range<usize>) -> Vec<Rect> {
+        let mut rects: Vec<Rect> = Vec::new();
+
+        for line in &self.lines {
+            let mut previous_selected = false;
+
+            for glyph in self.line_glyphs(line) {
+                let selected = !glyph.cluster.is_empty()
+                    && glyph.cluster.start >= range.start
+                    && glyph.cluster.end <= range.end;
+
+                if selected {
+                    match rects.last_mut() {
+                        Some(rect) if previous_selected => {
+                            rect.dimensions.width =
+                                glyph.rect.position.x + glyph.advance - rect.position.x;
+                        }
+                        _ => rects.push(glyph.rect.clone()),
+                    }
+                }
+
+                previous_selected = selected;
+            }
+        }
+
+        rects
+    }
+
+    fn text_end(&self) -> usize {
+        self.glyphs.iter().map(|g| g.cluster.end).max().unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{mock::layout::FONT, text::*, Float, Position};
+
+    fn layout(content: &str, max_width: Float) -> TextLayout {
+        let style = FONT.with(|f| TextStyle::new(f.clone(), 16.));
+        Text::new(content, style).layout(max_width)
+    }
+
+    fn width_of(content: &str) -> Float {
+        layout(content, Float::INFINITY).dimensions.width
+    }
+
+    #[test]
+    fn hit_tests_grapheme_boundaries() {
+        let layout = layout("pizza krabbe", width_of("krabbe"));
+        let second_line = layout.lines[1].rect.position.y + 1.;
+
+        assert_eq!(layout.hit_test(Position::new(0., 0.)), 0);
+        assert_eq!(layout.hit_test(Position::new(width_of("pi") - 1., 0.)), 2);
+        assert_eq!(layout.hit_test(Position::new(-10., second_line)), 6);
+        assert_eq!(layout.hit_test(Position::new(1000., second_line)), 12);
+        assert_eq!(layout.hit_test(Position::new(1000., 1000.)), 12);
+
+        // Combining marks are part of the grapheme before them
+        let layout = self::layout("a\u{308}b", Float::INFINITY);
+        assert_eq!(layout.hit_test(Position::new(width_of("a") - 1., 0.)), 3);
+    }
+
+    #[test]
+    fn places_carets_at_boundaries() {
+        let layout = layout("pizza krabbe", width_of("krabbe"));
+
+        let start = layout.caret_rect(0).unwrap();
+        let middle = layout.caret_rect(2).unwrap();
+        let wrapped = layout.caret_rect(6).unwrap();
+        let end = layout.caret_rect(12).unwrap();
+
+        assert_eq!(start.position.x, 0.);
+        assert_eq!(middle.position.x, width_of("pi"));
+        assert_eq!(wrapped.position.x, 0.);
+        assert_eq!(wrapped.position.y, layout.lines[1].rect.position.y);
+        assert_eq!(end.position.x, width_of("krabbe"));
+        assert_eq!(
+            start.dimensions.height,
+            layout.lines[0].rect.dimensions.height
+        );
+
+        // Right to left graphemes start at their right edge
+        let layout = self::layout("\u{5d0}\u{5d1}", Float::INFINITY);
+        assert_eq!(
+            layout.caret_rect(0).unwrap().position.x,
+            layout.dimensions.width
+        );
+    }
+
+    #[test]
+    fn selects_across_lines_and_directions() {
+        let layout = layout("pizza krabbe", width_of("krabbe"));
+        let rects = layout.selection_rects(3..9);
+
+        assert_eq!(rects.len(), 2);
+        assert_eq!(rects[0].position.x, width_of("piz"));
+        assert_eq!(rects[1].position.x, 0.);
+        assert_eq!(rects[1].dimensions.width, width_of("kra"));
+
+        // A selection across a direction change is split into visually contiguous parts
+        let layout = self::layout("ab \u{5d0}\u{5d1}\u{5d2}", Float::INFINITY);
+        let rects = layout.selection_rects(1..5);
+
+        assert_eq!(rects.len(), 2);
+    }
+}
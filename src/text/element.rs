@@ -0,0 +1,214 @@
+use crate::{CalculatedElement, Dimensions, Element, Float, LayoutContext, Rect};
+
+use super::{InlineObject, TextCache, TextDirection, TextLayout, TextSpan, TextStyle, WhiteSpace};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// Horizontal alignment of lines within the text, which is separate from
+/// the alignment of the element within its parent
+pub enum TextAlign {
+    /// Left for left to right paragraphs, right for right to left paragraphs
+    Start,
+    Center,
+    End,
+
+    /// Every line except the last of a paragraph is stretched to the full width,
+    /// by distributing the extra space across its break opportunities
+    Justify,
+}
+
+#[derive(Debug)]
+/// Represents a text laid out in lines, sized by its content
+pub struct Text {
+    /// The content of all spans joined together
+    pub(crate) content: String,
+    pub(crate) spans: Vec<TextSpan>,
+
+    /// How whitespace is collapsed and lines are wrapped (by default collapsed and wrapped)
+    pub(crate) white_space: WhiteSpace,
+
+    /// The distance between tab stops in spaces (by default 8)
+    pub(crate) tab_size: Float,
+
+    /// The base direction of paragraphs (by default detected from their content)
+    pub(crate) direction: TextDirection,
+
+    /// How lines are aligned (by default at the start)
+    pub(crate) align: TextAlign,
+
+    /// Lines after this amount are cut off, so none are shown for 0 (by default unlimited)
+    pub(crate) max_lines: Option<usize>,
+
+    /// Appended to the last line when it's cut off or doesn't fit (by default none)
+    pub(crate) ellipsis: Option<String>,
+}
+
+impl Text {
+    pub fn new(content: impl ToString, style: TextStyle) -> Text {
+        Text::rich(vec![TextSpan::new(content, style)])
+    }
+
+    /// Creates a text out of spans with their own styles, which are wrapped as one
+    pub fn rich(spans: Vec<TextSpan>) -> Text {
+        Text {
+            content: spans.iter().map(|s| s.content.as_str()).collect(),
+            spans,
+            white_space: WhiteSpace::Normal,
+            tab_size: 8.,
+            direction: TextDirection::Auto,
+            align: TextAlign::Start,
+            max_lines: None,
+            ellipsis: None,
+        }
+    }
+
+    pub fn white_space(mut self, white_space: WhiteSpace) -> Self {
+        self.white_space = white_space;
+        self
+    }
+
+    pub fn tab_size(mut self, tab_size: Float) -> Self {
+        self.tab_size = tab_size;
+        self
+    }
+
+    pub fn direction(mut self, direction: TextDirection) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    pub fn align(mut self, align: TextAlign) -> Self {
+        self.align = align;
+        self
+    }
+
+    pub fn max_lines(mut self, max_lines: usize) -> Self {
+        self.max_lines = Some(max_lines);
+        self
+    }
+
+    pub fn ellipsis(mut self, ellipsis: impl ToString) -> Self {
+        self.ellipsis = Some(ellipsis.to_string());
+        self
+    }
+
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    pub fn spans(&self) -> &Vec<TextSpan> {
+        &self.spans
+    }
+
+    /// Lays out the text, reusing the layout of an identical text at the same width
+    pub fn layout(&self, max_width: Float) -> TextLayout {
+        self.layout_with(&[], max_width)
+    }
+
+    pub fn min_content(&self, element: &Element, context: &LayoutContext) -> Dimensions {
+        self.min_content_with(element, &[], context)
+    }
+
+    pub fn max_content(&self, element: &Element, context: &LayoutContext) -> Dimensions {
+        self.max_content_with(element, &[], context)
+    }
+
+    pub fn height_for_width(&self, width: Float) -> Float {
+        self.layout(width).dimensions.height
+    }
+
+    pub fn calculate(
+        &self,
+        element: &Element,
+        available_bounds: Rect,
+        outer_bounds: Rect,
+        context: &LayoutContext,
+    ) -> CalculatedElement {
+        let (rect, layout) = self.fit(element, &available_bounds, &outer_bounds, &[], context);
+
+        CalculatedElement {
+            rect,
+            children: Vec::new(),
+            text: Some(layout),
+        }
+    }
+
+    /// Lays out the text with the inline objects in place of its object replacement characters
+    pub(crate) fn layout_with(&self, objects: &[InlineObject], max_width: Float) -> TextLayout {
+        TextCache::shared().layout(self, objects, max_width)
+    }
+
+    pub(crate) fn min_content_with(
+        &self,
+        element: &Element,
+        objects: &[InlineObject],
+        context: &LayoutContext,
+    ) -> Dimensions {
+        let width = TextCache::shared().min_content_width(self, objects);
+        let height = self.layout_with(objects, width).dimensions.height;
+
+        element
+            .sizing_in(context)
+            .intrinsic(Dimensions::new(width, height), context)
+    }
+
+    pub(crate) fn max_content_with(
+        &self,
+        element: &Element,
+        objects: &[InlineObject],
+        context: &LayoutContext,
+    ) -> Dimensions {
+        element.sizing_in(context).intrinsic(
+            self.layout_with(objects, Float::INFINITY).dimensions,
+            context,
+        )
+    }
+
+    /// Lines wrap at the width the element takes on, which is all of the available width if it collapses
+    pub(crate) fn wrap_width(
+        element: &Element,
+        available_bounds: &Rect,
+        outer_bounds: &Rect,
+        context: &LayoutContext,
+    ) -> Float {
+        let available = available_bounds.dimensions.width;
+
+        element.sizing_in(context).width.calculate(
+            available,
+            available,
+            outer_bounds.dimensions.width,
+            context,
+        )
+    }
+
+    /// Sizes the element around its layout, returning its rect and the final layout
+    pub(crate) fn fit(
+        &self,
+        element: &Element,
+        available_bounds: &Rect,
+        outer_bounds: &Rect,
+        objects: &[InlineObject],
+        context: &LayoutContext,
+    ) -> (Rect, TextLayout) {
+        let wrap_width = Text::wrap_width(element, available_bounds, outer_bounds, context);
+
+        let mut layout = self.layout_with(objects, wrap_width);
+        let dimensions = element.sizing_in(context).calculate(
+            layout.dimensions,
+            available_bounds.dimensions,
+            outer_bounds.dimensions,
+            context,
+        );
+
+        // Lines are aligned within the width the element ends up with, if it's narrower
+        let aligned = self.align != TextAlign::Start || layout.lines.iter().any(|l| l.rtl);
+
+        if aligned && dimensions.width != wrap_width {
+            layout = self.layout_with(objects, dimensions.width);
+        }
+
+        (
+            Rect::from_dimensions_and_position(dimensions, available_bounds.position),
+            layout,
+        )
+    }
+}
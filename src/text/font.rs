@@ -0,0 +1,92 @@
+use std::sync::Arc;
+
+use rusttype::Font;
+
+pub type FontHandle = Arc<Font<'static>>;
+
+#[derive(Clone)]
+/// An ordered list of fonts, where characters missing from one font are taken from the next
+pub struct FontCollection {
+    fonts: Arc<Vec<FontHandle>>,
+}
+
+impl FontCollection {
+    pub fn new(primary: FontHandle) -> FontCollection {
+        FontCollection {
+            fonts: Arc::new(vec![primary]),
+        }
+    }
+
+    /// Adds a font that is used for characters missing from the fonts before it
+    pub fn fallback(mut self, font: FontHandle) -> Self {
+        Arc::make_mut(&mut self.fonts).push(font);
+        self
+    }
+
+    pub fn fonts(&self) -> &Vec<FontHandle> {
+        &self.fonts
+    }
+
+    pub fn primary(&self) -> &FontHandle {
+        &self.fonts[0]
+    }
+
+    pub fn get(&self, index: usize) -> &FontHandle {
+        &self.fonts[index]
+    }
+
+    /// Returns the index of the first font that has a glyph for every character in the grapheme,
+    /// so that a cluster is never split across fonts. Falls back to the primary font,
+    /// which then renders its .notdef glyph.
+    pub fn select(&self, grapheme: &str) -> usize {
+        self.fonts
+            .iter()
+            .position(|font| {
+                grapheme
+                    .chars()
+                    .filter(|c| !c.is_control())
+                    .all(|c| font.glyph(c).id().0 != 0)
+            })
+            .unwrap_or(0)
+    }
+}
+
+impl From<FontHandle> for FontCollection {
+    fn from(font: FontHandle) -> Self {
+        FontCollection::new(font)
+    }
+}
+
+impl std::fmt::Debug for FontCollection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FontCollection")
+            .field("fonts", &self.fonts.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        mock::layout::{FALLBACK_FONT, FONT},
+        text::*,
+    };
+
+    #[test]
+    fn selects_first_font_with_glyphs() {
+        let fonts = FONT.with(|f| {
+            FALLBACK_FONT.with(|fallback| FontCollection::new(f.clone()).fallback(fallback.clone()))
+        });
+
+        assert_eq!(fonts.select("a"), 0);
+        assert_eq!(fonts.select("a\u{308}"), 0);
+
+        // Script capitals are only in the fallback font
+        assert_eq!(fonts.select("\u{1D49C}"), 1);
+        assert_eq!(fonts.select("a\u{1D49C}"), 1);
+
+        // Without a font that has it, the primary font's .notdef glyph is used
+        assert_eq!(fonts.select("\u{1F355}"), 0);
+        assert_eq!(fonts.get(0).glyph('\u{1F355}').id().0, 0);
+    }
+}
@@ -0,0 +1,208 @@
+use crate::{CalculatedElement, Dimensions, Element, Float, LayoutContext, Rect};
+
+use super::{InlineObject, Text};
+
+/// Marks where an inline child flows within the text of an inline element
+pub const OBJECT_REPLACEMENT: char = '\u{fffc}';
+
+#[derive(Debug)]
+/// Lays out a text where the element's children flow together with the words,
+/// each taking the place of the next object replacement character, such as
+/// a span created with `TextSpan::object`. Children wrap like words and sit on the baseline.
+pub struct Inline {
+    pub(crate) text: Text,
+}
+
+impl Inline {
+    pub fn new(text: Text) -> Inline {
+        Inline { text }
+    }
+
+    pub fn text(&self) -> &Text {
+        &self.text
+    }
+
+    /// Children sit on the baseline of their last line of text, or on their bottom edge
+    fn object(calculated: &CalculatedElement) -> InlineObject {
+        let dimensions = calculated.rect.dimensions;
+        let baseline = calculated
+            .text
+            .as_ref()
+            .and_then(|t| t.lines.last())
+            .map_or(dimensions.height, |l| l.baseline);
+
+        InlineObject::new(dimensions, baseline)
+    }
+
+    /// Children at their largest size, used to measure the element without bounds
+    fn intrinsic_objects(element: &Element, context: &LayoutContext) -> Vec<InlineObject> {
+        let nested = context.unbounded();
+
+        element
+            .children
+            .iter()
+            .map(|child| {
+                let bounds = Rect::from_dimensions(child.max_content(&nested));
+                Inline::object(&child.calculate_in(bounds.clone(), bounds, &nested))
+            })
+            .collect()
+    }
+
+    pub fn min_content(&self, element: &Element, context: &LayoutContext) -> Dimensions {
+        let objects = Inline::intrinsic_objects(element, context);
+
+        self.text.min_content_with(element, &objects, context)
+    }
+
+    pub fn max_content(&self, element: &Element, context: &LayoutContext) -> Dimensions {
+        let objects = Inline::intrinsic_objects(element, context);
+
+        self.text.max_content_with(element, &objects, context)
+    }
+
+    pub fn height_for_width(
+        &self,
+        element: &Element,
+        width: Float,
+        context: &LayoutContext,
+    ) -> Float {
+        self.text
+            .layout_with(&Inline::intrinsic_objects(element, context), width)
+            .dimensions
+            .height
+    }
+
+    pub fn calculate(
+        &self,
+        element: &Element,
+        available_bounds: Rect,
+        outer_bounds: Rect,
+        context: &LayoutContext,
+    ) -> CalculatedElement {
+        let nested = element.nested_context(&available_bounds, &outer_bounds, context);
+
+        // Children are sized within the width the lines wrap at
+        let wrap_width = Text::wrap_width(element, &available_bounds, &outer_bounds, context);
+        let bounds = Rect::new(wrap_width, available_bounds.dimensions.height, 0., 0.);
+
+        let objects: Vec<InlineObject> = element
+            .children
+            .iter()
+            .map(|child| {
+                Inline::object(&child.calculate_in(bounds.clone(), outer_bounds.clone(), &nested))
+            })
+            .collect();
+
+        let (rect, layout) =
+            self.text
+                .fit(element, &available_bounds, &outer_bounds, &objects, context);
+
+        // Children that were cut off by an ellipsis or a line limit are left out
+        let children = element
+            .children
+            .iter()
+            .zip(&layout.objects)
+            .filter_map(|(child, placed)| {
+                let (width, height, x, y) = placed.as_ref()?.as_tuple();
+                let bounds = Rect::new(width, height, rect.position.x + x, rect.position.y + y);
+
+                Some(child.calculate_in(bounds, outer_bounds.clone(), &nested))
+            })
+            .collect();
+
+        CalculatedElement {
+            rect,
+            children,
+            text: Some(layout),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        mock::layout::FONT, text::*, Direction, ElementBuilder, Float, LayoutContext, Rect,
+    };
+
+    fn style() -> TextStyle {
+        FONT.with(|f| TextStyle::new(f.clone(), 16.))
+    }
+
+    fn width_of(content: &str) -> Float {
+        Text::new(content, style())
+            .white_space(WhiteSpace::Pre)
+            .layout(Float::INFINITY)
+            .dimensions
+            .width
+    }
+
+    fn space() -> Float {
+        let style = style();
+        let glyph = style.font.primary().glyph(' ').scaled(style.scale());
+
+        glyph.h_metrics().advance_width
+    }
+
+    fn inline(children: usize) -> ElementBuilder {
+        let text = Text::rich(vec![
+            TextSpan::new("pizza ", style()),
+            TextSpan::object(style()),
+            TextSpan::new(" krabbe ", style()),
+            TextSpan::object(style()),
+        ]);
+
+        let children = (0..children)
+            .map(|_| {
+                ElementBuilder::new()
+                    .directional(Direction::Horizontal, 0.)
+                    .sizing("Fixed:20", "Fixed:30")
+                    .build()
+            })
+            .collect();
+
+        ElementBuilder::new()
+            .inline(text)
+            .sizing("Stretch", "Collapse")
+            .children(children)
+    }
+
+    #[test]
+    fn flows_children_with_text() {
+        let element = inline(2).build();
+        let bounds = Rect::new(1000., 1000., 10., 10.);
+        let calculated = element.calculate(bounds.clone(), bounds);
+
+        let text = calculated.text.as_ref().unwrap();
+        let line = &text.lines[0];
+        let first = &calculated.children[0].rect;
+
+        assert_eq!(text.lines.len(), 1);
+        assert_eq!(calculated.children.len(), 2);
+        assert!((first.position.x - (10. + width_of("pizza") + space())).abs() < 0.001);
+        assert_eq!(first.dimensions.width, 20.);
+
+        // Children sit on the baseline, growing the line above it
+        assert_eq!(first.position.y + 30., 10. + line.baseline);
+        assert!(line.rect.dimensions.height >= 30.);
+    }
+
+    #[test]
+    fn wraps_children_like_words() {
+        let element = inline(2).build();
+        let width = width_of("pizza  krabbe") + 20.;
+        let bounds = Rect::new(width, 1000., 0., 0.);
+        let context = LayoutContext::new(bounds.dimensions);
+        let calculated = element.calculate(bounds.clone(), bounds);
+
+        let text = calculated.text.as_ref().unwrap();
+        let second = &calculated.children[1].rect;
+
+        assert_eq!(text.lines.len(), 2);
+        assert_eq!(second.position.x, 0.);
+        assert_eq!(second.position.y + 30., text.lines[1].baseline);
+
+        // The widest child or word is the narrowest the element gets
+        let min_content = element.min_content(&context);
+        assert_eq!(min_content.width, width_of("krabbe"));
+    }
+}
@@ -0,0 +1,1196 @@
+use std::{ops::Range, sync::Arc};
+
+use rusttype::{point, PositionedGlyph, ScaledGlyph};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{Dimensions, Float, Position, Rect};
+
+use super::{
+    collapse_whitespace, find_line_breaks, hangs, BidiText, Color, LineBreak, Text, TextAlign,
+    TextStyle, OBJECT_REPLACEMENT,
+};
+
+#[derive(Clone, Copy, Debug)]
+/// The size of an element that flows within a text in place of an object replacement character
+pub struct InlineObject {
+    pub dimensions: Dimensions,
+
+    /// Distance from the top of the object to the baseline it sits on
+    pub baseline: Float,
+}
+
+impl InlineObject {
+    pub fn new(dimensions: Dimensions, baseline: Float) -> InlineObject {
+        InlineObject {
+            dimensions,
+            baseline,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LaidOutGlyph {
+    /// Positioned relative to the origin of the text element
+    pub glyph: PositionedGlyph<'static>,
+
+    /// Byte index of the glyph's character in the text,
+    /// or of the first hidden character for an ellipsis
+    pub index: usize,
+
+    /// Byte range of the grapheme the character is part of, which is empty for an ellipsis
+    pub cluster: Range<usize>,
+
+    /// Index of the span the glyph's character belongs to
+    pub span: usize,
+
+    /// Identifies the font the glyph was taken from, such as for caching rasterized glyphs
+    pub font_id: usize,
+
+    /// Whether the glyph is part of a right to left run
+    pub rtl: bool,
+    pub advance: Float,
+
+    /// The advance of the glyph over the full height of its line
+    pub rect: Rect,
+}
+
+#[derive(Clone, Debug)]
+pub struct TextLine {
+    /// Range of the glyphs on this line
+    pub glyphs: Range<usize>,
+
+    /// Relative to the origin of the text element, excluding trailing whitespace
+    pub rect: Rect,
+    pub baseline: Float,
+
+    /// Whether the line is part of a right to left paragraph
+    pub rtl: bool,
+}
+
+#[derive(Clone, Debug)]
+/// A part of a line where all glyphs belong to the same span and go in the same direction
+pub struct TextRun {
+    pub span: usize,
+    pub line: usize,
+    pub rtl: bool,
+
+    /// Range of the glyphs in this run
+    pub glyphs: Range<usize>,
+
+    /// Relative to the origin of the text element, over the full height of the line
+    pub rect: Rect,
+    pub color: Color,
+    pub underline: Option<Rect>,
+    pub strikethrough: Option<Rect>,
+    pub link: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+/// The result of laying out a text, kept so the renderer can draw exactly what was measured
+pub struct TextLayout {
+    pub glyphs: Vec<LaidOutGlyph>,
+    pub lines: Vec<TextLine>,
+    pub runs: Vec<TextRun>,
+
+    /// Where every inline object was placed, or None if it was cut off
+    pub objects: Vec<Option<Rect>>,
+    pub dimensions: Dimensions,
+}
+
+impl TextLayout {
+    /// Returns the glyphs positioned relative to the given origin,
+    /// which is usually the position of the calculated element
+    pub fn positioned_glyphs(&self, origin: Position) -> Vec<PositionedGlyph<'static>> {
+        self.glyphs
+            .iter()
+            .map(|g| {
+                let position = g.glyph.position();
+
+                g.glyph
+                    .unpositioned()
+                    .clone()
+                    .positioned(point(position.x + origin.x, position.y + origin.y))
+            })
+            .collect()
+    }
+}
+
+struct ShapedChar {
+    index: usize,
+    cluster: Range<usize>,
+    span: usize,
+
+    /// Index of the font in the span's collection the glyph was taken from
+    font: usize,
+    glyph: ScaledGlyph<'static>,
+    advance: Float,
+
+    /// Kerning against the previous glyph of the same span and font, applied on the same line
+    kerning: Float,
+    line_break: LineBreak,
+    hangs: bool,
+
+    /// Tabs advance to the next multiple of their advance instead
+    tab: bool,
+
+    /// Index of the inline object the character stands in for
+    object: Option<usize>,
+}
+
+impl ShapedChar {
+    /// The advance of the character when it's placed at the given distance from the line start
+    fn advance_at(&self, x: Float) -> Float {
+        if self.tab && self.advance > 0. {
+            self.advance - x.rem_euclid(self.advance)
+        } else {
+            self.advance
+        }
+    }
+}
+
+fn shape_span(
+    content: &str,
+    offset: usize,
+    span: usize,
+    style: &TextStyle,
+    tab_size: Float,
+) -> Vec<ShapedChar> {
+    let scale = style.scale();
+    let space = style.font.primary().glyph(' ').scaled(scale);
+    let tab_interval = tab_size.max(0.) * space.h_metrics().advance_width;
+    let mut last_glyph = None;
+    let mut shaped = Vec::with_capacity(content.len());
+
+    // Fonts are chosen per grapheme, so that a cluster is never split across fonts
+    for (start, grapheme) in content.grapheme_indices(true) {
+        let font = style.font.select(grapheme);
+
+        for (index, character) in grapheme.char_indices() {
+            // Control characters are drawn as spaces rather than as missing glyphs
+            let glyph = if character.is_control() {
+                space.clone()
+            } else {
+                style.font.get(font).glyph(character).scaled(scale)
+            };
+
+            // Glyphs are only kerned against glyphs of the same font
+            let kerning = match last_glyph {
+                Some((last_font, last)) if last_font == font => {
+                    style.font.get(font).pair_kerning(scale, last, glyph.id())
+                }
+                _ => 0.,
+            };
+
+            // Control characters such as newlines take up no space, except for tabs
+            let advance = if character == '\t' {
+                tab_interval
+            } else if character.is_control() {
+                0.
+            } else {
+                glyph.h_metrics().advance_width
+            };
+
+            last_glyph = Some((font, glyph.id()));
+
+            shaped.push(ShapedChar {
+                index: offset + start + index,
+                cluster: offset + start..offset + start + grapheme.len(),
+                span,
+                font,
+                advance,
+                glyph,
+                kerning,
+                line_break: LineBreak::None,
+                hangs: hangs(character),
+                tab: character == '\t',
+                object: None,
+            });
+        }
+    }
+
+    shaped
+}
+
+fn shape(text: &Text, objects: &[InlineObject]) -> Vec<ShapedChar> {
+    let collapsed = if text.white_space.collapses() {
+        Some(collapse_whitespace(&text.content))
+    } else {
+        None
+    };
+
+    let content = collapsed.as_ref().map_or(&text.content, |(c, _)| c);
+
+    // Maps a byte index in the original content to the shaped content
+    let shaped_index = |index: usize| match &collapsed {
+        Some((_, origins)) => origins[..origins.len() - 1].partition_point(|o| *o < index),
+        None => index,
+    };
+
+    let mut shaped = Vec::with_capacity(content.len());
+    let mut offset = 0;
+
+    for (i, span) in text.spans.iter().enumerate() {
+        let start = shaped_index(offset);
+        let end = shaped_index(offset + span.content.len());
+
+        shaped.extend(shape_span(
+            &content[start..end],
+            start,
+            i,
+            &span.style,
+            text.tab_size,
+        ));
+        offset += span.content.len();
+    }
+
+    // Break opportunities are found in the joined content, so wrapping ignores span boundaries
+    for (current, line_break) in shaped.iter_mut().zip(find_line_breaks(content)) {
+        current.line_break = line_break;
+    }
+
+    // Indices refer to the original content, where a collapsed space covers the whole run
+    if let Some((_, origins)) = &collapsed {
+        for current in &mut shaped {
+            current.index = origins[current.index];
+            current.cluster = origins[current.cluster.start]..origins[current.cluster.end];
+        }
+    }
+
+    // Object replacement characters take up the width of their object, in order
+    let mut next_object = 0;
+
+    for i in 0..shaped.len() {
+        let current = &mut shaped[i];
+
+        if next_object == objects.len()
+            || !text.content[current.index..].starts_with(OBJECT_REPLACEMENT)
+        {
+            continue;
+        }
+
+        let style = &text.spans[current.span].style;
+
+        current.glyph = style.font.primary().glyph(' ').scaled(style.scale());
+        current.advance = objects[next_object].dimensions.width;
+        current.kerning = 0.;
+        current.object = Some(next_object);
+        next_object += 1;
+
+        if let Some(next) = shaped.get_mut(i + 1) {
+            next.kerning = 0.;
+        }
+    }
+
+    shaped
+}
+
+/// Greedily breaks the shaped text into lines at break opportunities, and at
+/// grapheme boundaries for words that don't fit on a line of their own.
+fn break_lines(shaped: &[ShapedChar], wraps: bool, max_width: Float) -> Vec<Range<usize>> {
+    let mut lines = Vec::new();
+
+    let mut start = 0;
+    let mut x = 0.;
+    let mut opportunity = None;
+    let mut i = 0;
+
+    while i < shaped.len() {
+        let current = &shaped[i];
+
+        if i > start {
+            match current.line_break {
+                LineBreak::Mandatory => {
+                    lines.push(start..i);
+
+                    start = i;
+                    x = 0.;
+                    opportunity = None;
+                    continue;
+                }
+                LineBreak::Allowed => opportunity = Some(i),
+                _ => {}
+            }
+        }
+
+        let kerning = if i == start { 0. } else { current.kerning };
+        let advance = current.advance_at(x + kerning);
+        let overflows = x + kerning + advance > max_width;
+
+        if wraps && overflows && !current.hangs && i > start {
+            let grapheme = || {
+                (start + 1..=i)
+                    .rev()
+                    .find(|j| shaped[*j].line_break != LineBreak::None)
+            };
+
+            // A line always keeps at least one grapheme
+            if let Some(end) = opportunity.take().or_else(grapheme) {
+                lines.push(start..end);
+
+                start = end;
+                x = 0.;
+                i = end;
+                continue;
+            }
+        }
+
+        x += kerning + advance;
+        i += 1;
+    }
+
+    lines.push(start..shaped.len());
+    lines
+}
+
+/// The width of the widest part of the text that can't be broken, such as the longest word
+pub fn min_content_width(text: &Text, objects: &[InlineObject]) -> Float {
+    let shaped = shape(text, objects);
+    let wraps = text.white_space.wraps();
+
+    let mut widest: Float = 0.;
+    let mut x = 0.;
+    let mut width = 0.;
+
+    for (i, current) in shaped.iter().enumerate() {
+        match current.line_break {
+            LineBreak::Allowed if !wraps => x += current.kerning,
+            LineBreak::Mandatory | LineBreak::Allowed => {
+                x = 0.;
+                width = 0.;
+            }
+            _ if i > 0 => x += current.kerning,
+            _ => {}
+        }
+
+        x += current.advance_at(x);
+
+        if !current.hangs {
+            width = x;
+            widest = widest.max(width);
+        }
+    }
+
+    widest.max(width)
+}
+
+/// The visible width of the shaped range, excluding hanging characters at the end
+fn visible_width(shaped: &[ShapedChar]) -> Float {
+    visible_widths(shaped).last().unwrap_or(0.)
+}
+
+/// The visible width of the shaped range up to and including every character
+fn visible_widths(shaped: &[ShapedChar]) -> impl Iterator<Item = Float> + '_ {
+    let mut x = 0.;
+    let mut width = 0.;
+
+    shaped.iter().enumerate().map(move |(i, current)| {
+        if i > 0 {
+            x += current.kerning;
+        }
+
+        x += current.advance_at(x);
+
+        if !current.hangs {
+            width = x;
+        }
+
+        width
+    })
+}
+
+/// Shortens the line until it fits the given width together with the ellipsis,
+/// returning the new end of the line
+fn truncate_line(shaped: &[ShapedChar], line: Range<usize>, max_width: Float) -> usize {
+    // Characters are only taken off the end, which doesn't move the ones before them
+    let widths: Vec<Float> = visible_widths(&shaped[line.clone()]).collect();
+    let mut end = line.end;
+
+    // Truncation happens at grapheme boundaries, keeping at least one grapheme
+    while end > line.start + 1
+        && (shaped[end - 1].hangs
+            || widths[end - line.start - 1] > max_width
+            || end < shaped.len() && shaped[end].line_break == LineBreak::None)
+    {
+        end -= 1;
+    }
+
+    end
+}
+
+/// The vertical metrics of a line, which fit the largest font of its spans
+struct LineMetrics {
+    ascent: Float,
+    descent: Float,
+    height: Float,
+}
+
+impl LineMetrics {
+    fn new<'a>(fonts: impl Iterator<Item = (&'a TextStyle, usize)>) -> LineMetrics {
+        let mut metrics = LineMetrics {
+            ascent: 0.,
+            descent: 0.,
+            height: 0.,
+        };
+
+        for (style, font) in fonts {
+            let v_metrics = style.font_v_metrics(font);
+
+            metrics.ascent = metrics.ascent.max(v_metrics.ascent);
+            metrics.descent = metrics.descent.min(v_metrics.descent);
+            metrics.height = metrics.height.max(style.calculate_font_line_height(font));
+        }
+
+        metrics.height = metrics.height.max(metrics.ascent - metrics.descent);
+        metrics
+    }
+
+    /// Grows the line so that the object fits when it sits on the baseline
+    fn fit(&mut self, object: &InlineObject) {
+        self.ascent = self.ascent.max(object.baseline);
+        self.descent = self.descent.min(object.baseline - object.dimensions.height);
+        self.height = self.height.max(self.ascent - self.descent);
+    }
+
+    /// Extra line height is distributed evenly above and below the glyphs
+    fn baseline(&self, top: Float) -> Float {
+        let leading = self.height - (self.ascent - self.descent);
+        top + leading / 2. + self.ascent
+    }
+}
+
+/// Groups the glyphs of a line into runs of the same span, with decorations
+/// positioned relative to the line's baseline
+fn collect_runs(
+    text: &Text,
+    glyphs: &[LaidOutGlyph],
+    line: &TextLine,
+    number: usize,
+    runs: &mut Vec<TextRun>,
+) {
+    let mut start = line.glyphs.start;
+
+    while start < line.glyphs.end {
+        let (span, rtl) = (glyphs[start].span, glyphs[start].rtl);
+        let end = (start..line.glyphs.end)
+            .find(|i| glyphs[*i].span != span || glyphs[*i].rtl != rtl)
+            .unwrap_or(line.glyphs.end);
+
+        // Trailing whitespace is not part of the run, like it's not part of the line
+        let (line_width, _, left, _) = line.rect.as_tuple();
+        let x = glyphs[start].rect.position.x.max(left);
+        let right = glyphs[end - 1].rect.position.x + glyphs[end - 1].advance;
+        let width = (right.min(left + line_width) - x).max(0.);
+
+        let style = &text.spans[span].style;
+        let thickness = (style.size / 14.).max(1.);
+        let decoration = |y: Float| Rect::new(width, thickness, x, y);
+
+        runs.push(TextRun {
+            span,
+            line: number,
+            rtl,
+            glyphs: start..end,
+            rect: Rect::new(width, line.rect.dimensions.height, x, line.rect.position.y),
+            color: style.color,
+            underline: if style.underline {
+                Some(decoration(line.baseline + thickness))
+            } else {
+                None
+            },
+            strikethrough: if style.strikethrough {
+                Some(decoration(
+                    line.baseline - style.v_metrics().ascent * 0.3 - thickness / 2.,
+                ))
+            } else {
+                None
+            },
+            link: text.spans[span].link.clone(),
+        });
+
+        start = end;
+    }
+}
+
+pub fn layout_text(text: &Text, objects: &[InlineObject], max_width: Float) -> TextLayout {
+    let shaped = shape(text, objects);
+    let bidi = BidiText::new(&text.content, text.direction);
+    let mut ranges = break_lines(&shaped, text.white_space.wraps(), max_width);
+
+    let truncated = match text.max_lines {
+        Some(max_lines) if ranges.len() > max_lines => {
+            ranges.truncate(max_lines);
+            true
+        }
+        _ => false,
+    };
+
+    // The last line ends with an ellipsis if lines were cut off or it doesn't fit
+    let ellipsis = text.ellipsis.as_ref().and_then(|ellipsis| {
+        let last = ranges.last()?;
+        let overflows = visible_width(&shaped[last.clone()]) > max_width;
+
+        // Styled like the end of the line it's appended to
+        let span = shaped[..last.end].last()?.span;
+
+        if truncated || overflows {
+            Some(shape_span(
+                ellipsis,
+                0,
+                span,
+                &text.spans[span].style,
+                text.tab_size,
+            ))
+        } else {
+            None
+        }
+    });
+
+    if let Some(ellipsis) = &ellipsis {
+        let last = ranges.pop().unwrap();
+        let available = max_width - visible_width(ellipsis);
+
+        ranges.push(last.start..truncate_line(&shaped, last, available));
+    }
+
+    let mut glyphs: Vec<LaidOutGlyph> = Vec::with_capacity(shaped.len());
+    let mut lines = Vec::with_capacity(ranges.len());
+    let mut runs = Vec::new();
+    let mut placed = vec![None; objects.len()];
+    let mut max_line_width: Float = 0.;
+    let mut top = 0.;
+
+    let line_count = ranges.len();
+
+    // Lines are aligned within the width they were wrapped at, or the widest line if unlimited
+    let container = if max_width.is_finite() {
+        max_width
+    } else {
+        let ellipsis_width = ellipsis.as_ref().map_or(0., |e| visible_width(e));
+
+        ranges
+            .iter()
+            .enumerate()
+            .map(|(number, range)| {
+                let width = visible_width(&shaped[range.clone()]);
+
+                if number == line_count - 1 {
+                    width + ellipsis_width
+                } else {
+                    width
+                }
+            })
+            .fold(0., Float::max)
+    };
+
+    for (number, range) in ranges.into_iter().enumerate() {
+        let is_last = number == line_count - 1;
+        let line_ellipsis = ellipsis.as_ref().filter(|_| is_last);
+
+        // Empty lines take on the metrics of the primary font of the span they're in
+        let fallback = shaped
+            .get(range.start)
+            .map_or(text.spans.len().saturating_sub(1), |s| s.span);
+
+        let fonts = shaped[range.clone()]
+            .iter()
+            .chain(line_ellipsis.into_iter().flatten())
+            .map(|s| (s.span, s.font))
+            .chain(std::iter::once((fallback, 0)).filter(|_| range.is_empty()));
+
+        let mut metrics = LineMetrics::new(
+            fonts.filter_map(|(span, font)| text.spans.get(span).map(|s| (&s.style, font))),
+        );
+
+        for object in shaped[range.clone()].iter().filter_map(|s| s.object) {
+            metrics.fit(&objects[object]);
+        }
+
+        let baseline = metrics.baseline(top);
+        let first_glyph = glyphs.len();
+
+        let byte_index = |i: usize| shaped.get(i).map_or(text.content.len(), |s| s.index);
+        let rtl_paragraph = bidi.is_rtl_paragraph(byte_index(range.start));
+
+        // The characters of the line in visual order, with their byte index, grapheme and direction
+        let mut order: Vec<(&ShapedChar, usize, Range<usize>, bool)> =
+            Vec::with_capacity(range.len());
+
+        for (run, rtl) in bidi.visual_runs(byte_index(range.start)..byte_index(range.end)) {
+            let line = &shaped[range.clone()];
+            let first = range.start + line.partition_point(|s| s.index < run.start);
+            let last = range.start + line.partition_point(|s| s.index < run.end);
+
+            if rtl {
+                // Graphemes are reversed, but the characters within them stay in order
+                let mut end = last;
+
+                for i in (first..last).rev() {
+                    if i == first || shaped[i].line_break != LineBreak::None {
+                        order.extend(
+                            shaped[i..end]
+                                .iter()
+                                .map(|s| (s, s.index, s.cluster.clone(), true)),
+                        );
+                        end = i;
+                    }
+                }
+            } else {
+                order.extend(
+                    shaped[first..last]
+                        .iter()
+                        .map(|s| (s, s.index, s.cluster.clone(), false)),
+                );
+            }
+        }
+
+        // Whitespace at the logical end of the line hangs past its visual end
+        let hanging_from = match line_ellipsis {
+            Some(_) => usize::MAX,
+            None => (range.start..range.end)
+                .rev()
+                .take_while(|i| shaped[*i].hangs)
+                .last()
+                .map_or(usize::MAX, |i| shaped[i].index),
+        };
+
+        if let Some(ellipsis) = line_ellipsis {
+            // The ellipsis is at the end of the line in the paragraph's direction
+            let index = byte_index(range.end);
+            let ellipsis = ellipsis
+                .iter()
+                .map(|s| (s, index, index..index, rtl_paragraph));
+
+            if rtl_paragraph {
+                order.splice(0..0, ellipsis.rev());
+            } else {
+                order.extend(ellipsis);
+            }
+        }
+
+        let mut positions = Vec::with_capacity(order.len());
+        let mut advances = Vec::with_capacity(order.len());
+        let mut previous: Option<&ShapedChar> = None;
+        let mut x = 0.;
+
+        // Kerned against the visually previous glyph of the same span and font
+        for (current, _, _, _) in &order {
+            if let Some(previous) = previous.filter(|p| {
+                p.span == current.span
+                    && p.font == current.font
+                    && p.object.is_none()
+                    && current.object.is_none()
+            }) {
+                let style = &text.spans[current.span].style;
+                x += style.font.get(current.font).pair_kerning(
+                    style.scale(),
+                    previous.glyph.id(),
+                    current.glyph.id(),
+                );
+            }
+
+            let advance = current.advance_at(x);
+
+            positions.push(x);
+            advances.push(advance);
+            x += advance;
+            previous = Some(current);
+        }
+
+        let visible = || {
+            order
+                .iter()
+                .zip(positions.iter().zip(&advances))
+                .filter(|((_, index, _, _), _)| *index < hanging_from)
+        };
+
+        let left = visible()
+            .map(|(_, (x, _))| *x)
+            .fold(Float::INFINITY, Float::min);
+        let right = visible()
+            .map(|(_, (x, advance))| x + advance)
+            .fold(0., Float::max);
+
+        // Right to left lines hang their trailing whitespace off the left edge instead
+        let shift = if rtl_paragraph && left.is_finite() {
+            -left
+        } else {
+            0.
+        };
+
+        let width = right + shift;
+
+        // Justified lines grow at their break opportunities, except for the first character
+        let is_opportunity = |(s, index, _, _): &(&ShapedChar, usize, Range<usize>, bool)| {
+            s.line_break == LineBreak::Allowed
+                && s.index == *index
+                && *index < hanging_from
+                && *index > byte_index(range.start)
+        };
+
+        let opportunities = order.iter().filter(|o| is_opportunity(o)).count();
+        let ends_paragraph = shaped
+            .get(range.end)
+            .is_none_or(|s| s.line_break == LineBreak::Mandatory);
+
+        let free = (container - width).max(0.);
+        let (offset, gap) = match (text.align, rtl_paragraph) {
+            (TextAlign::Justify, _) if !is_last && !ends_paragraph && opportunities > 0 => {
+                (0., free / opportunities as Float)
+            }
+            (TextAlign::Center, _) => (free / 2., 0.),
+            (TextAlign::Start, true) | (TextAlign::End, false) | (TextAlign::Justify, true) => {
+                (free, 0.)
+            }
+            _ => (0., 0.),
+        };
+
+        let mut gaps = 0.;
+
+        for ((item, x), advance) in order.iter().zip(positions).zip(advances) {
+            let (current, index, cluster, rtl) = item;
+
+            // The space is added on the side of the character that the opportunity is on
+            if is_opportunity(item) && !rtl {
+                gaps += gap;
+            }
+
+            let x = x + shift + offset + gaps;
+
+            if is_opportunity(item) && *rtl {
+                gaps += gap;
+            }
+
+            glyphs.push(LaidOutGlyph {
+                glyph: current.glyph.clone().positioned(point(x, baseline)),
+                index: *index,
+                cluster: cluster.clone(),
+                span: current.span,
+                font_id: Arc::as_ptr(text.spans[current.span].style.font.get(current.font))
+                    as usize,
+                rtl: *rtl,
+                advance,
+                rect: Rect::new(advance, metrics.height, x, top),
+            });
+
+            if let Some(object) = current.object {
+                let InlineObject {
+                    dimensions,
+                    baseline: object_baseline,
+                } = objects[object];
+
+                placed[object] = Some(Rect::new(
+                    dimensions.width,
+                    dimensions.height,
+                    x,
+                    baseline - object_baseline,
+                ));
+            }
+        }
+
+        let width = width + gaps;
+        max_line_width = max_line_width.max(width);
+
+        let line = TextLine {
+            glyphs: first_glyph..glyphs.len(),
+            rect: Rect::new(width, metrics.height, offset, top),
+            baseline,
+            rtl: rtl_paragraph,
+        };
+
+        collect_runs(text, &glyphs, &line, number, &mut runs);
+        lines.push(line);
+
+        top += metrics.height;
+    }
+
+    TextLayout {
+        glyphs,
+        dimensions: Dimensions::new(max_line_width, top),
+        lines,
+        runs,
+        objects: placed,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::{
+        mock::layout::{FALLBACK_FONT, FONT},
+        text::*,
+        Float,
+    };
+
+    fn style() -> TextStyle {
+        FONT.with(|f| TextStyle::new(f.clone(), 16.))
+    }
+
+    fn width_of(content: &str) -> Float {
+        text(content, WhiteSpace::Pre)
+            .layout(Float::INFINITY)
+            .dimensions
+            .width
+    }
+
+    fn text(content: &str, white_space: WhiteSpace) -> Text {
+        Text::new(content, style()).white_space(white_space)
+    }
+
+    #[test]
+    fn wraps_between_words() {
+        let layout = text("pizza pizza pizza", WhiteSpace::PreWrap).layout(width_of("pizza pizza"));
+
+        assert_eq!(layout.lines.len(), 2);
+        assert_eq!(layout.lines[1].glyphs.start, 12);
+        assert_eq!(layout.dimensions.width, width_of("pizza pizza"));
+        assert_eq!(
+            layout.dimensions.height,
+            style().calculate_line_height() * 2.
+        );
+    }
+
+    #[test]
+    fn positions_glyphs_on_lines() {
+        let layout = text("ab cd", WhiteSpace::PreWrap).layout(width_of("ab"));
+        let second = &layout.glyphs[layout.lines[1].glyphs.start];
+
+        assert_eq!(second.index, 3);
+        assert_eq!(second.glyph.position().x, 0.);
+        assert_eq!(second.glyph.position().y, layout.lines[1].baseline);
+    }
+
+    #[test]
+    fn breaks_long_words_at_graphemes() {
+        let layout = text("krabbe", WhiteSpace::PreWrap).layout(width_of("krabb"));
+
+        assert_eq!(layout.lines.len(), 2);
+        assert_eq!(layout.lines[1].glyphs, 5..6);
+
+        // At least one grapheme is kept on every line
+        let layout = text("ab", WhiteSpace::PreWrap).layout(1.);
+
+        assert_eq!(layout.lines.len(), 2);
+    }
+
+    #[test]
+    fn breaks_at_newlines() {
+        let layout = text("pizza\npizza", WhiteSpace::Pre).layout(Float::INFINITY);
+
+        assert_eq!(layout.lines.len(), 2);
+        assert_eq!(layout.dimensions.width, width_of("pizza"));
+    }
+
+    #[test]
+    fn does_not_break_at_non_breaking_spaces() {
+        let layout = text("für\u{a0}dich und", WhiteSpace::PreWrap).layout(width_of("für dich"));
+
+        assert_eq!(layout.lines.len(), 2);
+        assert_eq!(layout.lines[1].glyphs, 9..12);
+
+        let layout = text("für\u{a0}dich", WhiteSpace::PreWrap).layout(width_of("für"));
+
+        // Falls back to graphemes, as the whole text is a single word
+        assert_ne!(layout.lines[1].glyphs.start, 4);
+    }
+
+    #[test]
+    fn breaks_between_ideographs() {
+        let layout = text("日本語", WhiteSpace::PreWrap).layout(width_of("日本"));
+
+        assert_eq!(layout.lines.len(), 2);
+        assert_eq!(layout.lines[1].glyphs, 2..3);
+    }
+
+    #[test]
+    fn limits_line_count() {
+        let layout = text("pizza pizza pizza pizza", WhiteSpace::PreWrap)
+            .max_lines(1)
+            .layout(width_of("pizza pizza"));
+
+        assert_eq!(layout.lines.len(), 1);
+        assert_eq!(layout.glyphs.len(), 12);
+    }
+
+    #[test]
+    fn limits_to_no_lines() {
+        let layout = text("pizza pizza", WhiteSpace::PreWrap)
+            .max_lines(0)
+            .ellipsis("…")
+            .layout(width_of("pizza"));
+
+        assert!(layout.lines.is_empty());
+        assert!(layout.glyphs.is_empty());
+        assert_eq!(layout.dimensions.height, 0.);
+    }
+
+    #[test]
+    fn truncates_with_ellipsis() {
+        let max_width = width_of("pizza pizza…");
+        let layout = text("pizza pizza pizza pizza", WhiteSpace::PreWrap)
+            .max_lines(1)
+            .ellipsis("…")
+            .layout(max_width);
+
+        let last = &layout.glyphs[layout.lines[0].glyphs.clone()]
+            .last()
+            .unwrap();
+
+        assert_eq!(layout.lines.len(), 1);
+        assert_eq!(last.glyph.id(), style().font.primary().glyph('…').id());
+        assert_eq!(last.index, 11);
+        assert!(layout.dimensions.width <= max_width);
+
+        // Single lines that don't fit are truncated as well
+        let layout = text("krabbe krabbe", WhiteSpace::Pre)
+            .ellipsis("...")
+            .layout(width_of("krabbe"));
+
+        assert!(layout.dimensions.width <= width_of("krabbe"));
+        assert_eq!(
+            layout.glyphs.last().unwrap().glyph.id(),
+            style().font.primary().glyph('.').id()
+        );
+
+        // Text that fits is left alone
+        let layout = text("krabbe", WhiteSpace::Pre)
+            .ellipsis("…")
+            .layout(width_of("krabbe"));
+
+        assert_eq!(layout.glyphs.len(), 6);
+    }
+
+    #[test]
+    fn measures_min_content_width() {
+        let width = min_content_width(&text("ist die krabbe", WhiteSpace::PreWrap), &[]);
+
+        assert_eq!(width, width_of("krabbe"));
+    }
+
+    #[test]
+    fn wraps_across_spans() {
+        let layout = Text::rich(vec![
+            TextSpan::new("pizza pi", style()),
+            TextSpan::new("zza pizza", style().color((0., 0., 1., 1.)).underline()),
+        ])
+        .layout(width_of("pizza pizza"));
+
+        assert_eq!(layout.lines.len(), 2);
+        assert_eq!(layout.lines[1].glyphs.start, 12);
+
+        // The word split between spans is still wrapped as one
+        let spans: Vec<_> = layout.runs.iter().map(|r| (r.line, r.span)).collect();
+        assert_eq!(spans, vec![(0, 0), (0, 1), (1, 1)]);
+
+        let run = &layout.runs[1];
+        let first = &layout.glyphs[run.glyphs.start];
+
+        assert_eq!(run.glyphs, 8..12);
+        assert_eq!(run.rect.position.x, first.rect.position.x);
+        assert_eq!(run.color, (0., 0., 1., 1.));
+        assert!(run.underline.is_some() && layout.runs[0].underline.is_none());
+    }
+
+    #[test]
+    fn fits_lines_to_fallback_font() {
+        let fonts = FONT.with(|f| {
+            FALLBACK_FONT.with(|fallback| FontCollection::new(f.clone()).fallback(fallback.clone()))
+        });
+        let style = TextStyle::new(fonts, 16.);
+
+        let layout = Text::new("a \u{1D49C}\nb", style.clone())
+            .white_space(WhiteSpace::PreWrap)
+            .layout(Float::INFINITY);
+
+        let fallback = style.calculate_font_line_height(1);
+        assert!(fallback > style.calculate_line_height());
+
+        assert_eq!(
+            layout.glyphs[2].font_id,
+            FALLBACK_FONT.with(|f| Arc::as_ptr(f) as usize)
+        );
+        assert_eq!(layout.lines[0].rect.dimensions.height, fallback);
+        assert_eq!(layout.lines[1].rect.position.y, fallback);
+        assert_eq!(
+            layout.lines[1].rect.dimensions.height,
+            style.calculate_line_height()
+        );
+    }
+
+    #[test]
+    fn fits_lines_to_largest_span() {
+        let large = style();
+        let large = TextStyle::new(large.font.clone(), 32.);
+
+        let layout = Text::rich(vec![
+            TextSpan::new("krabbe ", style()),
+            TextSpan::new("pizza", large.clone()).link("pizza"),
+            TextSpan::new("\nkrabbe", style()),
+        ])
+        .white_space(WhiteSpace::PreWrap)
+        .layout(Float::INFINITY);
+
+        let first = &layout.lines[0];
+
+        assert_eq!(first.rect.dimensions.height, large.calculate_line_height());
+        assert_eq!(first.baseline, large.v_metrics().ascent);
+        assert_eq!(layout.runs[1].link.as_deref(), Some("pizza"));
+
+        // Glyphs of different sizes share the baseline
+        for glyph in &layout.glyphs[first.glyphs.clone()] {
+            assert_eq!(glyph.glyph.position().y, first.baseline);
+        }
+
+        assert_eq!(
+            layout.dimensions.height,
+            large.calculate_line_height() + style().calculate_line_height()
+        );
+    }
+
+    fn indices(layout: &TextLayout) -> Vec<usize> {
+        layout.glyphs.iter().map(|g| g.index).collect()
+    }
+
+    #[test]
+    fn reorders_right_to_left_runs() {
+        let layout = text("ab \u{5d0}\u{5d1} cd", WhiteSpace::Pre).layout(Float::INFINITY);
+
+        assert_eq!(indices(&layout), vec![0, 1, 2, 5, 3, 7, 8, 9]);
+        assert!(!layout.lines[0].rtl);
+        assert_eq!(
+            layout.runs.iter().map(|r| r.rtl).collect::<Vec<_>>(),
+            vec![false, true, false]
+        );
+
+        // Glyphs are still placed from left to right
+        for pair in layout.glyphs.windows(2) {
+            assert!(pair[0].glyph.position().x < pair[1].glyph.position().x);
+        }
+    }
+
+    #[test]
+    fn detects_paragraph_direction() {
+        let layout = text("\u{5d0}\u{5d1} ab", WhiteSpace::Pre).layout(Float::INFINITY);
+
+        assert!(layout.lines[0].rtl);
+        assert_eq!(indices(&layout), vec![5, 6, 4, 2, 0]);
+
+        let layout = text("\u{5d0}\u{5d1} ab", WhiteSpace::Pre)
+            .direction(TextDirection::LeftToRight)
+            .layout(Float::INFINITY);
+
+        assert!(!layout.lines[0].rtl);
+        assert_eq!(indices(&layout), vec![2, 0, 4, 5, 6]);
+    }
+
+    #[test]
+    fn hangs_whitespace_at_the_start_of_right_to_left_lines() {
+        let layout =
+            text("\u{5d0}\u{5d1} \u{5d2}", WhiteSpace::PreWrap).layout(width_of("\u{5d0}\u{5d1}"));
+        let first = &layout.glyphs[layout.lines[0].glyphs.start];
+
+        assert_eq!(layout.lines.len(), 2);
+        assert_eq!(first.index, 4);
+        assert!(first.glyph.position().x < 0.);
+
+        let width = layout.lines[0].rect.dimensions.width;
+        assert!((width - width_of("\u{5d0}\u{5d1}")).abs() < 0.001);
+    }
+
+    #[test]
+    fn aligns_lines() {
+        let max_width = width_of("pizza pizza");
+        let aligned = |align| {
+            text("pizza pizza krabbe", WhiteSpace::PreWrap)
+                .align(align)
+                .layout(max_width)
+        };
+
+        let layout = aligned(TextAlign::Center);
+        let line = &layout.lines[1];
+
+        assert_eq!(line.rect.position.x, (max_width - width_of("krabbe")) / 2.);
+        assert_eq!(
+            layout.glyphs[line.glyphs.start].glyph.position().x,
+            line.rect.position.x
+        );
+
+        let layout = aligned(TextAlign::End);
+        assert_eq!(
+            layout.lines[1].rect.position.x,
+            max_width - width_of("krabbe")
+        );
+
+        // Right to left paragraphs start on the right
+        let layout = text("\u{5d0}\u{5d1}", WhiteSpace::PreWrap).layout(100.);
+        let x = layout.lines[0].rect.position.x;
+
+        assert!((x - (100. - width_of("\u{5d0}\u{5d1}"))).abs() < 0.001);
+    }
+
+    #[test]
+    fn justifies_all_lines_but_the_last() {
+        let max_width = width_of("pizzapizzapizza");
+        let layout = text("a b c pizzapizzapizza d e", WhiteSpace::PreWrap)
+            .align(TextAlign::Justify)
+            .layout(max_width);
+
+        let first = &layout.lines[0];
+        let c = &layout.glyphs[first.glyphs.start + 4];
+
+        assert_eq!(layout.lines.len(), 3);
+        assert!((c.glyph.position().x + c.advance - max_width).abs() < 0.001);
+        assert!((first.rect.dimensions.width - max_width).abs() < 0.001);
+        assert_eq!(layout.lines[2].rect.dimensions.width, width_of("d e"));
+    }
+
+    #[test]
+    fn collapses_whitespace() {
+        let layout = text("  pizza \n\t krabbe ", WhiteSpace::Normal).layout(Float::INFINITY);
+
+        assert_eq!(layout.lines.len(), 1);
+        assert_eq!(layout.dimensions.width, width_of("pizza krabbe"));
+
+        // The collapsed space covers the whole run of whitespace it replaced
+        assert_eq!(layout.glyphs[0].index, 2);
+        assert_eq!(layout.glyphs[5].cluster, 7..11);
+        assert_eq!(layout.glyphs[6].index, 11);
+
+        let layout = text("pizza\n\nkrabbe", WhiteSpace::NoWrap).layout(width_of("pizza"));
+        assert_eq!(layout.lines.len(), 1);
+    }
+
+    #[test]
+    fn preserves_whitespace() {
+        let layout = text("  pizza  \nkrabbe", WhiteSpace::Pre).layout(width_of("pizza"));
+
+        assert_eq!(layout.lines.len(), 2);
+        assert_eq!(layout.lines[0].rect.dimensions.width, width_of("  pizza"));
+
+        let layout = text("  pizza  krabbe", WhiteSpace::PreWrap).layout(width_of("  krabbe"));
+
+        assert_eq!(layout.lines.len(), 2);
+        assert_eq!(layout.lines[0].rect.dimensions.width, width_of("  pizza"));
+    }
+
+    #[test]
+    fn advances_tabs_to_stops() {
+        let space = style().font.primary().glyph(' ');
+        let space = space.scaled(style().scale()).h_metrics().advance_width;
+        let tab = |content: &str, tab_size: Float| {
+            text(content, WhiteSpace::Pre)
+                .tab_size(tab_size)
+                .layout(Float::INFINITY)
+        };
+
+        let layout = tab("a\tb", 4.);
+        let b = &layout.glyphs[2];
+
+        assert!((b.glyph.position().x - space * 4.).abs() < 0.001);
+        assert!((layout.glyphs[1].advance - (space * 4. - width_of("a"))).abs() < 0.001);
+
+        // A tab right at a stop advances to the next one
+        let layout = tab("\t\tb", 2.);
+        assert!((layout.glyphs[2].glyph.position().x - space * 4.).abs() < 0.001);
+    }
+}
@@ -0,0 +1,58 @@
+use unicode_linebreak::{break_property, linebreaks, BreakClass, BreakOpportunity};
+use unicode_segmentation::UnicodeSegmentation;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// The kind of line break allowed before a character
+pub enum LineBreak {
+    /// The line must end here, such as after a newline
+    Mandatory,
+    /// A line break opportunity as defined by UAX #14
+    Allowed,
+    /// Only a grapheme boundary, used as a last resort for words longer than the line
+    Grapheme,
+    None,
+}
+
+/// Finds the kind of line break allowed before every character of the text
+pub fn find_line_breaks(content: &str) -> Vec<LineBreak> {
+    let mut opportunities = linebreaks(content).peekable();
+    let mut graphemes = content.grapheme_indices(true).map(|(i, _)| i).peekable();
+
+    content
+        .char_indices()
+        .map(|(index, _)| {
+            while opportunities.peek().is_some_and(|(i, _)| *i < index) {
+                opportunities.next();
+            }
+
+            while graphemes.peek().is_some_and(|i| *i < index) {
+                graphemes.next();
+            }
+
+            // There's never a break before the first character
+            if index == 0 {
+                return LineBreak::None;
+            }
+
+            match opportunities.peek() {
+                Some((i, BreakOpportunity::Mandatory)) if *i == index => LineBreak::Mandatory,
+                Some((i, BreakOpportunity::Allowed)) if *i == index => LineBreak::Allowed,
+                _ if graphemes.peek() == Some(&index) => LineBreak::Grapheme,
+                _ => LineBreak::None,
+            }
+        })
+        .collect()
+}
+
+/// Whether the character is allowed to hang past the end of a line, such as spaces
+pub fn hangs(character: char) -> bool {
+    matches!(
+        break_property(character as u32),
+        BreakClass::Space
+            | BreakClass::Mandatory
+            | BreakClass::CarriageReturn
+            | BreakClass::LineFeed
+            | BreakClass::NextLine
+            | BreakClass::ZeroWidthSpace
+    )
+}
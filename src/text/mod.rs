@@ -0,0 +1,24 @@
+mod bidi;
+mod cache;
+mod caret;
+mod element;
+mod font;
+mod inline;
+mod layout;
+mod linebreak;
+mod registry;
+mod span;
+mod style;
+mod whitespace;
+
+pub use bidi::*;
+pub use cache::*;
+pub use element::*;
+pub use font::*;
+pub use inline::*;
+pub use layout::*;
+pub use linebreak::*;
+pub use registry::*;
+pub use span::*;
+pub use style::*;
+pub use whitespace::*;
@@ -0,0 +1,320 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use rusttype::Font;
+
+use super::{FontCollection, FontHandle};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FontStyle {
+    Normal,
+    Italic,
+    Oblique,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// Weight of a font from 100 to 900, where 400 is regular and 700 is bold
+pub struct FontWeight(pub u16);
+
+impl FontWeight {
+    pub const THIN: FontWeight = FontWeight(100);
+    pub const LIGHT: FontWeight = FontWeight(300);
+    pub const NORMAL: FontWeight = FontWeight(400);
+    pub const MEDIUM: FontWeight = FontWeight(500);
+    pub const BOLD: FontWeight = FontWeight(700);
+    pub const BLACK: FontWeight = FontWeight(900);
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+/// Describes a font to request from a registry
+pub struct FontDescriptor {
+    pub family: String,
+    pub weight: FontWeight,
+    pub style: FontStyle,
+}
+
+impl FontDescriptor {
+    pub fn new(family: impl ToString) -> FontDescriptor {
+        FontDescriptor {
+            family: family.to_string(),
+            weight: FontWeight::NORMAL,
+            style: FontStyle::Normal,
+        }
+    }
+
+    pub fn weight(mut self, weight: FontWeight) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    pub fn style(mut self, style: FontStyle) -> Self {
+        self.style = style;
+        self
+    }
+}
+
+#[derive(Debug)]
+pub enum FontError {
+    Io(PathBuf, std::io::Error),
+    InvalidFont(PathBuf),
+    NoFamilyName(PathBuf),
+    NotFound(FontDescriptor),
+}
+
+use FontError::*;
+
+impl fmt::Display for FontError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Io(path, error) => write!(f, "{}: {}", path.display(), error),
+            InvalidFont(path) => write!(
+                f,
+                "{}: not a valid TrueType or OpenType font",
+                path.display()
+            ),
+            NoFamilyName(path) => write!(f, "{}: the font has no family name", path.display()),
+            NotFound(descriptor) => write!(
+                f,
+                "no font found for '{}' at weight {} ({:?})",
+                descriptor.family, descriptor.weight.0, descriptor.style
+            ),
+        }
+    }
+}
+
+impl Error for FontError {}
+
+#[derive(Debug, Default)]
+/// The fonts that were loaded from a directory, and the files that couldn't be loaded
+pub struct DirectoryFonts {
+    pub loaded: Vec<FontDescriptor>,
+    pub failed: Vec<FontError>,
+}
+
+#[derive(Default)]
+/// Fonts indexed by family, weight and style, sharing one handle per loaded file
+pub struct FontRegistry {
+    families: HashMap<String, Vec<(FontWeight, FontStyle, FontHandle)>>,
+}
+
+impl FontRegistry {
+    pub fn new() -> FontRegistry {
+        FontRegistry::default()
+    }
+
+    /// Loads every TrueType and OpenType font in the directory. Files that can't be loaded
+    /// are skipped and returned with the fonts that were, so only an unreadable directory fails.
+    pub fn load_directory(&mut self, path: impl AsRef<Path>) -> Result<DirectoryFonts, FontError> {
+        let path = path.as_ref();
+        let entries = std::fs::read_dir(path).map_err(|e| Io(path.to_owned(), e))?;
+
+        let mut fonts = DirectoryFonts::default();
+        let mut paths = Vec::new();
+
+        for entry in entries {
+            match entry {
+                Ok(entry) => paths.push(entry.path()),
+                Err(error) => fonts.failed.push(Io(path.to_owned(), error)),
+            }
+        }
+
+        paths.retain(|p| {
+            let extension = p.extension().and_then(|e| e.to_str()).unwrap_or("");
+            matches!(extension.to_lowercase().as_str(), "ttf" | "otf")
+        });
+
+        // Sorted so that the same directory is always loaded in the same order
+        paths.sort();
+
+        for path in &paths {
+            match self.load_file(path) {
+                Ok(descriptor) => fonts.loaded.push(descriptor),
+                Err(error) => fonts.failed.push(error),
+            }
+        }
+
+        Ok(fonts)
+    }
+
+    pub fn load_file(&mut self, path: impl AsRef<Path>) -> Result<FontDescriptor, FontError> {
+        let path = path.as_ref();
+        let data = std::fs::read(path).map_err(|e| Io(path.to_owned(), e))?;
+
+        self.load_data(data, path)
+    }
+
+    /// Adds a font from memory, with the path only used to describe errors
+    pub fn load_data(
+        &mut self,
+        data: Vec<u8>,
+        path: impl AsRef<Path>,
+    ) -> Result<FontDescriptor, FontError> {
+        let path = path.as_ref();
+
+        let descriptor = {
+            let face = ttf_parser::Font::from_data(&data, 0).ok_or(InvalidFont(path.to_owned()))?;
+            let family = face.family_name().ok_or(NoFamilyName(path.to_owned()))?;
+
+            let style = if face.is_italic() {
+                FontStyle::Italic
+            } else if face.is_oblique() {
+                FontStyle::Oblique
+            } else {
+                FontStyle::Normal
+            };
+
+            FontDescriptor::new(family)
+                .weight(FontWeight(face.weight().to_number()))
+                .style(style)
+        };
+
+        let font = Font::try_from_vec(data).ok_or(InvalidFont(path.to_owned()))?;
+
+        self.families
+            .entry(descriptor.family.to_lowercase())
+            .or_default()
+            .push((descriptor.weight, descriptor.style, Arc::new(font)));
+
+        Ok(descriptor)
+    }
+
+    /// Returns the font of the family that matches the descriptor best. Italic falls back
+    /// to oblique and the other way around, then the closest weight is chosen, preferring
+    /// bolder fonts for regular and heavier weights, and lighter fonts otherwise.
+    pub fn get(&self, descriptor: &FontDescriptor) -> Result<FontHandle, FontError> {
+        let fonts = self
+            .families
+            .get(&descriptor.family.to_lowercase())
+            .ok_or_else(|| NotFound(descriptor.clone()))?;
+
+        let style_rank = |style: FontStyle| match (descriptor.style, style) {
+            (a, b) if a == b => 0,
+            (FontStyle::Normal, _) | (_, FontStyle::Normal) => 2,
+            _ => 1,
+        };
+
+        let weight_rank = |weight: FontWeight| {
+            let difference = (weight.0 as i32 - descriptor.weight.0 as i32).abs();
+            let lighter = weight < descriptor.weight;
+            let prefers_bolder = descriptor.weight >= FontWeight::NORMAL;
+
+            (difference, lighter == prefers_bolder)
+        };
+
+        fonts
+            .iter()
+            .min_by_key(|(weight, style, _)| (style_rank(*style), weight_rank(*weight)))
+            .map(|(_, _, font)| font.clone())
+            .ok_or_else(|| NotFound(descriptor.clone()))
+    }
+
+    /// Creates a collection from the descriptors in order of preference,
+    /// skipping the ones that are not found as long as at least one is
+    pub fn collection(&self, descriptors: &[FontDescriptor]) -> Result<FontCollection, FontError> {
+        let mut fonts = descriptors.iter().filter_map(|d| self.get(d).ok());
+
+        let primary = fonts.next().ok_or_else(|| {
+            NotFound(
+                descriptors
+                    .first()
+                    .cloned()
+                    .unwrap_or_else(|| FontDescriptor::new("")),
+            )
+        })?;
+
+        Ok(
+            fonts.fold(FontCollection::new(primary), |collection, font| {
+                collection.fallback(font)
+            }),
+        )
+    }
+
+    pub fn families(&self) -> impl Iterator<Item = &String> {
+        self.families.keys()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::text::*;
+
+    fn registry() -> FontRegistry {
+        let mut registry = FontRegistry::new();
+        registry.load_directory("./assets/fonts").unwrap();
+        registry
+    }
+
+    #[test]
+    fn indexes_fonts_by_descriptor() {
+        let registry = registry();
+        let regular = registry.get(&FontDescriptor::new("DejaVu Sans")).unwrap();
+
+        // Lookups are case insensitive and fall back to the closest weight and style
+        let bold = FontDescriptor::new("dejavu sans")
+            .weight(FontWeight::BOLD)
+            .style(FontStyle::Italic);
+
+        assert!(Arc::ptr_eq(&regular, &registry.get(&bold).unwrap()));
+        assert!(registry.families().any(|f| f == "dejavu sans"));
+    }
+
+    #[test]
+    fn returns_errors_for_missing_fonts() {
+        let mut registry = registry();
+
+        assert!(matches!(
+            registry.get(&FontDescriptor::new("Comic Sans")),
+            Err(FontError::NotFound(_))
+        ));
+        assert!(matches!(
+            registry.load_file("./assets/fonts/missing.ttf"),
+            Err(FontError::Io(_, _))
+        ));
+        assert!(matches!(
+            registry.load_data(vec![0; 16], "broken.ttf"),
+            Err(FontError::InvalidFont(_))
+        ));
+
+        let collection = registry
+            .collection(&[
+                FontDescriptor::new("Comic Sans"),
+                FontDescriptor::new("DejaVu Sans"),
+            ])
+            .unwrap();
+
+        assert_eq!(collection.fonts().len(), 1);
+    }
+
+    #[test]
+    fn skips_fonts_that_fail_to_load() {
+        let directory = std::env::temp_dir().join(format!("rust-gui-{}-fonts", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+
+        std::fs::copy("./assets/fonts/DejaVuSans.ttf", directory.join("a.ttf")).unwrap();
+        std::fs::write(directory.join("b.otf"), [0; 16]).unwrap();
+        std::fs::write(directory.join("c.txt"), "not a font").unwrap();
+
+        let mut registry = FontRegistry::new();
+        let fonts = registry.load_directory(&directory).unwrap();
+
+        assert_eq!(fonts.loaded, vec![FontDescriptor::new("DejaVu Sans")]);
+        assert_eq!(fonts.failed.len(), 1);
+        assert_eq!(
+            fonts.failed[0].to_string(),
+            format!(
+                "{}: not a valid TrueType or OpenType font",
+                directory.join("b.otf").display()
+            )
+        );
+        assert!(registry.get(&FontDescriptor::new("DejaVu Sans")).is_ok());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,44 @@
+use super::{TextStyle, OBJECT_REPLACEMENT};
+
+#[derive(Clone, Debug)]
+/// A run of content within a text that shares one style
+pub struct TextSpan {
+    pub(crate) content: String,
+    pub(crate) style: TextStyle,
+
+    /// Where the span leads to if it's an inline link
+    pub(crate) link: Option<String>,
+}
+
+impl TextSpan {
+    pub fn new(content: impl ToString, style: TextStyle) -> TextSpan {
+        TextSpan {
+            content: content.to_string(),
+            style,
+            link: None,
+        }
+    }
+
+    /// A placeholder for the next inline child of an inline element, styled like
+    /// the text around it so that the line keeps its metrics
+    pub fn object(style: TextStyle) -> TextSpan {
+        TextSpan::new(OBJECT_REPLACEMENT, style)
+    }
+
+    pub fn link(mut self, target: impl ToString) -> Self {
+        self.link = Some(target.to_string());
+        self
+    }
+
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    pub fn style(&self) -> &TextStyle {
+        &self.style
+    }
+
+    pub fn target(&self) -> Option<&String> {
+        self.link.as_ref()
+    }
+}
@@ -0,0 +1,92 @@
+use rusttype::{Scale, VMetrics};
+
+use crate::Float;
+
+use super::FontCollection;
+
+/// RGBA, with every channel in the range 0 to 1
+pub type Color = (Float, Float, Float, Float);
+
+#[derive(Clone)]
+pub struct TextStyle {
+    pub font: FontCollection,
+
+    /// Font size in pixels
+    pub size: Float,
+    pub color: Color,
+
+    /// Multiplier of the font's own line height (by default 1)
+    pub line_height: Float,
+    pub underline: bool,
+    pub strikethrough: bool,
+}
+
+impl TextStyle {
+    pub fn new(font: impl Into<FontCollection>, size: Float) -> TextStyle {
+        TextStyle {
+            font: font.into(),
+            size,
+            color: (1.0, 1.0, 1.0, 1.0),
+            line_height: 1.0,
+            underline: false,
+            strikethrough: false,
+        }
+    }
+
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn line_height(mut self, line_height: Float) -> Self {
+        self.line_height = line_height;
+        self
+    }
+
+    pub fn underline(mut self) -> Self {
+        self.underline = true;
+        self
+    }
+
+    pub fn strikethrough(mut self) -> Self {
+        self.strikethrough = true;
+        self
+    }
+
+    pub fn scale(&self) -> Scale {
+        Scale::uniform(self.size)
+    }
+
+    /// The vertical metrics of the primary font
+    pub fn v_metrics(&self) -> VMetrics {
+        self.font_v_metrics(0)
+    }
+
+    pub fn font_v_metrics(&self, font: usize) -> VMetrics {
+        self.font.get(font).v_metrics(self.scale())
+    }
+
+    /// The distance between the baselines of two lines
+    pub fn calculate_line_height(&self) -> Float {
+        self.calculate_font_line_height(0)
+    }
+
+    /// The line height of one of the fonts in the collection, as lines use the tallest font on them
+    pub fn calculate_font_line_height(&self, font: usize) -> Float {
+        let v_metrics = self.font_v_metrics(font);
+        (v_metrics.ascent - v_metrics.descent + v_metrics.line_gap) * self.line_height
+    }
+}
+
+impl std::fmt::Debug for TextStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TextStyle")
+            .field("font", &self.font)
+            .field("size", &self.size)
+            .field("color", &self.color)
+            .field("line_height", &self.line_height)
+            .field("underline", &self.underline)
+            .field("strikethrough", &self.strikethrough)
+            .finish()
+    }
+}
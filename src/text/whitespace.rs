@@ -0,0 +1,73 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// How whitespace in a text is collapsed and whether lines wrap, like the CSS property
+pub enum WhiteSpace {
+    /// Runs of whitespace, including newlines, are collapsed into single spaces and lines wrap
+    Normal,
+
+    /// Whitespace and newlines are preserved, and lines only break at newlines
+    Pre,
+
+    /// Whitespace and newlines are preserved, and lines wrap
+    PreWrap,
+
+    /// Whitespace is collapsed like normal, but lines never wrap
+    NoWrap,
+}
+
+impl WhiteSpace {
+    pub fn collapses(&self) -> bool {
+        matches!(self, WhiteSpace::Normal | WhiteSpace::NoWrap)
+    }
+
+    pub fn wraps(&self) -> bool {
+        matches!(self, WhiteSpace::Normal | WhiteSpace::PreWrap)
+    }
+}
+
+fn is_collapsible(character: char) -> bool {
+    matches!(character, ' ' | '\t' | '\n' | '\r' | '\u{c}')
+}
+
+/// Collapses runs of whitespace into single spaces and removes it at the start and end.
+/// Returns the collapsed content with the byte index in the original content of every byte,
+/// followed by the end of the original content.
+pub fn collapse_whitespace(content: &str) -> (String, Vec<usize>) {
+    let mut collapsed = String::with_capacity(content.len());
+    let mut origins = Vec::with_capacity(content.len() + 1);
+    let mut run = None;
+
+    for (index, character) in content.char_indices() {
+        if is_collapsible(character) {
+            run = run.or(Some(index));
+            continue;
+        }
+
+        // The space takes the place of the first character in the run
+        if let (Some(start), false) = (run.take(), collapsed.is_empty()) {
+            collapsed.push(' ');
+            origins.push(start);
+        }
+
+        collapsed.push(character);
+        origins.extend(index..index + character.len_utf8());
+    }
+
+    origins.push(content.len());
+    (collapsed, origins)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn collapses_whitespace() {
+        let (collapsed, origins) = collapse_whitespace("  pizza \t\n krabbe\n");
+
+        assert_eq!(collapsed, "pizza krabbe");
+        assert_eq!(origins[0], 2);
+        assert_eq!(origins[5], 7);
+        assert_eq!(origins[6], 11);
+        assert_eq!(origins.last(), Some(&18));
+    }
+}